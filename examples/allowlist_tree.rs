@@ -0,0 +1,55 @@
+// Builds the tree of a Merkle allowlist from a file with one account id per line. Prints the
+// root to pass as `{"MerkleRoot": "<root>"}` in the drop config and the proof each account
+// passes when claiming:
+//
+//     cargo run --example allowlist_tree -- accounts.txt > allowlist.json
+use std::error::Error;
+use std::fs;
+
+use near_drops::merkle;
+use near_sdk::json_types::Base58CryptoHash;
+use near_sdk::serde_json::json;
+use near_sdk::AccountId;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let path = std::env::args()
+        .nth(1)
+        .ok_or("Usage: allowlist_tree <accounts.txt>")?;
+    let accounts = fs::read_to_string(path)?;
+
+    let mut account_ids = Vec::new();
+    for line in accounts.lines().map(str::trim) {
+        if line.is_empty() {
+            continue;
+        }
+        account_ids.push(line.parse::<AccountId>()?);
+    }
+    if account_ids.is_empty() {
+        return Err("The allowlist needs at least one account".into());
+    }
+
+    let leaves: Vec<_> = account_ids.iter().map(merkle::allowlist_leaf).collect();
+    let levels = merkle::build_sorted_tree(&leaves);
+    let root = levels[levels.len() - 1][0];
+
+    let proofs: Vec<_> = account_ids
+        .iter()
+        .enumerate()
+        .map(|(index, account_id)| {
+            let proof: Vec<Base58CryptoHash> = merkle::sorted_proof(&levels, index)
+                .into_iter()
+                .map(Base58CryptoHash::from)
+                .collect();
+
+            json!({"account_id": account_id, "proof": proof})
+        })
+        .collect();
+
+    let tree = json!({
+        "root": Base58CryptoHash::from(root),
+        "proofs": proofs,
+    });
+    println!("{}", near_sdk::serde_json::to_string_pretty(&tree)?);
+
+    Ok(())
+}
@@ -0,0 +1,76 @@
+// Builds the tree of a Merkle airdrop from a CSV of `account_id,amount` lines, with amounts
+// in yoctoNEAR or in the smallest unit of the FT. Prints the root to pass to
+// `create_merkle_drop` and the proof each recipient passes to `claim_merkle`:
+//
+//     cargo run --example merkle_tree -- recipients.csv > tree.json
+use std::error::Error;
+use std::fs;
+
+use near_drops::merkle;
+use near_sdk::json_types::Base58CryptoHash;
+use near_sdk::serde_json::json;
+use near_sdk::{AccountId, NearToken};
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let path = std::env::args()
+        .nth(1)
+        .ok_or("Usage: merkle_tree <recipients.csv>")?;
+    let csv = fs::read_to_string(path)?;
+
+    let mut recipients = Vec::new();
+    for line in csv.lines().map(str::trim) {
+        if line.is_empty() || line == "account_id,amount" {
+            continue;
+        }
+
+        let (account_id, amount) = line
+            .split_once(',')
+            .ok_or_else(|| format!("Expected `account_id,amount`, got `{line}`"))?;
+        let account_id: AccountId = account_id.trim().parse()?;
+        let amount = NearToken::from_yoctonear(amount.trim().parse()?);
+        recipients.push((account_id, amount));
+    }
+
+    let leaves: Vec<_> = recipients
+        .iter()
+        .enumerate()
+        .map(|(index, (account_id, amount))| {
+            merkle::airdrop_leaf(index as u32, account_id, *amount)
+        })
+        .collect();
+    let levels = merkle::build_tree(&leaves);
+    let root = levels[levels.len() - 1][0];
+
+    let total = recipients
+        .iter()
+        .fold(NearToken::from_yoctonear(0), |total, (_, amount)| {
+            total.saturating_add(*amount)
+        });
+
+    let claims: Vec<_> = recipients
+        .iter()
+        .enumerate()
+        .map(|(index, (account_id, amount))| {
+            let hashes: Vec<Base58CryptoHash> = merkle::proof(&levels, index)
+                .into_iter()
+                .map(Base58CryptoHash::from)
+                .collect();
+
+            json!({
+                "account_id": account_id,
+                "amount": amount,
+                "proof": {"index": index, "hashes": hashes},
+            })
+        })
+        .collect();
+
+    let tree = json!({
+        "root": Base58CryptoHash::from(root),
+        "num_leaves": recipients.len(),
+        "total": total,
+        "claims": claims,
+    });
+    println!("{}", near_sdk::serde_json::to_string_pretty(&tree)?);
+
+    Ok(())
+}
@@ -0,0 +1,111 @@
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::{env, log, near, AccountId};
+
+use crate::errors::DropError;
+use crate::{Contract, ContractExt};
+
+// Roles the owner can hand out. The owner implicitly holds all of them
+#[derive(Clone, Copy, Debug, PartialEq, Eq, BorshDeserialize, BorshSerialize)]
+#[near(serializers = [json])]
+#[borsh(crate = "near_sdk::borsh")]
+pub enum Role {
+    Pauser,     // Can pause and unpause the contract
+    FeeManager, // Can configure protocol fees
+    Upgrader,   // Can deploy new code to the contract
+}
+
+#[near]
+impl Contract {
+    pub fn get_owner(&self) -> AccountId {
+        self.owner.clone()
+    }
+
+    #[handle_result]
+    pub fn set_owner(&mut self, new_owner: AccountId) -> Result<(), DropError> {
+        self.check_owner()?;
+        log!("Owner changed from {} to {}", self.owner, new_owner);
+        self.owner = new_owner;
+        Ok(())
+    }
+
+    #[handle_result]
+    pub fn grant_role(&mut self, account_id: AccountId, role: Role) -> Result<(), DropError> {
+        self.check_owner()?;
+
+        let mut roles = self.roles.get(&account_id).cloned().unwrap_or_default();
+        if !roles.contains(&role) {
+            roles.push(role);
+            self.roles.insert(account_id, roles);
+        }
+        Ok(())
+    }
+
+    #[handle_result]
+    pub fn revoke_role(&mut self, account_id: AccountId, role: Role) -> Result<(), DropError> {
+        self.check_owner()?;
+
+        if let Some(roles) = self.roles.get_mut(&account_id) {
+            roles.retain(|granted| granted != &role);
+            if roles.is_empty() {
+                self.roles.remove(&account_id);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn has_role(&self, account_id: AccountId, role: Role) -> bool {
+        account_id == self.owner
+            || self
+                .roles
+                .get(&account_id)
+                .is_some_and(|roles| roles.contains(&role))
+    }
+
+    pub fn get_roles(&self, account_id: AccountId) -> Vec<Role> {
+        self.roles.get(&account_id).cloned().unwrap_or_default()
+    }
+
+    // Stops the creation and claiming of drops until `unpause` is called
+    #[handle_result]
+    pub fn pause(&mut self) -> Result<(), DropError> {
+        self.check_role(Role::Pauser)?;
+        self.paused = true;
+        log!("Contract paused by {}", env::predecessor_account_id());
+        Ok(())
+    }
+
+    #[handle_result]
+    pub fn unpause(&mut self) -> Result<(), DropError> {
+        self.check_role(Role::Pauser)?;
+        self.paused = false;
+        log!("Contract unpaused by {}", env::predecessor_account_id());
+        Ok(())
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+}
+
+impl Contract {
+    pub(crate) fn check_owner(&self) -> Result<(), DropError> {
+        if env::predecessor_account_id() != self.owner {
+            return Err(DropError::NotOwner);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn check_role(&self, role: Role) -> Result<(), DropError> {
+        if !self.has_role(env::predecessor_account_id(), role) {
+            return Err(DropError::MissingRole(role));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn check_not_paused(&self) -> Result<(), DropError> {
+        if self.paused {
+            return Err(DropError::ContractPaused);
+        }
+        Ok(())
+    }
+}
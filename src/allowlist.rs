@@ -0,0 +1,28 @@
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::Base58CryptoHash;
+use near_sdk::{near, AccountId, CryptoHash};
+
+use crate::merkle;
+
+// Recipients allowed to claim a drop
+#[derive(Clone, Debug, BorshDeserialize, BorshSerialize)]
+#[near(serializers = [json])]
+#[borsh(crate = "near_sdk::borsh")]
+pub enum Allowlist {
+    Accounts(Vec<AccountId>),
+    // Root of a Merkle tree whose leaves are the account ids, claims then come with a proof
+    MerkleRoot(Base58CryptoHash),
+}
+
+impl Allowlist {
+    pub fn is_listed(&self, account_id: &AccountId, proof: &[CryptoHash]) -> bool {
+        match self {
+            Allowlist::Accounts(accounts) => accounts.contains(account_id),
+            Allowlist::MerkleRoot(root) => merkle::verify(
+                proof,
+                &CryptoHash::from(*root),
+                merkle::allowlist_leaf(account_id),
+            ),
+        }
+    }
+}
@@ -0,0 +1,281 @@
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::{
+    env, log, near, AccountId, GasWeight, NearToken, Promise, PromiseOrValue, PublicKey,
+};
+
+use crate::constants::*;
+use crate::drop_types::{Drop, DropConfig, Getters, Setters};
+use crate::errors::DropError;
+use crate::ft_drop::{self, FTDrop};
+use crate::{near_drop, storage_cost, Contract, ContractExt};
+
+// A drop getting its keys in batches, which cannot be claimed until it is finalized
+#[derive(Clone, Debug, BorshDeserialize, BorshSerialize)]
+#[near(serializers = [json])]
+#[borsh(crate = "near_sdk::borsh")]
+pub struct DropInCreation {
+    pub capacity: u32,          // Most keys the drop can get, paid for upfront
+    pub num_keys: u32,          // Keys added so far
+    pub key_storage: NearToken, // Deposit left for the storage of the keys still to add
+    pub fee_deposit: NearToken, // Protocol fee paid for the capacity, charged on the keys added
+}
+
+#[near]
+impl Contract {
+    // Creates a NEAR drop without keys, which are added with `add_keys_batch`. The deposit
+    // covers `capacity` keys, what the unused capacity holds is refunded by `finalize_drop`
+    #[payable]
+    #[handle_result]
+    pub fn create_near_drop_batched(
+        &mut self,
+        capacity: u32,
+        amount_per_drop: NearToken,
+        config: Option<DropConfig>,
+    ) -> Result<DropId, DropError> {
+        self.check_not_paused()?;
+
+        let config = config.unwrap_or_default();
+        let required_deposit =
+            near_drop::required_deposit(amount_per_drop, capacity, &config, &self.fees);
+        let drop = near_drop::create(
+            env::predecessor_account_id(),
+            amount_per_drop,
+            capacity,
+            config,
+        )?;
+
+        self.internal_create_batched_drop(
+            drop,
+            env::attached_deposit(),
+            required_deposit,
+            self.fees.near_fee(amount_per_drop, capacity),
+        )
+    }
+
+    // Same as `create_near_drop_batched` for an FT drop, which is funded once finalized
+    #[payable]
+    #[handle_result]
+    pub fn create_ft_drop_batched(
+        &mut self,
+        capacity: u32,
+        ft_contract: AccountId,
+        amount_per_drop: NearToken,
+        config: Option<DropConfig>,
+    ) -> Result<PromiseOrValue<DropId>, DropError> {
+        self.check_not_paused()?;
+
+        let mut drop = ft_drop::create(
+            env::predecessor_account_id(),
+            ft_contract.clone(),
+            amount_per_drop,
+            capacity,
+            &self.fees,
+            config.unwrap_or_default(),
+        )?;
+        let attached_deposit = env::attached_deposit();
+
+        if let Some(registration) = self.ft_storage_minimums.get(&ft_contract) {
+            drop.set_registration(*registration);
+            let drop_id = self.internal_create_batched_ft_drop(drop, attached_deposit)?;
+            return Ok(PromiseOrValue::Value(drop_id));
+        }
+
+        Ok(PromiseOrValue::Promise(
+            Promise::new(ft_contract)
+                .function_call_weight(
+                    "storage_balance_bounds".to_string(),
+                    vec![],
+                    NearToken::from_yoctonear(0),
+                    MIN_GAS_FOR_FT_STORAGE_BALANCE,
+                    GasWeight(0),
+                )
+                .then(
+                    Self::ext(env::current_account_id())
+                        .with_static_gas(FT_STORAGE_BOUNDS_CALLBACK_GAS)
+                        .resolve_ft_storage_bounds(vec![], attached_deposit, drop, true),
+                ),
+        ))
+    }
+
+    // Adds keys to a drop being created, returning how many it has. Keys the drop already has
+    // are skipped, so a batch can be sent again if its transaction failed or timed out
+    #[handle_result]
+    pub fn add_keys_batch(
+        &mut self,
+        drop_id: DropId,
+        public_keys: Vec<PublicKey>,
+    ) -> Result<u32, DropError> {
+        self.check_not_paused()?;
+        self.internal_get_drop_as_funder(&drop_id)?;
+        let mut creation = self
+            .drops_in_creation
+            .get(&drop_id)
+            .cloned()
+            .ok_or(DropError::DropFinalized(drop_id))?;
+        let initial_storage = env::storage_usage();
+
+        let mut new_keys = vec![];
+        for public_key in public_keys {
+            match self.drop_id_by_key.get(&public_key) {
+                Some(key_drop_id) if *key_drop_id == drop_id => continue,
+                Some(_) => return Err(DropError::KeyAlreadyUsed),
+                None => {}
+            }
+            self.drop_id_by_key.insert(public_key.clone(), drop_id);
+            new_keys.push(public_key);
+        }
+
+        creation.num_keys += new_keys.len() as u32;
+        if creation.num_keys > creation.capacity {
+            return Err(DropError::CapacityExceeded(creation.capacity));
+        }
+
+        // The storage of the keys comes out of what was paid for them at creation
+        self.flush_drops();
+        let storage_used = storage_cost(env::storage_usage().saturating_sub(initial_storage));
+        if storage_used > creation.key_storage {
+            return Err(DropError::InsufficientDeposit(storage_used));
+        }
+        creation.key_storage = creation.key_storage.saturating_sub(storage_used);
+
+        let num_keys = creation.num_keys;
+        self.drops_in_creation.insert(drop_id, creation);
+        for public_key in new_keys {
+            self.add_claim_key(public_key);
+        }
+
+        Ok(num_keys)
+    }
+
+    // Opens a drop created in batches for claims. What its unused capacity holds is refunded
+    // to the funder, and the fee is only charged on the keys added. A drop without keys is
+    // removed, refunding the whole deposit
+    #[handle_result]
+    pub fn finalize_drop(&mut self, drop_id: DropId) -> Result<(), DropError> {
+        self.check_not_paused()?;
+        let mut drop = self.internal_get_drop_as_funder(&drop_id)?;
+        let creation = self
+            .drops_in_creation
+            .remove(&drop_id)
+            .ok_or(DropError::DropFinalized(drop_id))?;
+        let funder = drop.get_funder().clone();
+        let initial_storage = env::storage_usage();
+
+        let num_keys = creation.num_keys;
+        let amount_per_drop = drop.get_amount_per_drop()?;
+        let fee = match drop {
+            Drop::FT(_) => self.fees.key_fee(num_keys),
+            _ => self.fees.near_fee(amount_per_drop, num_keys),
+        };
+
+        let refund = self
+            .release_unused_keys(&drop, creation.capacity - num_keys)
+            .saturating_add(creation.key_storage)
+            .saturating_add(creation.fee_deposit.saturating_sub(fee));
+        self.release_storage_locked(creation.key_storage);
+        self.release_near_owed(creation.fee_deposit);
+        self.collect_near_fee(fee);
+
+        if num_keys == 0 {
+            self.internal_remove_drop(&drop_id);
+            self.claim_logs.remove(&drop_id);
+            self.vesting_funders.remove(&drop_id);
+            self.pending_drop_owners.remove(&drop_id);
+            self.paused_drops.remove(&drop_id);
+            log!("Drop {drop_id} removed without keys");
+        } else {
+            drop.set_counter(num_keys)?;
+            if let Drop::FT(ft_drop) = &mut drop {
+                ft_drop.set_fee(self.fees.ft_fee(amount_per_drop, num_keys));
+            }
+            self.internal_update_drop(drop_id, drop);
+            log!("Drop {drop_id} finalized with {num_keys} keys");
+        }
+
+        // The storage freed is refunded along with the rest
+        self.settle_storage_change(initial_storage, refund, funder)
+    }
+
+    pub fn get_drop_in_creation(&self, drop_id: DropId) -> Option<DropInCreation> {
+        self.drops_in_creation.get(&drop_id).cloned()
+    }
+}
+
+impl Contract {
+    pub(crate) fn check_drop_finalized(&self, drop_id: &DropId) -> Result<(), DropError> {
+        if self.drops_in_creation.contains_key(drop_id) {
+            return Err(DropError::DropNotFinalized(*drop_id));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn internal_create_batched_ft_drop(
+        &mut self,
+        drop: FTDrop,
+        attached_deposit: NearToken,
+    ) -> Result<DropId, DropError> {
+        let key_fee = self.fees.key_fee(drop.get_counter()?);
+        let required_deposit = drop.required_deposit().saturating_add(key_fee);
+
+        self.internal_create_batched_drop(
+            Drop::FT(drop),
+            attached_deposit,
+            required_deposit,
+            key_fee,
+        )
+    }
+
+    // Saves a drop whose counter is its capacity, charging the funder for the storage it takes
+    // and for the keys to come on top of `required_deposit`. The fee is held until finalized
+    fn internal_create_batched_drop(
+        &mut self,
+        drop: Drop,
+        attached_deposit: NearToken,
+        required_deposit: NearToken,
+        fee: NearToken,
+    ) -> Result<DropId, DropError> {
+        let funder = drop.get_funder().clone();
+        let capacity = drop.get_counter()?;
+        let key_storage = storage_cost(MAX_KEY_STORAGE).saturating_mul(capacity as u128);
+        let initial_storage = env::storage_usage();
+
+        let drop_id = self.save_drop(drop.clone());
+        self.open_claim_log(drop_id, &drop);
+        self.open_vesting_funder(drop_id, &drop);
+        self.drops_in_creation.insert(
+            drop_id,
+            DropInCreation {
+                capacity,
+                num_keys: 0,
+                key_storage,
+                fee_deposit: fee,
+            },
+        );
+
+        self.flush_drops();
+        let storage_used = storage_cost(env::storage_usage().saturating_sub(initial_storage));
+        let required_deposit = required_deposit
+            .saturating_add(storage_used)
+            .saturating_add(key_storage);
+
+        if attached_deposit < required_deposit {
+            self.internal_remove_drop(&drop_id);
+            self.claim_logs.remove(&drop_id);
+            self.vesting_funders.remove(&drop_id);
+            self.drops_in_creation.remove(&drop_id);
+            self.next_drop_id -= 1;
+            return Err(DropError::InsufficientDeposit(required_deposit));
+        }
+
+        self.add_drop_liabilities(&drop, capacity, storage_used.saturating_add(key_storage));
+        self.add_near_owed(fee);
+        self.assert_solvent();
+
+        let extra_deposit = attached_deposit.saturating_sub(required_deposit);
+        if !extra_deposit.is_zero() {
+            Promise::new(funder).transfer(extra_deposit);
+        }
+
+        Ok(drop_id)
+    }
+}
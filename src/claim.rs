@@ -1,32 +1,66 @@
+use crate::claim_log::ClaimStatus;
 use crate::constants::*;
 
-use crate::drop_types::{Dropper, Getters, Setters};
-use crate::{Contract, ContractExt};
+use crate::drop_types::{Drop, Dropper, Getters, Setters};
+use crate::eligibility::Eligibility;
+use crate::errors::DropError;
+use crate::{storage_cost, Contract, ContractExt};
 
-use near_sdk::serde_json::json;
-use near_sdk::{env, near, AccountId, Promise, PromiseError};
+use near_sdk::json_types::Base58CryptoHash;
+use near_sdk::serde_json::{json, Value};
+use near_sdk::{env, near, AccountId, CryptoHash, Promise, PromiseError, PublicKey};
 
 #[near]
 impl Contract {
     #[private]
-    pub fn claim_for(&mut self, account_id: AccountId) -> Promise {
+    #[handle_result]
+    pub fn claim_for(
+        &mut self,
+        account_id: AccountId,
+        proof: Option<Vec<Base58CryptoHash>>,
+    ) -> Result<Promise, DropError> {
+        let drop = self.internal_get_signer_drop()?;
+        check_listed(&drop, &account_id, proof)?;
+
         self.internal_claim(account_id, false)
     }
 
     #[private]
-    pub fn create_account_and_claim(&mut self, account_id: AccountId) -> Promise {
+    #[handle_result]
+    pub fn create_account_and_claim(
+        &mut self,
+        account_id: AccountId,
+        new_public_key: Option<PublicKey>,
+        proof: Option<Vec<Base58CryptoHash>>,
+    ) -> Result<Promise, DropError> {
+        self.check_not_paused()?;
         let public_key = env::signer_account_pk();
+        let drop = self.internal_get_signer_drop()?;
+        check_listed(&drop, &account_id, proof)?;
+
+        // A new account cannot hold anything yet, so gated drops only go to existing ones
+        if drop.get_config().eligibility.is_some() {
+            return Err(DropError::ExistingAccountRequired);
+        }
 
-        if let None = self.drop_id_by_key.get(&public_key) {
-            panic!("No drop for public key")
+        // The linkdrop key is known to whoever created the link, so drops can
+        // require the claimer to bring their own key for the new account
+        let require_new_key = drop.get_config().require_new_key;
+        let new_public_key = match new_public_key {
+            Some(new_public_key) => new_public_key,
+            None if require_new_key => return Err(DropError::NewKeyRequired),
+            None => public_key.clone(),
+        };
+        if require_new_key && new_public_key == public_key {
+            return Err(DropError::LinkdropKeyReused);
         }
 
-        let create_args = json!({ "new_account_id": account_id, "new_public_key": public_key })
+        let create_args = json!({ "new_account_id": account_id, "new_public_key": new_public_key })
             .to_string()
             .into_bytes()
             .to_vec();
 
-        Promise::new(self.top_level_account.clone())
+        Ok(Promise::new(self.top_level_account.clone())
             .function_call(
                 "create_account".to_string(),
                 create_args,
@@ -37,50 +71,208 @@ impl Contract {
                 Self::ext(env::current_account_id())
                     .with_static_gas(CREATE_CALLBACK_GAS)
                     .resolve_account_create(account_id),
-            )
+            ))
     }
 
     #[private]
+    #[handle_result]
     pub fn resolve_account_create(
         &mut self,
         account_id: AccountId,
         #[callback_result] created: Result<bool, PromiseError>,
-    ) -> Promise {
+    ) -> Result<Promise, DropError> {
         // The first step of creating an account has finished
-        if let Err(_) = created {
-            panic!("Creating account failed")
+        if created.is_err() {
+            return Err(DropError::AccountCreationFailed);
         }
 
         // Creating the account was successful, we can continue with the claim
         self.internal_claim(account_id, true)
     }
 
-    fn internal_claim(&mut self, account_id: AccountId, account_created: bool) -> Promise {
+    #[private]
+    #[handle_result]
+    pub fn resolve_eligibility(
+        &mut self,
+        account_id: AccountId,
+        account_created: bool,
+        eligibility: Eligibility,
+        #[callback_result] result: Result<Value, PromiseError>,
+    ) -> Result<Promise, DropError> {
+        let eligible = result.is_ok_and(|result| eligibility.is_met(&account_id, &result));
+        if !eligible {
+            return Err(DropError::NotEligible(account_id));
+        }
+
+        self.internal_pay_out(account_id, account_created)
+    }
+
+    fn internal_claim(
+        &mut self,
+        account_id: AccountId,
+        account_created: bool,
+    ) -> Result<Promise, DropError> {
+        self.check_not_paused()?;
+        let drop = self.internal_get_signer_drop()?;
+
+        // Gated drops check the recipient on the other contract before anything is paid out
+        if let Some(eligibility) = drop.get_config().eligibility.clone() {
+            return Ok(eligibility.promise_to_check(&account_id).then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(ELIGIBILITY_CALLBACK_GAS)
+                    .resolve_eligibility(account_id, account_created, eligibility),
+            ));
+        }
+
+        self.internal_pay_out(account_id, account_created)
+    }
+
+    fn internal_pay_out(
+        &mut self,
+        account_id: AccountId,
+        account_created: bool,
+    ) -> Result<Promise, DropError> {
+        self.check_not_paused()?;
         let public_key = env::signer_account_pk();
+        let initial_storage = env::storage_usage();
 
         // get the id for the public_key
         let drop_id = self
             .drop_id_by_key
             .remove(&public_key)
-            .expect("No drop for public key");
+            .ok_or(DropError::NoDropForKey)?;
+        // The drop may have changed while the eligibility check was running
+        self.check_drop_not_paused(&drop_id)?;
+        self.check_drop_finalized(&drop_id)?;
 
         let drop = self
-            .drop_by_id
-            .remove(&drop_id)
-            .expect("No drop information for such drop_id");
+            .internal_remove_drop(&drop_id)
+            .ok_or(DropError::DropNotFound(drop_id))?;
+        drop.check_claimable()?;
         let counter = drop.get_counter().unwrap_or(1);
         let updated_counter = counter - 1;
-        let mut drop_deleted = true;
 
         if updated_counter > 0 {
             let mut updated_drop = drop.clone();
             let _ = updated_drop.set_counter(updated_counter);
 
-            self.drop_by_id.insert(drop_id.clone(), updated_drop);
-            drop_deleted = false;
+            self.internal_update_drop(drop_id, updated_drop);
+        } else {
+            // Its storage is refunded to the funder with the rest of the drop
+            self.pending_drop_owners.remove(&drop_id);
+        }
+
+        // Counted before measuring the storage, so the funder pays for it out of the refund
+        if let Some(max_claims) = drop.get_config().max_claims_per_account {
+            let claims = self
+                .claims_per_account
+                .get(&(drop_id, account_id.clone()))
+                .copied()
+                .unwrap_or(0);
+            if claims >= max_claims {
+                return Err(DropError::ClaimLimitReached(account_id, max_claims));
+            }
+            self.claims_per_account
+                .insert((drop_id, account_id.clone()), claims + 1);
         }
 
-        drop.promise_for_claiming(account_id)
-            .then(drop.promise_to_resolve_claim(account_created, drop_deleted))
+        // Vesting drops hold the claim in a grant, which the recipient withdraws over time
+        let vesting = drop.get_config().vesting.is_some();
+        if vesting {
+            self.add_vesting_grant(drop_id, &drop, &account_id)?;
+        }
+
+        // Staking drops record the stake the contract holds for the recipient
+        if let Drop::Staking(staking_drop) = &drop {
+            self.add_delegation(
+                staking_drop.get_staking_pool(),
+                &account_id,
+                staking_drop.get_amount_per_drop().unwrap(),
+            )?;
+        }
+
+        // POAP drops issue a soulbound token to the recipient
+        if let Drop::POAP(_) = &drop {
+            self.issue_sbt(drop_id, &account_id)?;
+        }
+
+        // Logged before measuring the storage, out of what each key paid for its entry. Vesting
+        // and POAP claims are settled here, the callback of other claims tells how they went
+        let status = match vesting || matches!(drop, Drop::POAP(_)) {
+            true => ClaimStatus::Succeeded,
+            false => ClaimStatus::Pending,
+        };
+        let log_index = self.log_claim(drop_id, &drop, &public_key, &account_id, status);
+
+        // The funder gets back exactly the storage released by this claim, with what is left
+        // of the storage paid upfront for its records
+        self.flush_drops();
+        let storage_freed = storage_cost(
+            (initial_storage + drop.claim_storage()).saturating_sub(env::storage_usage()),
+        );
+        let refund = self.release_claim_liabilities(&drop, storage_freed);
+        self.assert_solvent();
+
+        // The key cannot claim anything else, remove it from the contract
+        Promise::new(env::current_account_id()).delete_key(public_key);
+
+        if vesting {
+            return Ok(self.refund_vesting_claim(&drop, account_created, refund));
+        }
+        if let Drop::POAP(poap_drop) = &drop {
+            return Ok(self.refund_poap_claim(poap_drop, account_created, refund));
+        }
+
+        let claim = drop
+            .promise_for_claiming(account_id.clone())?
+            .then(drop.promise_to_resolve_claim(account_id, account_created, storage_freed)?);
+
+        Ok(match log_index {
+            Some(index) => claim.then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(CLAIM_LOG_CALLBACK_GAS)
+                    .with_unused_gas_weight(0)
+                    .resolve_claim_log(drop_id, index),
+            ),
+            None => claim,
+        })
+    }
+}
+
+impl Contract {
+    // The drop of the key that signed the transaction, which is still the case in callbacks.
+    // Claims go through here, so keys that cannot be claimed are rejected before anything happens
+    fn internal_get_signer_drop(&self) -> Result<Drop, DropError> {
+        let drop_id = self
+            .drop_id_by_key
+            .get(&env::signer_account_pk())
+            .ok_or(DropError::NoDropForKey)?;
+        self.check_drop_not_paused(drop_id)?;
+        self.check_drop_finalized(drop_id)?;
+
+        let drop = self
+            .internal_get_drop(drop_id)
+            .ok_or(DropError::DropNotFound(*drop_id))?;
+        drop.check_claimable()?;
+
+        Ok(drop)
+    }
+}
+
+fn check_listed(
+    drop: &Drop,
+    account_id: &AccountId,
+    proof: Option<Vec<Base58CryptoHash>>,
+) -> Result<(), DropError> {
+    if let Some(allowlist) = &drop.get_config().allowlist {
+        let proof: Vec<CryptoHash> = proof
+            .unwrap_or_default()
+            .into_iter()
+            .map(CryptoHash::from)
+            .collect();
+        if !allowlist.is_listed(account_id, &proof) {
+            return Err(DropError::NotListed(account_id.clone()));
+        }
     }
+    Ok(())
 }
@@ -0,0 +1,215 @@
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::{Base58CryptoHash, U64};
+use near_sdk::{env, near, AccountId, NearToken, PromiseError, PublicKey};
+
+use crate::claim_preview::{claim_asset, ClaimAsset};
+use crate::constants::DropId;
+use crate::drop_types::{Drop, Getters};
+use crate::errors::DropError;
+use crate::{Contract, ContractExt};
+
+// Claims of a drop recorded for its funder, kept after the drop is claimed out
+#[derive(Clone, Debug, BorshDeserialize, BorshSerialize)]
+#[near(serializers = [json])]
+#[borsh(crate = "near_sdk::borsh")]
+pub struct ClaimLog {
+    pub funder: AccountId,      // Account managing the log, the funder of the drop
+    pub retention: Option<u32>, // How many entries are kept, all of them if none
+    pub first: u64,             // Index of the oldest entry kept
+    pub next: u64,              // Index of the next entry
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, BorshDeserialize, BorshSerialize)]
+#[near(serializers = [json])]
+#[borsh(crate = "near_sdk::borsh")]
+pub enum ClaimStatus {
+    Pending, // The asset is on its way to the recipient
+    Succeeded,
+    Failed, // The asset went back to the funder
+}
+
+#[derive(Clone, Debug, BorshDeserialize, BorshSerialize)]
+#[near(serializers = [json])]
+#[borsh(crate = "near_sdk::borsh")]
+pub struct ClaimLogEntry {
+    pub index: u64,
+    pub key_hash: Base58CryptoHash, // SHA-256 of the claimed public key
+    pub account_id: AccountId,      // Recipient of the claim
+    pub claimed_at: U64,            // Timestamp of the claim, in milliseconds
+    pub asset: Option<ClaimAsset>,
+    pub status: ClaimStatus,
+}
+
+#[near]
+impl Contract {
+    pub fn get_claim_log(&self, drop_id: DropId) -> Option<ClaimLog> {
+        self.claim_logs.get(&drop_id).cloned()
+    }
+
+    // Entries of a claim log, oldest first, starting at `from_index`
+    pub fn get_claim_log_entries(
+        &self,
+        drop_id: DropId,
+        from_index: Option<u64>,
+        limit: Option<u32>,
+    ) -> Vec<ClaimLogEntry> {
+        let Some(claim_log) = self.claim_logs.get(&drop_id) else {
+            return vec![];
+        };
+
+        (from_index.unwrap_or(0).max(claim_log.first)..claim_log.next)
+            .take(limit.unwrap_or(u32::MAX) as usize)
+            .filter_map(|index| self.claim_log_entries.get(&(drop_id, index)).cloned())
+            .collect()
+    }
+
+    // Keeps the latest `retention` entries of a claim log, or all of them if none. The storage
+    // of pruned entries is refunded to the funder along with the deposit, which pays for the
+    // storage the setting may add
+    #[payable]
+    #[handle_result]
+    pub fn set_claim_log_retention(
+        &mut self,
+        drop_id: DropId,
+        retention: Option<u32>,
+    ) -> Result<(), DropError> {
+        self.check_not_paused()?;
+        let mut claim_log = self
+            .claim_logs
+            .get(&drop_id)
+            .cloned()
+            .ok_or(DropError::NoClaimLog(drop_id))?;
+        if env::predecessor_account_id() != claim_log.funder {
+            return Err(DropError::NotFunder);
+        }
+        let funder = claim_log.funder.clone();
+
+        let initial_storage = env::storage_usage();
+        claim_log.retention = retention;
+        self.prune_claim_log(drop_id, &mut claim_log);
+        self.claim_logs.insert(drop_id, claim_log);
+
+        self.settle_storage_change(initial_storage, env::attached_deposit(), funder)
+    }
+
+    // Deletes the claim log of a drop which is claimed out, refunding the storage of the log
+    // and its entries to the funder
+    #[handle_result]
+    pub fn delete_claim_log(&mut self, drop_id: DropId) -> Result<(), DropError> {
+        self.check_not_paused()?;
+        let claim_log = self
+            .claim_logs
+            .get(&drop_id)
+            .cloned()
+            .ok_or(DropError::NoClaimLog(drop_id))?;
+        if env::predecessor_account_id() != claim_log.funder {
+            return Err(DropError::NotFunder);
+        }
+        if self.drop_by_id.contains_key(&drop_id) {
+            return Err(DropError::DropNotClaimed(drop_id));
+        }
+
+        let initial_storage = env::storage_usage();
+        for index in claim_log.first..claim_log.next {
+            self.claim_log_entries.remove(&(drop_id, index));
+        }
+        self.claim_logs.remove(&drop_id);
+
+        self.settle_storage_change(
+            initial_storage,
+            NearToken::from_yoctonear(0),
+            claim_log.funder,
+        )
+    }
+
+    // Records whether the asset of a logged claim reached the recipient, as reported by the
+    // callback resolving the claim
+    #[private]
+    pub fn resolve_claim_log(
+        &mut self,
+        drop_id: DropId,
+        index: u64,
+        #[callback_result] delivered: Result<bool, PromiseError>,
+    ) -> bool {
+        let delivered = delivered.unwrap_or(false);
+
+        // The entry may have been pruned in the meantime
+        if let Some(entry) = self.claim_log_entries.get_mut(&(drop_id, index)) {
+            entry.status = match delivered {
+                true => ClaimStatus::Succeeded,
+                false => ClaimStatus::Failed,
+            };
+        }
+
+        delivered
+    }
+}
+
+impl Contract {
+    // Starts the claim log of a new drop that asked for one
+    pub(crate) fn open_claim_log(&mut self, drop_id: DropId, drop: &Drop) {
+        if !drop.get_config().claim_log {
+            return;
+        }
+
+        self.claim_logs.insert(
+            drop_id,
+            ClaimLog {
+                funder: drop.get_funder().clone(),
+                retention: None,
+                first: 0,
+                next: 0,
+            },
+        );
+    }
+
+    // Adds a claim to the log of its drop, pruning the oldest entries past the retention.
+    // Returns the index of the entry, if the drop logs its claims
+    pub(crate) fn log_claim(
+        &mut self,
+        drop_id: DropId,
+        drop: &Drop,
+        public_key: &PublicKey,
+        account_id: &AccountId,
+        status: ClaimStatus,
+    ) -> Option<u64> {
+        let mut claim_log = self.claim_logs.get(&drop_id).cloned()?;
+
+        let index = claim_log.next;
+        self.claim_log_entries.insert(
+            (drop_id, index),
+            ClaimLogEntry {
+                index,
+                key_hash: env::sha256_array(public_key.as_bytes()).into(),
+                account_id: account_id.clone(),
+                claimed_at: U64(env::block_timestamp_ms()),
+                asset: claim_asset(drop_id, drop),
+                status,
+            },
+        );
+        claim_log.next += 1;
+
+        self.prune_claim_log(drop_id, &mut claim_log);
+        self.claim_logs.insert(drop_id, claim_log);
+
+        Some(index)
+    }
+
+    // The funder of a drop also manages its claim log
+    pub(crate) fn set_claim_log_funder(&mut self, drop_id: DropId, funder: &AccountId) {
+        if let Some(claim_log) = self.claim_logs.get_mut(&drop_id) {
+            claim_log.funder = funder.clone();
+        }
+    }
+
+    fn prune_claim_log(&mut self, drop_id: DropId, claim_log: &mut ClaimLog) {
+        let Some(retention) = claim_log.retention else {
+            return;
+        };
+
+        while claim_log.next - claim_log.first > retention as u64 {
+            self.claim_log_entries.remove(&(drop_id, claim_log.first));
+            claim_log.first += 1;
+        }
+    }
+}
@@ -0,0 +1,218 @@
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::{near, AccountId, NearToken, PublicKey};
+
+use crate::allowlist::Allowlist;
+use crate::constants::DropId;
+use crate::drop_types::{Drop, Getters};
+use crate::errors::DropError;
+use crate::vesting::Vesting;
+use crate::{Contract, ContractExt};
+
+// Why a claim would fail, `code` being one of the stable error codes
+#[near(serializers = [json])]
+pub struct ClaimError {
+    pub code: String,
+    pub message: String,
+}
+
+impl From<DropError> for ClaimError {
+    fn from(error: DropError) -> Self {
+        ClaimError {
+            code: error.code().to_string(),
+            message: error.message(),
+        }
+    }
+}
+
+#[near(serializers = [json])]
+pub struct ClaimCheck {
+    pub claimable: bool,
+    pub error: Option<ClaimError>,
+}
+
+// What the recipient of a claim gets
+#[derive(Clone, Debug, BorshDeserialize, BorshSerialize)]
+#[near(serializers = [json])]
+#[borsh(crate = "near_sdk::borsh")]
+#[allow(clippy::upper_case_acronyms)]
+pub enum ClaimAsset {
+    NEAR {
+        amount: NearToken,
+    },
+    FT {
+        ft_contract: AccountId,
+        amount: NearToken,
+    },
+    NFT {
+        nft_contract: AccountId,
+        token_id: String,
+    },
+    // Token minted by the claim
+    NFTMint {
+        nft_contract: AccountId,
+        token_id: String,
+    },
+    // NEAR staked with the pool and held as a delegation
+    Stake {
+        staking_pool: AccountId,
+        amount: NearToken,
+    },
+    // Soulbound token of a POAP drop, whose class is the drop id
+    SBT {
+        class: DropId,
+    },
+}
+
+#[near(serializers = [json])]
+pub struct ClaimPreview {
+    pub drop_id: Option<DropId>,
+    pub claimable: bool,
+    pub error: Option<ClaimError>,
+    pub asset: Option<ClaimAsset>,
+    // The asset is released over time instead of at once
+    pub vesting: Option<Vesting>,
+    // No `account_id` was given, so the claim would create a new account
+    pub creates_account: bool,
+    // The recipient is checked on another contract when claiming, which a view cannot do
+    pub checks_eligibility: bool,
+}
+
+#[near]
+impl Contract {
+    // Whether `public_key` could be claimed now, for `account_id` or for a new account if none
+    // is given. Merkle allowlists and eligibility conditions are only checked when claiming
+    pub fn can_claim(&self, public_key: PublicKey, account_id: Option<AccountId>) -> ClaimCheck {
+        let result = self
+            .internal_get_key_drop(&public_key)
+            .and_then(|(drop_id, drop)| self.check_claim(drop_id, &drop, account_id.as_ref()));
+
+        ClaimCheck {
+            claimable: result.is_ok(),
+            error: result.err().map(ClaimError::from),
+        }
+    }
+
+    // Same as `can_claim`, along with what the claim would give
+    pub fn preview_claim(
+        &self,
+        public_key: PublicKey,
+        account_id: Option<AccountId>,
+    ) -> ClaimPreview {
+        let creates_account = account_id.is_none();
+        let (drop_id, drop) = match self.internal_get_key_drop(&public_key) {
+            Ok((drop_id, drop)) => (drop_id, drop),
+            Err(error) => {
+                return ClaimPreview {
+                    drop_id: None,
+                    claimable: false,
+                    error: Some(error.into()),
+                    asset: None,
+                    vesting: None,
+                    creates_account,
+                    checks_eligibility: false,
+                }
+            }
+        };
+
+        let result = self.check_claim(drop_id, &drop, account_id.as_ref());
+        ClaimPreview {
+            drop_id: Some(drop_id),
+            claimable: result.is_ok(),
+            error: result.err().map(ClaimError::from),
+            asset: claim_asset(drop_id, &drop),
+            vesting: drop.get_config().vesting.clone(),
+            creates_account,
+            checks_eligibility: drop.get_config().eligibility.is_some(),
+        }
+    }
+}
+
+impl Contract {
+    fn internal_get_key_drop(&self, public_key: &PublicKey) -> Result<(DropId, Drop), DropError> {
+        let drop_id = *self
+            .drop_id_by_key
+            .get(public_key)
+            .ok_or(DropError::NoDropForKey)?;
+        let drop = self
+            .internal_get_drop(&drop_id)
+            .ok_or(DropError::DropNotFound(drop_id))?;
+
+        Ok((drop_id, drop))
+    }
+
+    // Runs the checks a claim makes before paying out
+    fn check_claim(
+        &self,
+        drop_id: DropId,
+        drop: &Drop,
+        account_id: Option<&AccountId>,
+    ) -> Result<(), DropError> {
+        self.check_not_paused()?;
+        self.check_drop_not_paused(&drop_id)?;
+        self.check_drop_finalized(&drop_id)?;
+        drop.check_claimable()?;
+
+        let config = drop.get_config();
+        let Some(account_id) = account_id else {
+            if config.eligibility.is_some() {
+                return Err(DropError::ExistingAccountRequired);
+            }
+            return Ok(());
+        };
+
+        if let Some(allowlist @ Allowlist::Accounts(_)) = &config.allowlist {
+            if !allowlist.is_listed(account_id, &[]) {
+                return Err(DropError::NotListed(account_id.clone()));
+            }
+        }
+
+        if let Some(max_claims) = config.max_claims_per_account {
+            let claims = self
+                .claims_per_account
+                .get(&(drop_id, account_id.clone()))
+                .copied()
+                .unwrap_or(0);
+            if claims >= max_claims {
+                return Err(DropError::ClaimLimitReached(account_id.clone(), max_claims));
+            }
+        }
+
+        let key = (drop_id, account_id.clone());
+        if config.vesting.is_some() && self.vesting_grants.contains_key(&key) {
+            return Err(DropError::GrantExists(account_id.clone()));
+        }
+        if matches!(drop, Drop::POAP(_)) && self.sbts.contains_key(&key) {
+            return Err(DropError::AlreadyAttended(account_id.clone()));
+        }
+
+        Ok(())
+    }
+}
+
+pub(crate) fn claim_asset(drop_id: DropId, drop: &Drop) -> Option<ClaimAsset> {
+    let asset = match drop {
+        Drop::NEAR(near_drop) => ClaimAsset::NEAR {
+            amount: near_drop.get_amount_per_drop().ok()?,
+        },
+        Drop::FT(ft_drop) => ClaimAsset::FT {
+            ft_contract: ft_drop.get_ft_contract().clone(),
+            amount: ft_drop.get_amount_per_drop().ok()?,
+        },
+        Drop::NFT(nft_drop) if nft_drop.has_token() => ClaimAsset::NFT {
+            nft_contract: nft_drop.get_nft_contract().clone(),
+            token_id: nft_drop.get_token_id().to_string(),
+        },
+        Drop::NFTMint(nft_mint_drop) => ClaimAsset::NFTMint {
+            nft_contract: nft_mint_drop.get_nft_contract().clone(),
+            token_id: nft_mint_drop.token_id(),
+        },
+        Drop::Staking(staking_drop) => ClaimAsset::Stake {
+            staking_pool: staking_drop.get_staking_pool().clone(),
+            amount: staking_drop.get_amount_per_drop().ok()?,
+        },
+        Drop::POAP(_) => ClaimAsset::SBT { class: drop_id },
+        Drop::NFT(_) | Drop::Merkle(_) => return None,
+    };
+
+    Some(asset)
+}
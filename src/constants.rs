@@ -13,17 +13,58 @@ pub const CREATE_ACCOUNT_FEE: NearToken = NearToken::from_yoctonear(0); // 0 N
 pub const CREATE_CALLBACK_GAS: Gas = Gas::from_tgas(55); // 55 TGas
 pub const CLAIM_CALLBACK_GAS: Gas = Gas::from_tgas(5); // 5 TGas
 
+// Upper bounds for the gas a drop can request through its config
+pub const MAX_TRANSFER_GAS: Gas = Gas::from_tgas(100); // 100 TGas
+pub const MAX_CALLBACK_GAS: Gas = Gas::from_tgas(50); // 50 TGas
+
 // Actual amount of GAS to attach when creating a new account. No unspent GAS will be attached on top of this (weight of 0)
 pub const GAS_FOR_CREATE_ACCOUNT: Gas = Gas::from_tgas(28); // 28 TGas
 
+// Eligibility checks on other contracts, the callback also gets any unspent GAS
+pub const MIN_GAS_FOR_ELIGIBILITY_CHECK: Gas = Gas::from_tgas(10); // 10 TGas
+pub const ELIGIBILITY_CALLBACK_GAS: Gas = Gas::from_tgas(5); // 5 TGas
+
 // FT
+pub const MIN_GAS_FOR_FT_STORAGE_BALANCE: Gas = Gas::from_tgas(5); // 5 TGas
 pub const MIN_GAS_FOR_FT_STORAGE_DEPOSIT: Gas = Gas::from_tgas(5); // 5 TGas
 pub const MIN_GAS_FOR_FT_TRANSFER: Gas = Gas::from_tgas(5); // 5 TGas
+pub const FT_STORAGE_BOUNDS_CALLBACK_GAS: Gas = Gas::from_tgas(20); // 20 TGas
+pub const FT_STORAGE_CHECK_CALLBACK_GAS: Gas = Gas::from_tgas(10); // 10 TGas
 pub const FT_CLAIM_CALLBACK_GAS: Gas = Gas::from_tgas(10); // 10 TGas
 
+// Fees
+pub const FEE_WITHDRAW_CALLBACK_GAS: Gas = Gas::from_tgas(5); // 5 TGas
+
+// Surplus
+pub const MIN_GAS_FOR_FT_BALANCE_OF: Gas = Gas::from_tgas(5); // 5 TGas
+pub const SWEEP_FT_CALLBACK_GAS: Gas = Gas::from_tgas(15); // 15 TGas
+
+// Merkle
+pub const MERKLE_CLAIM_CALLBACK_GAS: Gas = Gas::from_tgas(10); // 10 TGas
+pub const MERKLE_RECLAIM_CALLBACK_GAS: Gas = Gas::from_tgas(10); // 10 TGas
+
+// Staking
+pub const MIN_GAS_FOR_DEPOSIT_AND_STAKE: Gas = Gas::from_tgas(50); // 50 TGas
+pub const MIN_GAS_FOR_UNSTAKE: Gas = Gas::from_tgas(50); // 50 TGas
+pub const MIN_GAS_FOR_STAKING_WITHDRAW: Gas = Gas::from_tgas(50); // 50 TGas
+pub const MIN_GAS_FOR_STAKED_BALANCE: Gas = Gas::from_tgas(5); // 5 TGas
+pub const STAKING_CLAIM_CALLBACK_GAS: Gas = Gas::from_tgas(10); // 10 TGas
+pub const DELEGATION_CALLBACK_GAS: Gas = Gas::from_tgas(10); // 10 TGas
+pub const POOL_WITHDRAW_CALLBACK_GAS: Gas = Gas::from_tgas(80); // 80 TGas, sends the next batch
+pub const POOL_CALL_GAS: Gas = Gas::from_tgas(130); // 130 TGas, a withdraw or unstake with its callback
+pub const NUM_EPOCHS_TO_UNLOCK: u64 = 4; // Epochs a pool holds unstaked NEAR for
+
+// Claim log
+pub const CLAIM_LOG_CALLBACK_GAS: Gas = Gas::from_tgas(5); // 5 TGas
+
+// Vesting
+pub const VESTING_WITHDRAW_CALLBACK_GAS: Gas = Gas::from_tgas(10); // 10 TGas
+
 // NFT
 pub const MIN_GAS_FOR_NFT_TRANSFER: Gas = Gas::from_tgas(5); // 5 TGas
 pub const NFT_CLAIM_CALLBACK_GAS: Gas = Gas::from_tgas(10); // 10 TGas
+pub const MIN_GAS_FOR_NFT_MINT: Gas = Gas::from_tgas(20); // 20 TGas
+pub const NFT_MINT_CALLBACK_GAS: Gas = Gas::from_tgas(10); // 10 TGas
 
 /*
     minimum amount of storage required to store an access key on the contract
@@ -32,10 +73,25 @@ pub const NFT_CLAIM_CALLBACK_GAS: Gas = Gas::from_tgas(10); // 10 TGas
 */
 pub const ACCESS_KEY_STORAGE: NearToken = NearToken::from_millinear(1); // 0.001 N
 
-// Bytes used to store common data types
-pub const PK_STORAGE: u128 = 32; // PublicKey
-pub const ID_STORAGE: u128 = 4; // PublicKey
-pub const ACC_STORAGE: u128 = 4 + 8; // AccountId
-pub const ENUM_STORAGE: u128 = 1; // Enum
-pub const TOKEN_AMOUNT_STORAGE: u128 = 16; // NearToken
-pub const NFT_TOKEN_ID_STORAGE: u128 = 16; // String
+// Largest vesting grant, its storage is paid for each key of a vesting drop
+pub const MAX_VESTING_GRANT_STORAGE: u64 = 400; // bytes
+
+// Largest delegation of a claimer with the total of its pool, paid for each key of a staking drop
+pub const MAX_DELEGATION_STORAGE: u64 = 400; // bytes
+
+// Largest soulbound token with its entry in the list of its owner, paid for each key of a POAP drop
+pub const MAX_SBT_STORAGE: u64 = 300; // bytes
+
+// Largest entry of a claim log, paid for each key of a drop logging its claims
+pub const MAX_CLAIM_LOG_ENTRY_STORAGE: u64 = 400; // bytes
+
+// Largest count of the claims of a recipient, with the longest account id. Paid for each key of
+// a drop limiting the claims per account
+pub const MAX_CLAIM_COUNT_STORAGE: u64 = 130; // bytes
+
+// Largest entry mapping a key to its drop, SECP256K1 keys being the longest. Drops created in
+// batches pay it upfront for each key
+pub const MAX_KEY_STORAGE: u64 = 120; // bytes
+
+// Longest NFT token id accepted, its storage is paid when the drop is created
+pub const MAX_NFT_TOKEN_ID_LEN: u64 = 128; // bytes
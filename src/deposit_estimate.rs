@@ -0,0 +1,219 @@
+use near_sdk::{borsh, near, AccountId, NearToken, PublicKey};
+
+use crate::claim_log::ClaimLog;
+use crate::constants::*;
+use crate::drop_types::{Drop, DropConfig, Getters, VersionedDrop};
+use crate::errors::DropError;
+use crate::vesting::VestingFunder;
+use crate::{ft_drop, near_drop, nft_drop, storage_cost, Contract, ContractExt};
+
+// Bytes the protocol charges for every record on top of its key and value
+const STORAGE_RECORD_OVERHEAD: u64 = 40;
+
+// Bytes of the prefix of a collection in its storage keys
+const STORAGE_KEY_PREFIX_LEN: u64 = 1;
+
+// Deposit needed to create a drop, item by item. All amounts are in NEAR and add up to `total`
+#[near(serializers = [json])]
+pub struct DepositBreakdown {
+    pub amounts: NearToken,          // NEAR to drop
+    pub allowance: NearToken,        // Gas allowance of the keys
+    pub key_storage: NearToken,      // Storage of the keys on the contract account
+    pub account_creation: NearToken, // Creating the accounts of claimers without one
+    pub registration: NearToken,     // Registering claimers on the FT contract
+    pub claim_storage: NearToken,    // Storage reserved for what claims record
+    pub drop_storage: NearToken,     // Storage of the drop and its keys in the contract state
+    pub fees: NearToken,             // Protocol fee
+    pub total: NearToken,
+    // False until the registration cost of the FT contract is known, which the first drop
+    // of that contract fetches. Creating the drop then needs `registration` on top
+    pub complete: bool,
+    // FTs to send with `ft_transfer_call` to fund an FT drop, protocol fee included
+    pub ft_amount: Option<NearToken>,
+}
+
+impl DepositBreakdown {
+    fn new(num_keys: u32) -> Self {
+        let num_keys = num_keys as u128;
+        DepositBreakdown {
+            amounts: NearToken::from_yoctonear(0),
+            allowance: ACCESS_KEY_ALLOWANCE.saturating_mul(num_keys),
+            key_storage: ACCESS_KEY_STORAGE.saturating_mul(num_keys),
+            account_creation: CREATE_ACCOUNT_FEE.saturating_mul(num_keys),
+            registration: NearToken::from_yoctonear(0),
+            claim_storage: NearToken::from_yoctonear(0),
+            drop_storage: NearToken::from_yoctonear(0),
+            fees: NearToken::from_yoctonear(0),
+            total: NearToken::from_yoctonear(0),
+            complete: true,
+            ft_amount: None,
+        }
+    }
+
+    fn with_total(mut self) -> Self {
+        self.total = [
+            self.amounts,
+            self.allowance,
+            self.key_storage,
+            self.account_creation,
+            self.registration,
+            self.claim_storage,
+            self.drop_storage,
+            self.fees,
+        ]
+        .into_iter()
+        .fold(NearToken::from_yoctonear(0), NearToken::saturating_add);
+        self
+    }
+}
+
+#[near]
+impl Contract {
+    // Deposit to attach to `create_near_drop`. The storage of the drop depends on the funder
+    // and is estimated for the longest account id if none is given, any surplus being
+    // refunded on creation. Keys are counted as ED25519 keys
+    #[handle_result]
+    pub fn get_required_deposit_near_drop(
+        &self,
+        num_keys: u32,
+        amount_per_drop: NearToken,
+        config: Option<DropConfig>,
+        funder: Option<AccountId>,
+    ) -> Result<DepositBreakdown, DropError> {
+        let config = config.unwrap_or_default();
+        let claim_storage = storage_cost(config.claim_storage());
+        let drop = near_drop::create(
+            funder.unwrap_or_else(longest_account_id),
+            amount_per_drop,
+            num_keys,
+            config,
+        )?;
+
+        Ok(DepositBreakdown {
+            amounts: amount_per_drop.saturating_mul(num_keys as u128),
+            claim_storage: claim_storage.saturating_mul(num_keys as u128),
+            drop_storage: drop_storage(&drop, num_keys),
+            fees: self.fees.near_fee(amount_per_drop, num_keys),
+            ..DepositBreakdown::new(num_keys)
+        }
+        .with_total())
+    }
+
+    // Deposit to attach to `create_ft_drop`, along with the FTs funding the drop
+    #[handle_result]
+    pub fn get_required_deposit_ft_drop(
+        &self,
+        num_keys: u32,
+        ft_contract: AccountId,
+        amount_per_drop: NearToken,
+        config: Option<DropConfig>,
+        funder: Option<AccountId>,
+    ) -> Result<DepositBreakdown, DropError> {
+        let config = config.unwrap_or_default();
+        let claim_storage = storage_cost(config.claim_storage());
+        let registration = self.ft_storage_minimums.get(&ft_contract).copied();
+        let mut drop = ft_drop::create(
+            funder.unwrap_or_else(longest_account_id),
+            ft_contract,
+            amount_per_drop,
+            num_keys,
+            &self.fees,
+            config,
+        )?;
+        drop.set_registration(registration.unwrap_or(NearToken::from_yoctonear(0)));
+
+        let ft_amount = amount_per_drop
+            .saturating_mul(num_keys as u128)
+            .saturating_add(self.fees.ft_fee(amount_per_drop, num_keys));
+
+        Ok(DepositBreakdown {
+            registration: drop.get_registration().saturating_mul(num_keys as u128),
+            claim_storage: claim_storage.saturating_mul(num_keys as u128),
+            drop_storage: drop_storage(&Drop::FT(drop), num_keys),
+            fees: self.fees.key_fee(num_keys),
+            complete: registration.is_some(),
+            ft_amount: Some(ft_amount),
+            ..DepositBreakdown::new(num_keys)
+        }
+        .with_total())
+    }
+
+    // Deposit to attach to `create_nft_drop`, which has a single key. Room for the longest
+    // token id is paid upfront as it is only known once the NFT is approved
+    #[handle_result]
+    pub fn get_required_deposit_nft_drop(
+        &self,
+        nft_contract: AccountId,
+        config: Option<DropConfig>,
+        funder: Option<AccountId>,
+    ) -> Result<DepositBreakdown, DropError> {
+        let config = config.unwrap_or_default();
+        let claim_storage = storage_cost(MAX_NFT_TOKEN_ID_LEN + config.claim_storage());
+        let drop = nft_drop::create(
+            funder.unwrap_or_else(longest_account_id),
+            nft_contract,
+            config,
+        )?;
+
+        Ok(DepositBreakdown {
+            claim_storage,
+            drop_storage: drop_storage(&drop, 1),
+            ..DepositBreakdown::new(1)
+        }
+        .with_total())
+    }
+}
+
+// Storage `internal_create_drop` measures when it saves the drop, maps its keys to it and
+// starts the records its settings need
+fn drop_storage(drop: &Drop, num_keys: u32) -> NearToken {
+    let drop_id_len = std::mem::size_of::<DropId>() as u64;
+    let drop_value = borsh::to_vec(&VersionedDrop::from(drop.clone())).unwrap();
+    let drop_record = record_bytes(drop_id_len, drop_value.len() as u64);
+
+    let public_key: PublicKey = "ed25519:11111111111111111111111111111111".parse().unwrap();
+    let key_len = borsh::to_vec(&public_key).unwrap().len() as u64;
+    let key_record = record_bytes(key_len, drop_id_len);
+
+    let vesting_funder_record = match drop.get_config().vesting {
+        Some(_) => {
+            let vesting_funder = VestingFunder {
+                funder: drop.get_funder().clone(),
+                open_grants: 0,
+            };
+            record_bytes(
+                drop_id_len,
+                borsh::to_vec(&vesting_funder).unwrap().len() as u64,
+            )
+        }
+        None => 0,
+    };
+
+    let claim_log_record = match drop.get_config().claim_log {
+        true => {
+            let claim_log = ClaimLog {
+                funder: drop.get_funder().clone(),
+                retention: None,
+                first: 0,
+                next: 0,
+            };
+            record_bytes(drop_id_len, borsh::to_vec(&claim_log).unwrap().len() as u64)
+        }
+        false => 0,
+    };
+
+    storage_cost(
+        drop_record
+            .saturating_add(key_record.saturating_mul(num_keys as u64))
+            .saturating_add(vesting_funder_record)
+            .saturating_add(claim_log_record),
+    )
+}
+
+fn record_bytes(key_len: u64, value_len: u64) -> u64 {
+    STORAGE_KEY_PREFIX_LEN + key_len + value_len + STORAGE_RECORD_OVERHEAD
+}
+
+fn longest_account_id() -> AccountId {
+    "a".repeat(64).parse().unwrap()
+}
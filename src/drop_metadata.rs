@@ -0,0 +1,44 @@
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::{env, near};
+
+use crate::constants::DropId;
+use crate::drop_types::{Getters, Setters};
+use crate::errors::DropError;
+use crate::{Contract, ContractExt};
+
+// What claim pages show about a drop, all fields are optional
+#[derive(Clone, Debug, Default, BorshDeserialize, BorshSerialize)]
+#[near(serializers = [json])]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(default)]
+pub struct DropMetadata {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub media: Option<String>, // URL of an image or video
+    pub extra: Option<String>, // Anything else, as stringified JSON
+}
+
+#[near]
+impl Contract {
+    // Replaces the metadata of a drop. The funder pays for the storage it adds and gets back the
+    // storage it frees, along with what is left of the deposit
+    #[payable]
+    #[handle_result]
+    pub fn set_drop_metadata(
+        &mut self,
+        drop_id: DropId,
+        metadata: Option<DropMetadata>,
+    ) -> Result<(), DropError> {
+        self.check_not_paused()?;
+        let mut drop = self.internal_get_drop_as_funder(&drop_id)?;
+        let funder = drop.get_funder().clone();
+
+        let mut config = drop.get_config().clone();
+        config.metadata = metadata;
+        drop.set_config(config);
+
+        let initial_storage = env::storage_usage();
+        self.internal_update_drop(drop_id, drop);
+        self.settle_storage_change(initial_storage, env::attached_deposit(), funder)
+    }
+}
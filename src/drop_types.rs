@@ -1,78 +1,358 @@
+use near_sdk::borsh::io::{self, Read, Write};
 use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
-use near_sdk::{near, AccountId, NearToken, Promise};
+use near_sdk::{near, AccountId, Gas, NearToken, Promise};
 
-use crate::ft_drop::FTDrop;
-use crate::near_drop::NearDrop;
-use crate::nft_drop::NFTDrop;
+use crate::allowlist::Allowlist;
+use crate::constants::{
+    MAX_CALLBACK_GAS, MAX_CLAIM_COUNT_STORAGE, MAX_CLAIM_LOG_ENTRY_STORAGE, MAX_DELEGATION_STORAGE,
+    MAX_SBT_STORAGE, MAX_TRANSFER_GAS, MAX_VESTING_GRANT_STORAGE,
+};
+
+use crate::drop_metadata::DropMetadata;
+use crate::eligibility::Eligibility;
+use crate::errors::DropError;
+use crate::ft_drop::{FTDrop, FTDropV1};
+use crate::merkle_drop::MerkleDrop;
+use crate::near_drop::{NearDrop, NearDropV1};
+use crate::nft_drop::{NFTDrop, NFTDropV1};
+use crate::nft_mint_drop::NFTMintDrop;
+use crate::poap_drop::POAPDrop;
+use crate::staking_drop::StakingDrop;
+use crate::vesting::Vesting;
 
 // This Drop enum stores drop details such as funder, amount to drop or token id, etc.
 #[derive(Clone, Debug, BorshDeserialize, BorshSerialize)]
 #[near(serializers = [json])]
 #[borsh(crate = "near_sdk::borsh")]
+#[allow(clippy::large_enum_variant, clippy::upper_case_acronyms)]
 pub enum Drop {
     NEAR(NearDrop),
     FT(FTDrop),
     NFT(NFTDrop),
+    Merkle(MerkleDrop),
+    Staking(StakingDrop),
+    NFTMint(NFTMintDrop),
+    POAP(POAPDrop),
+}
+
+// Drops are stored versioned so their layout can change between contract upgrades.
+// When a drop structure changes, the previous layout is kept as a new variant here
+// and converted into the current `Drop` the next time it is read
+#[derive(Clone, Debug)]
+#[allow(clippy::large_enum_variant)]
+pub enum VersionedDrop {
+    V1(DropV1),
+    V2(Drop),
+}
+
+// Drops stored before they were versioned start with the tag of their kind, so the tags of
+// later versions come after those of `DropV1`
+const V2_TAG: u8 = 3;
+
+impl BorshSerialize for VersionedDrop {
+    fn serialize<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        match self {
+            VersionedDrop::V1(drop) => drop.serialize(writer),
+            VersionedDrop::V2(drop) => {
+                V2_TAG.serialize(writer)?;
+                drop.serialize(writer)
+            }
+        }
+    }
+}
+
+impl BorshDeserialize for VersionedDrop {
+    fn deserialize_reader<R: Read>(reader: &mut R) -> io::Result<Self> {
+        match u8::deserialize_reader(reader)? {
+            V2_TAG => Ok(VersionedDrop::V2(Drop::deserialize_reader(reader)?)),
+            tag => {
+                let tag = [tag];
+                let mut reader = tag.as_slice().chain(reader);
+                Ok(VersionedDrop::V1(DropV1::deserialize_reader(&mut reader)?))
+            }
+        }
+    }
+}
+
+// Layout of `Drop` before drops were versioned
+#[derive(Clone, Debug, BorshDeserialize, BorshSerialize)]
+#[borsh(crate = "near_sdk::borsh")]
+#[allow(clippy::upper_case_acronyms)]
+pub enum DropV1 {
+    NEAR(NearDropV1),
+    FT(FTDropV1),
+    NFT(NFTDropV1),
+}
+
+impl From<DropV1> for Drop {
+    fn from(drop: DropV1) -> Self {
+        match drop {
+            DropV1::NEAR(near_drop) => Drop::NEAR(near_drop.into()),
+            DropV1::FT(ft_drop) => Drop::FT(ft_drop.into()),
+            DropV1::NFT(nft_drop) => Drop::NFT(nft_drop.into()),
+        }
+    }
+}
+
+impl Drop {
+    // Storage paid upfront for each key, for what its claim records on the contract
+    pub fn claim_storage(&self) -> u64 {
+        let record_storage = match self {
+            Drop::Staking(_) => MAX_DELEGATION_STORAGE,
+            Drop::POAP(_) => MAX_SBT_STORAGE,
+            _ => 0,
+        };
+        record_storage + self.get_config().claim_storage()
+    }
+
+    // Whether the keys of the drop can be claimed right now
+    pub fn check_claimable(&self) -> Result<(), DropError> {
+        match self {
+            Drop::FT(ft_drop) if !ft_drop.is_funded() => Err(DropError::NotFunded),
+            Drop::NFT(nft_drop) if !nft_drop.has_token() => Err(DropError::NoTokensToDrop),
+            Drop::POAP(poap_drop) => poap_drop.check_open(),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl From<VersionedDrop> for Drop {
+    fn from(versioned_drop: VersionedDrop) -> Self {
+        match versioned_drop {
+            VersionedDrop::V1(drop) => drop.into(),
+            VersionedDrop::V2(drop) => drop,
+        }
+    }
+}
+
+impl From<Drop> for VersionedDrop {
+    fn from(drop: Drop) -> Self {
+        VersionedDrop::V2(drop)
+    }
+}
+
+// Settings shared by every kind of drop, chosen by the funder at creation
+#[derive(Clone, Debug, Default, BorshDeserialize, BorshSerialize)]
+#[near(serializers = [json])]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(default)]
+pub struct DropConfig {
+    pub require_new_key: bool, // New accounts must be created with a key supplied by the claimer
+    pub transfer_gas: Option<Gas>, // Overrides the gas attached to the FT / NFT transfer
+    pub callback_gas: Option<Gas>, // Overrides the gas attached to the callback resolving the claim
+    pub eligibility: Option<Eligibility>, // Condition the recipient must meet to claim
+    pub allowlist: Option<Allowlist>, // Recipients allowed to claim
+    pub max_claims_per_account: Option<u32>, // How many keys of the drop one recipient can claim
+    pub vesting: Option<Vesting>, // Claims are released over time instead of at once
+    pub metadata: Option<DropMetadata>, // Describes the drop to claim pages, the funder can update it
+    pub claim_log: bool,                // Claims are recorded in a log kept for the funder
+}
+
+impl DropConfig {
+    pub fn transfer_gas(&self, default: Gas) -> Gas {
+        self.transfer_gas.unwrap_or(default)
+    }
+
+    pub fn callback_gas(&self, default: Gas) -> Gas {
+        self.callback_gas.unwrap_or(default)
+    }
+
+    // Storage paid upfront for each key, so the claim can write the grant of a vesting drop
+    pub fn grant_storage(&self) -> u64 {
+        match self.vesting {
+            Some(_) => MAX_VESTING_GRANT_STORAGE,
+            None => 0,
+        }
+    }
+
+    // Storage paid upfront for each key, so the claim can add its entry to the claim log
+    pub fn claim_log_storage(&self) -> u64 {
+        match self.claim_log {
+            true => MAX_CLAIM_LOG_ENTRY_STORAGE,
+            false => 0,
+        }
+    }
+
+    // Storage paid upfront for each key, so the claim can count the claims of its recipient
+    pub fn claim_count_storage(&self) -> u64 {
+        match self.max_claims_per_account {
+            Some(_) => MAX_CLAIM_COUNT_STORAGE,
+            None => 0,
+        }
+    }
+
+    // Storage paid upfront for each key for everything the settings make its claim record
+    pub fn claim_storage(&self) -> u64 {
+        self.grant_storage() + self.claim_log_storage() + self.claim_count_storage()
+    }
+
+    // Overrides can raise the gas up to a ceiling, but never below what the drop type needs
+    pub fn check_gas(&self, min_transfer_gas: Gas, min_callback_gas: Gas) -> Result<(), DropError> {
+        if let Some(transfer_gas) = self.transfer_gas {
+            if transfer_gas < min_transfer_gas || transfer_gas > MAX_TRANSFER_GAS {
+                return Err(DropError::GasOutOfBounds(
+                    "transfer_gas",
+                    min_transfer_gas,
+                    MAX_TRANSFER_GAS,
+                ));
+            }
+        }
+
+        if let Some(callback_gas) = self.callback_gas {
+            if callback_gas < min_callback_gas || callback_gas > MAX_CALLBACK_GAS {
+                return Err(DropError::GasOutOfBounds(
+                    "callback_gas",
+                    min_callback_gas,
+                    MAX_CALLBACK_GAS,
+                ));
+            }
+        }
+
+        Ok(())
+    }
 }
 
 pub trait Dropper {
-    fn promise_for_claiming(&self, account_id: AccountId) -> Promise;
-    fn promise_to_resolve_claim(&self, account_created: bool, drop_deleted: bool) -> Promise;
+    fn promise_for_claiming(&self, account_id: AccountId) -> Result<Promise, DropError>;
+    fn promise_to_resolve_claim(
+        &self,
+        account_id: AccountId,
+        account_created: bool,
+        storage_freed: NearToken,
+    ) -> Result<Promise, DropError>;
 }
 
 pub trait Getters {
-    fn get_amount_per_drop(&self) -> Result<NearToken, &str>;
-    fn get_counter(&self) -> Result<u32, &str>;
+    fn get_funder(&self) -> &AccountId;
+    fn get_config(&self) -> &DropConfig;
+    fn get_amount_per_drop(&self) -> Result<NearToken, DropError>;
+    fn get_counter(&self) -> Result<u32, DropError>;
 }
 
 pub trait Setters {
-    fn set_counter(&mut self, value: u32) -> Result<(), &str>;
+    fn set_counter(&mut self, value: u32) -> Result<(), DropError>;
+    fn set_config(&mut self, config: DropConfig);
+    fn set_funder(&mut self, funder: AccountId);
 }
 
 impl Dropper for Drop {
-    fn promise_for_claiming(&self, account_id: AccountId) -> Promise {
+    fn promise_for_claiming(&self, account_id: AccountId) -> Result<Promise, DropError> {
         match self {
             Drop::NEAR(near_drop) => near_drop.promise_for_claiming(account_id),
             Drop::FT(ft_drop) => ft_drop.promise_for_claiming(account_id),
             Drop::NFT(nft_drop) => nft_drop.promise_for_claiming(account_id),
+            Drop::Staking(staking_drop) => staking_drop.promise_for_claiming(account_id),
+            Drop::NFTMint(nft_mint_drop) => nft_mint_drop.promise_for_claiming(account_id),
+            Drop::Merkle(_) | Drop::POAP(_) => Err(DropError::WrongDropType),
         }
     }
 
-    fn promise_to_resolve_claim(&self, account_created: bool, drop_deleted: bool) -> Promise {
+    fn promise_to_resolve_claim(
+        &self,
+        account_id: AccountId,
+        account_created: bool,
+        storage_freed: NearToken,
+    ) -> Result<Promise, DropError> {
         match self {
             Drop::NEAR(near_drop) => {
-                near_drop.promise_to_resolve_claim(account_created, drop_deleted)
+                near_drop.promise_to_resolve_claim(account_id, account_created, storage_freed)
+            }
+            Drop::FT(ft_drop) => {
+                ft_drop.promise_to_resolve_claim(account_id, account_created, storage_freed)
+            }
+            Drop::NFT(nft_drop) => {
+                nft_drop.promise_to_resolve_claim(account_id, account_created, storage_freed)
             }
-            Drop::FT(ft_drop) => ft_drop.promise_to_resolve_claim(account_created, drop_deleted),
-            Drop::NFT(nft_drop) => nft_drop.promise_to_resolve_claim(account_created, drop_deleted),
+            Drop::Staking(staking_drop) => {
+                staking_drop.promise_to_resolve_claim(account_id, account_created, storage_freed)
+            }
+            Drop::NFTMint(nft_mint_drop) => {
+                nft_mint_drop.promise_to_resolve_claim(account_id, account_created, storage_freed)
+            }
+            Drop::Merkle(_) | Drop::POAP(_) => Err(DropError::WrongDropType),
         }
     }
 }
 
 impl Getters for Drop {
-    fn get_amount_per_drop(&self) -> Result<NearToken, &str> {
+    fn get_funder(&self) -> &AccountId {
+        match self {
+            Drop::NEAR(near_drop) => near_drop.get_funder(),
+            Drop::FT(ft_drop) => ft_drop.get_funder(),
+            Drop::NFT(nft_drop) => nft_drop.get_funder(),
+            Drop::Merkle(merkle_drop) => merkle_drop.get_funder(),
+            Drop::Staking(staking_drop) => staking_drop.get_funder(),
+            Drop::NFTMint(nft_mint_drop) => nft_mint_drop.get_funder(),
+            Drop::POAP(poap_drop) => poap_drop.get_funder(),
+        }
+    }
+
+    fn get_config(&self) -> &DropConfig {
+        match self {
+            Drop::NEAR(near_drop) => near_drop.get_config(),
+            Drop::FT(ft_drop) => ft_drop.get_config(),
+            Drop::NFT(nft_drop) => nft_drop.get_config(),
+            Drop::Merkle(merkle_drop) => merkle_drop.get_config(),
+            Drop::Staking(staking_drop) => staking_drop.get_config(),
+            Drop::NFTMint(nft_mint_drop) => nft_mint_drop.get_config(),
+            Drop::POAP(poap_drop) => poap_drop.get_config(),
+        }
+    }
+
+    fn get_amount_per_drop(&self) -> Result<NearToken, DropError> {
         match self {
             Drop::NEAR(near_drop) => near_drop.get_amount_per_drop(),
             Drop::FT(ft_drop) => ft_drop.get_amount_per_drop(),
-            _ => Err("There is no amount_per_drop field for NFT drop structure"),
+            Drop::Staking(staking_drop) => staking_drop.get_amount_per_drop(),
+            _ => Err(DropError::NoAmountPerDrop),
         }
     }
 
-    fn get_counter(&self) -> Result<u32, &str> {
+    fn get_counter(&self) -> Result<u32, DropError> {
         match self {
             Drop::NEAR(near_drop) => near_drop.get_counter(),
             Drop::FT(ft_drop) => ft_drop.get_counter(),
-            _ => Err("There is no amount_per_drop field for NFT drop structure"),
+            Drop::Staking(staking_drop) => staking_drop.get_counter(),
+            Drop::NFTMint(nft_mint_drop) => nft_mint_drop.get_counter(),
+            Drop::POAP(poap_drop) => poap_drop.get_counter(),
+            _ => Err(DropError::NoCounter),
         }
     }
 }
 
 impl Setters for Drop {
-    fn set_counter(&mut self, value: u32) -> Result<(), &str> {
+    fn set_counter(&mut self, value: u32) -> Result<(), DropError> {
         match self {
             Drop::NEAR(near_drop) => near_drop.set_counter(value),
             Drop::FT(ft_drop) => ft_drop.set_counter(value),
-            _ => Err("There is no counter field for NFT drop structure"),
+            Drop::Staking(staking_drop) => staking_drop.set_counter(value),
+            Drop::NFTMint(nft_mint_drop) => nft_mint_drop.set_counter(value),
+            Drop::POAP(poap_drop) => poap_drop.set_counter(value),
+            _ => Err(DropError::NoCounter),
+        }
+    }
+
+    fn set_config(&mut self, config: DropConfig) {
+        match self {
+            Drop::NEAR(near_drop) => near_drop.set_config(config),
+            Drop::FT(ft_drop) => ft_drop.set_config(config),
+            Drop::NFT(nft_drop) => nft_drop.set_config(config),
+            Drop::Merkle(merkle_drop) => merkle_drop.set_config(config),
+            Drop::Staking(staking_drop) => staking_drop.set_config(config),
+            Drop::NFTMint(nft_mint_drop) => nft_mint_drop.set_config(config),
+            Drop::POAP(poap_drop) => poap_drop.set_config(config),
+        }
+    }
+
+    fn set_funder(&mut self, funder: AccountId) {
+        match self {
+            Drop::NEAR(near_drop) => near_drop.set_funder(funder),
+            Drop::FT(ft_drop) => ft_drop.set_funder(funder),
+            Drop::NFT(nft_drop) => nft_drop.set_funder(funder),
+            Drop::Merkle(merkle_drop) => merkle_drop.set_funder(funder),
+            Drop::Staking(staking_drop) => staking_drop.set_funder(funder),
+            Drop::NFTMint(nft_mint_drop) => nft_mint_drop.set_funder(funder),
+            Drop::POAP(poap_drop) => poap_drop.set_funder(funder),
         }
     }
 }
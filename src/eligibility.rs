@@ -0,0 +1,82 @@
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::serde_json::{json, Value};
+use near_sdk::{near, AccountId, GasWeight, NearToken, Promise};
+
+use crate::constants::MIN_GAS_FOR_ELIGIBILITY_CHECK;
+
+// Condition the recipient must meet on another contract before a drop pays out
+#[derive(Clone, Debug, BorshDeserialize, BorshSerialize)]
+#[near(serializers = [json])]
+#[borsh(crate = "near_sdk::borsh")]
+pub enum Eligibility {
+    // Holds at least one token of an NFT collection
+    NftHolder {
+        nft_contract: AccountId,
+    },
+    // Holds at least `min_balance` of an FT
+    FtHolder {
+        ft_contract: AccountId,
+        min_balance: NearToken,
+    },
+    // Is listed in a group of a Sputnik DAO, in the given role if any
+    DaoMember {
+        dao_contract: AccountId,
+        role: Option<String>,
+    },
+}
+
+impl Eligibility {
+    // View call whose result is checked by `is_met`
+    pub fn promise_to_check(&self, account_id: &AccountId) -> Promise {
+        let (contract, method, args) = match self {
+            Eligibility::NftHolder { nft_contract } => (
+                nft_contract,
+                "nft_supply_for_owner",
+                json!({ "account_id": account_id }),
+            ),
+            Eligibility::FtHolder { ft_contract, .. } => (
+                ft_contract,
+                "ft_balance_of",
+                json!({ "account_id": account_id }),
+            ),
+            Eligibility::DaoMember { dao_contract, .. } => (dao_contract, "get_policy", json!({})),
+        };
+
+        Promise::new(contract.clone()).function_call_weight(
+            method.to_string(),
+            args.to_string().into_bytes().to_vec(),
+            NearToken::from_yoctonear(0),
+            MIN_GAS_FOR_ELIGIBILITY_CHECK,
+            GasWeight(0),
+        )
+    }
+
+    pub fn is_met(&self, account_id: &AccountId, result: &Value) -> bool {
+        match self {
+            Eligibility::NftHolder { .. } => as_u128(result).is_some_and(|supply| supply > 0),
+            Eligibility::FtHolder { min_balance, .. } => {
+                as_u128(result).is_some_and(|balance| balance >= min_balance.as_yoctonear())
+            }
+            Eligibility::DaoMember { role, .. } => {
+                let Some(roles) = result["roles"].as_array() else {
+                    return false;
+                };
+
+                roles
+                    .iter()
+                    .filter(|policy_role| match role {
+                        Some(role) => policy_role["name"].as_str() == Some(role.as_str()),
+                        None => true,
+                    })
+                    .filter_map(|policy_role| policy_role["kind"]["Group"].as_array())
+                    .flatten()
+                    .any(|member| member.as_str() == Some(account_id.as_str()))
+            }
+        }
+    }
+}
+
+// Token amounts are returned as strings
+fn as_u128(value: &Value) -> Option<u128> {
+    value.as_str()?.parse().ok()
+}
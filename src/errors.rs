@@ -0,0 +1,259 @@
+use std::fmt;
+
+use near_sdk::{AccountId, FunctionError, Gas, NearToken};
+
+use crate::access_control::Role;
+use crate::constants::DropId;
+
+// Errors returned by the drop methods. Panic messages start with the code of the error,
+// such as `ERR_NOT_FUNDED: Drop is not funded yet`. Codes never change, so frontends can
+// map them to their own messages
+#[derive(Clone, Debug, PartialEq, FunctionError)]
+pub enum DropError {
+    ContractPaused,
+    NoDropForKey,
+    DropNotFound(DropId),
+    KeyAlreadyUsed,
+    InsufficientDeposit(NearToken),
+    NotFunder,
+    DropPaused(DropId),
+    DropNotPaused(DropId),
+    NotOffered(AccountId),
+    NoPendingTransfer(DropId),
+    NoClaimLog(DropId),
+    DropNotClaimed(DropId),
+    DropFinalized(DropId),
+    DropNotFinalized(DropId),
+    CapacityExceeded(u32),
+    NotFunded,
+    AlreadyFunded(DropId),
+    InvalidDropId(String),
+    WrongFtContract(AccountId),
+    WrongFtAmount(NearToken),
+    NoTokensToDrop,
+    WrongDropType,
+    InvalidProof,
+    AlreadyClaimed,
+    NotEnoughLeft,
+    EventNotStarted,
+    EventOver,
+    NotListed(AccountId),
+    NotEligible(AccountId),
+    ExistingAccountRequired,
+    NewKeyRequired,
+    LinkdropKeyReused,
+    AlreadyAttended(AccountId),
+    GrantExists(AccountId),
+    ClaimLimitReached(AccountId, u32),
+    AccountCreationFailed,
+    NoCounter,
+    NoAmountPerDrop,
+    NotOwner,
+    MissingRole(Role),
+    NoContractCode,
+    AmountTooLow(NearToken),
+    NoRecipients,
+    GasOutOfBounds(&'static str, Gas, Gas),
+    UnsupportedConfig(&'static str),
+    InvalidVesting(&'static str),
+    InvalidEventWindow,
+    TokenIdTooLong(u64),
+    WrongNftContract(AccountId),
+    FeesTooHigh(u128),
+    NoFeesToWithdraw,
+    NoSurplus,
+    BalanceUnavailable(AccountId),
+    NoVestingGrant(AccountId),
+    NothingVested,
+    NothingStaked,
+    NothingUnstaked,
+    PoolBusy(AccountId),
+    UnstakeLocked(u64),
+    ShareOverflow(AccountId),
+}
+
+impl DropError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            DropError::ContractPaused => "ERR_CONTRACT_PAUSED",
+            DropError::NoDropForKey => "ERR_NO_DROP_FOR_KEY",
+            DropError::DropNotFound(_) => "ERR_DROP_NOT_FOUND",
+            DropError::KeyAlreadyUsed => "ERR_KEY_ALREADY_USED",
+            DropError::InsufficientDeposit(_) => "ERR_INSUFFICIENT_DEPOSIT",
+            DropError::NotFunder => "ERR_NOT_FUNDER",
+            DropError::DropPaused(_) => "ERR_DROP_PAUSED",
+            DropError::DropNotPaused(_) => "ERR_DROP_NOT_PAUSED",
+            DropError::NotOffered(_) => "ERR_NOT_OFFERED",
+            DropError::NoPendingTransfer(_) => "ERR_NO_PENDING_TRANSFER",
+            DropError::NoClaimLog(_) => "ERR_NO_CLAIM_LOG",
+            DropError::DropNotClaimed(_) => "ERR_DROP_NOT_CLAIMED",
+            DropError::DropFinalized(_) => "ERR_DROP_FINALIZED",
+            DropError::DropNotFinalized(_) => "ERR_DROP_NOT_FINALIZED",
+            DropError::CapacityExceeded(_) => "ERR_CAPACITY_EXCEEDED",
+            DropError::NotFunded => "ERR_NOT_FUNDED",
+            DropError::AlreadyFunded(_) => "ERR_ALREADY_FUNDED",
+            DropError::InvalidDropId(_) => "ERR_INVALID_DROP_ID",
+            DropError::WrongFtContract(_) => "ERR_WRONG_FT_CONTRACT",
+            DropError::WrongFtAmount(_) => "ERR_WRONG_FT_AMOUNT",
+            DropError::NoTokensToDrop => "ERR_NO_TOKENS_TO_DROP",
+            DropError::WrongDropType => "ERR_WRONG_DROP_TYPE",
+            DropError::InvalidProof => "ERR_INVALID_PROOF",
+            DropError::AlreadyClaimed => "ERR_ALREADY_CLAIMED",
+            DropError::NotEnoughLeft => "ERR_NOT_ENOUGH_LEFT",
+            DropError::EventNotStarted => "ERR_EVENT_NOT_STARTED",
+            DropError::EventOver => "ERR_EVENT_OVER",
+            DropError::NotListed(_) => "ERR_NOT_LISTED",
+            DropError::NotEligible(_) => "ERR_NOT_ELIGIBLE",
+            DropError::ExistingAccountRequired => "ERR_EXISTING_ACCOUNT_REQUIRED",
+            DropError::NewKeyRequired => "ERR_NEW_KEY_REQUIRED",
+            DropError::LinkdropKeyReused => "ERR_LINKDROP_KEY_REUSED",
+            DropError::AlreadyAttended(_) => "ERR_ALREADY_ATTENDED",
+            DropError::GrantExists(_) => "ERR_GRANT_EXISTS",
+            DropError::ClaimLimitReached(..) => "ERR_CLAIM_LIMIT_REACHED",
+            DropError::AccountCreationFailed => "ERR_ACCOUNT_CREATION_FAILED",
+            DropError::NoCounter => "ERR_NO_COUNTER",
+            DropError::NoAmountPerDrop => "ERR_NO_AMOUNT_PER_DROP",
+            DropError::NotOwner => "ERR_NOT_OWNER",
+            DropError::MissingRole(_) => "ERR_MISSING_ROLE",
+            DropError::NoContractCode => "ERR_NO_CONTRACT_CODE",
+            DropError::AmountTooLow(_) => "ERR_AMOUNT_TOO_LOW",
+            DropError::NoRecipients => "ERR_NO_RECIPIENTS",
+            DropError::GasOutOfBounds(..) => "ERR_GAS_OUT_OF_BOUNDS",
+            DropError::UnsupportedConfig(_) => "ERR_UNSUPPORTED_CONFIG",
+            DropError::InvalidVesting(_) => "ERR_INVALID_VESTING",
+            DropError::InvalidEventWindow => "ERR_INVALID_EVENT_WINDOW",
+            DropError::TokenIdTooLong(_) => "ERR_TOKEN_ID_TOO_LONG",
+            DropError::WrongNftContract(_) => "ERR_WRONG_NFT_CONTRACT",
+            DropError::FeesTooHigh(_) => "ERR_FEES_TOO_HIGH",
+            DropError::NoFeesToWithdraw => "ERR_NO_FEES_TO_WITHDRAW",
+            DropError::NoSurplus => "ERR_NO_SURPLUS",
+            DropError::BalanceUnavailable(_) => "ERR_BALANCE_UNAVAILABLE",
+            DropError::NoVestingGrant(_) => "ERR_NO_VESTING_GRANT",
+            DropError::NothingVested => "ERR_NOTHING_VESTED",
+            DropError::NothingStaked => "ERR_NOTHING_STAKED",
+            DropError::NothingUnstaked => "ERR_NOTHING_UNSTAKED",
+            DropError::PoolBusy(_) => "ERR_POOL_BUSY",
+            DropError::UnstakeLocked(_) => "ERR_UNSTAKE_LOCKED",
+            DropError::ShareOverflow(_) => "ERR_SHARE_OVERFLOW",
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            DropError::ContractPaused => "Contract is paused".to_string(),
+            DropError::NoDropForKey => "No drop for public key".to_string(),
+            DropError::DropNotFound(drop_id) => format!("No drop with id {drop_id}"),
+            DropError::KeyAlreadyUsed => "Public key is already used for a drop".to_string(),
+            DropError::InsufficientDeposit(required_deposit) => {
+                format!("Please attach at least {required_deposit}")
+            }
+            DropError::NotFunder => "Only the funder can update the drop".to_string(),
+            DropError::DropPaused(drop_id) => format!("Drop {drop_id} is paused by its funder"),
+            DropError::DropNotPaused(drop_id) => format!("Drop {drop_id} is not paused"),
+            DropError::NotOffered(account_id) => {
+                format!("The drop was not offered to {account_id}")
+            }
+            DropError::NoPendingTransfer(drop_id) => {
+                format!("No pending transfer for drop {drop_id}")
+            }
+            DropError::NoClaimLog(drop_id) => format!("Drop {drop_id} does not log its claims"),
+            DropError::DropNotClaimed(drop_id) => format!("Drop {drop_id} still has keys to claim"),
+            DropError::DropFinalized(drop_id) => {
+                format!("Drop {drop_id} is not being created in batches")
+            }
+            DropError::DropNotFinalized(drop_id) => {
+                format!("Drop {drop_id} is still being created")
+            }
+            DropError::CapacityExceeded(capacity) => {
+                format!("The drop cannot have more than {capacity} keys")
+            }
+            DropError::NotFunded => "Drop is not funded yet".to_string(),
+            DropError::AlreadyFunded(drop_id) => format!("Drop {drop_id} is already funded"),
+            DropError::InvalidDropId(msg) => format!("{msg} is not a drop id"),
+            DropError::WrongFtContract(ft_contract) => format!("Wrong FTs, expected {ft_contract}"),
+            DropError::WrongFtAmount(required_amount) => {
+                format!("Wrong FT amount, expected {required_amount}")
+            }
+            DropError::NoTokensToDrop => "No tokens to drop".to_string(),
+            DropError::WrongDropType => "The drop is not of the expected type".to_string(),
+            DropError::InvalidProof => "Invalid proof".to_string(),
+            DropError::AlreadyClaimed => "Already claimed".to_string(),
+            DropError::NotEnoughLeft => "Not enough left in the drop".to_string(),
+            DropError::EventNotStarted => "The event has not started yet".to_string(),
+            DropError::EventOver => "The event is over".to_string(),
+            DropError::NotListed(account_id) => {
+                format!("{account_id} is not allowed to claim this drop")
+            }
+            DropError::NotEligible(account_id) => {
+                format!("{account_id} is not eligible for this drop")
+            }
+            DropError::ExistingAccountRequired => {
+                "This drop can only be claimed by an existing account".to_string()
+            }
+            DropError::NewKeyRequired => "This drop requires a new_public_key".to_string(),
+            DropError::LinkdropKeyReused => {
+                "The linkdrop key cannot be used as the new account key".to_string()
+            }
+            DropError::AlreadyAttended(account_id) => {
+                format!("{account_id} already holds the token of this event")
+            }
+            DropError::GrantExists(account_id) => {
+                format!("{account_id} already has a vesting grant from this drop")
+            }
+            DropError::ClaimLimitReached(account_id, max_claims) => {
+                format!("{account_id} cannot claim this drop more than {max_claims} times")
+            }
+            DropError::AccountCreationFailed => "Creating account failed".to_string(),
+            DropError::NoCounter => "There is no counter field for this drop".to_string(),
+            DropError::NoAmountPerDrop => {
+                "There is no amount_per_drop field for this drop".to_string()
+            }
+            DropError::NotOwner => "Only the owner can call this method".to_string(),
+            DropError::MissingRole(role) => format!("Missing role {role:?}"),
+            DropError::NoContractCode => "Missing contract code in input".to_string(),
+            DropError::AmountTooLow(min_amount) => format!("Amount must be at least {min_amount}"),
+            DropError::NoRecipients => "The drop needs at least one recipient".to_string(),
+            DropError::GasOutOfBounds(name, min_gas, max_gas) => {
+                format!("{name} must be between {min_gas} and {max_gas}")
+            }
+            DropError::UnsupportedConfig(reason) => reason.to_string(),
+            DropError::InvalidVesting(reason) => reason.to_string(),
+            DropError::InvalidEventWindow => "The event must end after it starts".to_string(),
+            DropError::TokenIdTooLong(max_len) => {
+                format!("Token id cannot be longer than {max_len} bytes")
+            }
+            DropError::WrongNftContract(nft_contract) => {
+                format!("Wrong NFT contract, expected {nft_contract}")
+            }
+            DropError::FeesTooHigh(max_bps) => {
+                format!("Fees cannot exceed {max_bps} basis points")
+            }
+            DropError::NoFeesToWithdraw => "No fees to withdraw".to_string(),
+            DropError::NoSurplus => "No surplus to sweep".to_string(),
+            DropError::BalanceUnavailable(account_id) => {
+                format!("Could not get the balance from {account_id}")
+            }
+            DropError::NoVestingGrant(account_id) => {
+                format!("No vesting grant for {account_id}")
+            }
+            DropError::NothingVested => "Nothing has vested yet".to_string(),
+            DropError::NothingStaked => "Nothing staked to unstake".to_string(),
+            DropError::NothingUnstaked => "Nothing unstaked to withdraw".to_string(),
+            DropError::PoolBusy(staking_pool) => {
+                format!("A call to {staking_pool} is in progress, try again")
+            }
+            DropError::UnstakeLocked(epoch_height) => {
+                format!("Not released by the pool yet, the current batch unlocks at epoch {epoch_height}")
+            }
+            DropError::ShareOverflow(staking_pool) => {
+                format!("The share of the stake with {staking_pool} is too large to compute")
+            }
+        }
+    }
+}
+
+impl fmt::Display for DropError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.code(), self.message())
+    }
+}
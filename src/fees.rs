@@ -0,0 +1,155 @@
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde_json::json;
+use near_sdk::{env, log, near, AccountId, GasWeight, NearToken, Promise, PromiseError};
+
+use crate::access_control::Role;
+use crate::constants::*;
+use crate::errors::DropError;
+use crate::{Contract, ContractExt};
+
+const BASIS_POINTS: u128 = 10_000; // 100%
+
+// Protocol fee charged when drops are created, never on claims
+#[derive(Clone, Debug, Default, BorshDeserialize, BorshSerialize)]
+#[near(serializers = [json])]
+#[borsh(crate = "near_sdk::borsh")]
+pub struct FeeConfig {
+    pub per_key: NearToken, // Flat fee in NEAR for every key of a NEAR or FT drop
+    pub near_bps: u16,      // Share of the NEAR dropped, in basis points
+    pub ft_bps: u16,        // Share of the FTs dropped, in basis points, paid in the same FT
+}
+
+impl FeeConfig {
+    pub fn key_fee(&self, num_of_keys: u32) -> NearToken {
+        self.per_key.saturating_mul(num_of_keys as u128)
+    }
+
+    // NEAR charged for a NEAR drop
+    pub fn near_fee(&self, amount_per_drop: NearToken, num_of_keys: u32) -> NearToken {
+        self.key_fee(num_of_keys)
+            .saturating_add(share(amount_per_drop, num_of_keys, self.near_bps))
+    }
+
+    // NEAR charged for a drop without keys, such as a Merkle airdrop
+    pub fn near_share(&self, amount: NearToken) -> NearToken {
+        share(amount, 1, self.near_bps)
+    }
+
+    // FTs charged for an FT drop, on top of the tokens to drop
+    pub fn ft_fee(&self, amount_per_drop: NearToken, num_of_keys: u32) -> NearToken {
+        share(amount_per_drop, num_of_keys, self.ft_bps)
+    }
+}
+
+fn share(amount_per_drop: NearToken, num_of_keys: u32, bps: u16) -> NearToken {
+    amount_per_drop
+        .saturating_mul(num_of_keys as u128)
+        .saturating_mul(bps as u128)
+        .saturating_div(BASIS_POINTS)
+}
+
+#[near]
+impl Contract {
+    #[handle_result]
+    pub fn set_fees(&mut self, fees: FeeConfig) -> Result<(), DropError> {
+        self.check_role(Role::FeeManager)?;
+        if fees.near_bps as u128 > BASIS_POINTS || fees.ft_bps as u128 > BASIS_POINTS {
+            return Err(DropError::FeesTooHigh(BASIS_POINTS));
+        }
+        self.fees = fees;
+        Ok(())
+    }
+
+    pub fn get_fees(&self) -> FeeConfig {
+        self.fees.clone()
+    }
+
+    // Fees collected so far, in NEAR if no token is given
+    pub fn get_fee_balance(&self, token: Option<AccountId>) -> NearToken {
+        match token {
+            None => self.treasury,
+            Some(ft_contract) => self
+                .ft_fees
+                .get(&ft_contract)
+                .cloned()
+                .unwrap_or(NearToken::from_yoctonear(0)),
+        }
+    }
+
+    // Pays the collected fees out to the owner, in NEAR if no token is given
+    #[handle_result]
+    pub fn withdraw_fees(&mut self, token: Option<AccountId>) -> Result<Promise, DropError> {
+        self.check_owner()?;
+
+        match token {
+            None => {
+                let amount = std::mem::replace(&mut self.treasury, NearToken::from_yoctonear(0));
+                if amount.is_zero() {
+                    return Err(DropError::NoFeesToWithdraw);
+                }
+
+                Ok(Promise::new(self.owner.clone()).transfer(amount))
+            }
+            Some(ft_contract) => {
+                let amount = self
+                    .ft_fees
+                    .remove(&ft_contract)
+                    .ok_or(DropError::NoFeesToWithdraw)?;
+
+                let transfer_args =
+                    json!({"receiver_id": self.owner, "amount": U128(amount.as_yoctonear())})
+                        .to_string()
+                        .into_bytes()
+                        .to_vec();
+
+                Ok(Promise::new(ft_contract.clone())
+                    .function_call_weight(
+                        "ft_transfer".to_string(),
+                        transfer_args,
+                        NearToken::from_yoctonear(1),
+                        MIN_GAS_FOR_FT_TRANSFER,
+                        GasWeight(0),
+                    )
+                    .then(
+                        Self::ext(env::current_account_id())
+                            .with_static_gas(FEE_WITHDRAW_CALLBACK_GAS)
+                            .resolve_fee_withdrawal(ft_contract, amount),
+                    ))
+            }
+        }
+    }
+
+    #[private]
+    pub fn resolve_fee_withdrawal(
+        &mut self,
+        ft_contract: AccountId,
+        amount: NearToken,
+        #[callback_result] result: Result<(), PromiseError>,
+    ) -> bool {
+        if result.is_err() {
+            // Keep the fees so they can be withdrawn again
+            log!("Withdrawing fees failed, restoring {amount} of {ft_contract}");
+            self.collect_ft_fee(ft_contract, amount);
+            return false;
+        }
+
+        true
+    }
+}
+
+impl Contract {
+    pub(crate) fn collect_near_fee(&mut self, fee: NearToken) {
+        self.treasury = self.treasury.saturating_add(fee);
+    }
+
+    pub(crate) fn collect_ft_fee(&mut self, ft_contract: AccountId, fee: NearToken) {
+        if fee.is_zero() {
+            return;
+        }
+
+        let balance = self.get_fee_balance(Some(ft_contract.clone()));
+        self.ft_fees
+            .insert(ft_contract, balance.saturating_add(fee));
+    }
+}
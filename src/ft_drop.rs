@@ -1,236 +1,443 @@
+use near_contract_standards::storage_management::{StorageBalance, StorageBalanceBounds};
 use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
 use near_sdk::json_types::U128;
 use near_sdk::serde_json::json;
 use near_sdk::{
-    env, near, AccountId, GasWeight, NearToken, Promise, PromiseError, PromiseOrValue,
+    env, log, near, AccountId, Gas, GasWeight, NearToken, Promise, PromiseError, PromiseOrValue,
+    PublicKey,
 };
 
 use crate::constants::*;
-use crate::drop_types::{Dropper, Getters, Setters};
+use crate::drop_types::{DropConfig, Dropper, Getters, Setters};
+use crate::errors::DropError;
+use crate::fees::FeeConfig;
 use crate::Drop;
-use crate::{Contract, ContractExt};
-
-const FT_REGISTER: NearToken = NearToken::from_yoctonear(12_500_000_000_000_000_000_000);
+use crate::{storage_cost, Contract, ContractExt};
 
 #[derive(Clone, Debug, BorshDeserialize, BorshSerialize)]
 #[near(serializers = [json])]
 #[borsh(crate = "near_sdk::borsh")]
 pub struct FTDrop {
-    funder: AccountId,      // Account which created the drop and funded it
-    amount: NearToken,      // Reflects how much fungible tokens will be transfer to claiming user
-    ft_contract: AccountId, // Contract of fungible tokens which will be transfer to claiming user
-    counter: u32,           // Reflects how much times the drop can be claimed
-    funded: bool,           // Reflects if the drop is funded
+    funder: AccountId,       // Account which created the drop and funded it
+    amount: NearToken,       // Reflects how much fungible tokens will be transfer to claiming user
+    ft_contract: AccountId,  // Contract of fungible tokens which will be transfer to claiming user
+    counter: u32,            // Reflects how much times the drop can be claimed
+    funded: bool,            // Reflects if the drop is funded
+    fee: NearToken,          // Protocol fee in fungible tokens, collected when the drop is funded
+    registration: NearToken, // Storage deposit required by the FT contract to register a claimer
+    config: DropConfig,      // Settings chosen by the funder
+}
+
+// What the claim callbacks need from the drop, so its config does not travel with them
+#[near(serializers = [json])]
+pub struct FTClaim {
+    funder: AccountId,
+    ft_contract: AccountId,
+    amount: NearToken,
+    registration: NearToken,
+    transfer_gas: Gas,
+    callback_gas: Gas,
+}
+
+// Storage deposit each key of an FT drop paid for registering its claimer before drops were
+// versioned, whatever the FT contract asked for
+const FT_REGISTER_V1: NearToken = NearToken::from_yoctonear(12_500_000_000_000_000_000_000);
+
+// Layout of `FTDrop` before drops were versioned, kept to read drops stored back then
+#[derive(Clone, Debug, BorshDeserialize, BorshSerialize)]
+#[borsh(crate = "near_sdk::borsh")]
+pub struct FTDropV1 {
+    funder: AccountId,
+    amount: NearToken,
+    ft_contract: AccountId,
+    counter: u32,
+    funded: bool,
+}
+
+impl From<FTDropV1> for FTDrop {
+    fn from(ft_drop: FTDropV1) -> Self {
+        FTDrop {
+            funder: ft_drop.funder,
+            amount: ft_drop.amount,
+            ft_contract: ft_drop.ft_contract,
+            counter: ft_drop.counter,
+            funded: ft_drop.funded,
+            fee: NearToken::from_yoctonear(0),
+            registration: FT_REGISTER_V1,
+            config: DropConfig::default(),
+        }
+    }
 }
 
 impl Dropper for FTDrop {
-    fn promise_for_claiming(&self, account_id: AccountId) -> Promise {
-        assert!(
-            self.amount.gt(&NearToken::from_yoctonear(0)),
-            "No tokens to drop"
-        );
+    fn promise_for_claiming(&self, account_id: AccountId) -> Result<Promise, DropError> {
+        if self.amount.is_zero() {
+            return Err(DropError::NoTokensToDrop);
+        }
+        if !self.funded {
+            return Err(DropError::NotFunded);
+        }
 
-        assert!(self.funded, "Drop is not funded yet");
+        // Check if the claimer is registered, so storage is only paid when needed
+        Ok(promise_to_check_storage(&self.ft_contract, &account_id))
+    }
 
-        let deposit_args = json!({ "account_id": account_id })
-            .to_string()
-            .into_bytes()
-            .to_vec();
-        let transfer_args =
-            json!({"receiver_id": account_id, "amount": U128(self.amount.as_yoctonear())})
-                .to_string()
-                .into_bytes()
-                .to_vec();
-
-        Promise::new(self.ft_contract.clone())
-            .function_call_weight(
-                "storage_deposit".to_string(),
-                deposit_args,
-                FT_REGISTER,
-                MIN_GAS_FOR_FT_STORAGE_DEPOSIT,
-                GasWeight(0),
-            )
-            .function_call_weight(
-                "ft_transfer".to_string(),
-                transfer_args,
-                NearToken::from_yoctonear(1),
-                MIN_GAS_FOR_FT_TRANSFER,
-                GasWeight(0),
-            )
-    }
-
-    fn promise_to_resolve_claim(&self, account_created: bool, drop_deleted: bool) -> Promise {
-        Contract::ext(env::current_account_id())
-            .with_static_gas(FT_CLAIM_CALLBACK_GAS)
+    fn promise_to_resolve_claim(
+        &self,
+        account_id: AccountId,
+        account_created: bool,
+        storage_freed: NearToken,
+    ) -> Result<Promise, DropError> {
+        let claim = FTClaim {
+            funder: self.funder.clone(),
+            ft_contract: self.ft_contract.clone(),
+            amount: self.amount,
+            registration: self.registration,
+            transfer_gas: self.config.transfer_gas(MIN_GAS_FOR_FT_TRANSFER),
+            callback_gas: self.config.callback_gas(FT_CLAIM_CALLBACK_GAS),
+        };
+
+        // The storage check forwards gas for the registration, the transfer and the final callback
+        let storage_check_gas = FT_STORAGE_CHECK_CALLBACK_GAS
+            .saturating_add(MIN_GAS_FOR_FT_STORAGE_DEPOSIT)
+            .saturating_add(claim.transfer_gas)
+            .saturating_add(claim.callback_gas);
+
+        Ok(Contract::ext(env::current_account_id())
+            .with_static_gas(storage_check_gas)
             .with_unused_gas_weight(0)
-            .resolve_ft_claim(
-                account_created,
-                drop_deleted,
-                self.funder.clone(),
-                self.amount,
-                self.ft_contract.clone(),
-            )
+            .resolve_ft_storage_check(account_id, account_created, storage_freed, claim))
     }
 }
 
 impl Getters for FTDrop {
-    fn get_counter(&self) -> Result<u32, &str> {
+    fn get_funder(&self) -> &AccountId {
+        &self.funder
+    }
+
+    fn get_config(&self) -> &DropConfig {
+        &self.config
+    }
+
+    fn get_counter(&self) -> Result<u32, DropError> {
         Ok(self.counter)
     }
 
-    fn get_amount_per_drop(&self) -> Result<NearToken, &str> {
+    fn get_amount_per_drop(&self) -> Result<NearToken, DropError> {
         Ok(self.amount)
     }
 }
 
 impl Setters for FTDrop {
-    fn set_counter(&mut self, value: u32) -> Result<(), &str> {
+    fn set_counter(&mut self, value: u32) -> Result<(), DropError> {
         self.counter = value;
         Ok(())
     }
-}
 
-pub fn required_deposit_per_key() -> NearToken {
-  CREATE_ACCOUNT_FEE
-      .saturating_add(ACCESS_KEY_ALLOWANCE)
-      .saturating_add(ACCESS_KEY_STORAGE)
-}
+    fn set_config(&mut self, config: DropConfig) {
+        self.config = config;
+    }
 
-pub fn required_storage_drop(num_access_keys: u32) -> NearToken {
-  NearToken::from_yoctonear(
-      // DropId -> Drop::Near
-      ID_STORAGE + ENUM_STORAGE + ACC_STORAGE * 2 + TOKEN_AMOUNT_STORAGE + 8 
-      // PublicKey -> DropId
-      + num_access_keys as u128 * (PK_STORAGE + ID_STORAGE)
-  )
+    fn set_funder(&mut self, funder: AccountId) {
+        self.funder = funder;
+    }
 }
 
-pub fn create(ft_contract: AccountId, amount_per_drop: NearToken, num_of_keys: u32) -> Drop {
-    let funder = env::predecessor_account_id();
-
-    let attached_deposit = env::attached_deposit();
-    let required_deposit = // required_storage_drop + (required_deposit_per_key * num_of_keys)
-        required_storage_drop(num_of_keys)
-        .saturating_add(
-            required_deposit_per_key()
-                .saturating_mul(num_of_keys as u128),
-        );
-
-    assert!(
-        attached_deposit >= required_deposit,
-        "Please attach at least {required_deposit}"
-    );
+pub fn required_deposit_per_key(registration: NearToken) -> NearToken {
+    CREATE_ACCOUNT_FEE
+        .saturating_add(ACCESS_KEY_ALLOWANCE)
+        .saturating_add(ACCESS_KEY_STORAGE)
+        .saturating_add(registration)
+}
 
-    let extra_deposit = attached_deposit.saturating_sub(required_deposit);
-    if extra_deposit.gt(&NearToken::from_yoctonear(0)) {
-        // refund the user, we don't need that money
-        Promise::new(env::predecessor_account_id()).transfer(extra_deposit);
+// The deposit is checked once the registration cost of the FT contract is known
+pub fn create(
+    funder: AccountId,
+    ft_contract: AccountId,
+    amount_per_drop: NearToken,
+    num_of_keys: u32,
+    fees: &FeeConfig,
+    config: DropConfig,
+) -> Result<FTDrop, DropError> {
+    if amount_per_drop.is_zero() {
+        return Err(DropError::AmountTooLow(NearToken::from_yoctonear(1)));
     }
 
-    assert!(
-        amount_per_drop.ge(&NearToken::from_yoctonear(1)),
-        "Amount per drop cannot be 0"
-    );
+    config.check_gas(MIN_GAS_FOR_FT_TRANSFER, FT_CLAIM_CALLBACK_GAS)?;
+    if let Some(vesting) = &config.vesting {
+        vesting.check_valid()?;
+    }
 
-    Drop::FT(FTDrop {
+    Ok(FTDrop {
         funder,
         ft_contract,
         amount: amount_per_drop,
         counter: num_of_keys,
         funded: false,
+        fee: fees.ft_fee(amount_per_drop, num_of_keys),
+        registration: NearToken::from_yoctonear(0),
+        config,
     })
 }
 
+impl FTDrop {
+    pub fn get_registration(&self) -> NearToken {
+        self.registration
+    }
+
+    pub fn set_registration(&mut self, registration: NearToken) {
+        self.registration = registration;
+    }
+
+    // Deposit needed on top of the storage, which is measured when the drop is saved
+    pub fn required_deposit(&self) -> NearToken {
+        required_deposit_per_key(self.registration)
+            .saturating_add(storage_cost(self.config.claim_storage()))
+            .saturating_mul(self.counter as u128)
+    }
+
+    // The FT fee follows the number of keys of a drop created in batches
+    pub fn set_fee(&mut self, fee: NearToken) {
+        self.fee = fee;
+    }
+
+    pub fn get_ft_contract(&self) -> &AccountId {
+        &self.ft_contract
+    }
+
+    pub fn is_funded(&self) -> bool {
+        self.funded
+    }
+
+    // FTs needed to fund the drop, fee included
+    pub fn required_funding(&self) -> NearToken {
+        self.amount
+            .saturating_mul(self.counter.into())
+            .saturating_add(self.fee)
+    }
+}
+
+// Asks the FT contract whether an account is registered
+pub fn promise_to_check_storage(ft_contract: &AccountId, account_id: &AccountId) -> Promise {
+    let storage_args = json!({ "account_id": account_id })
+        .to_string()
+        .into_bytes()
+        .to_vec();
+
+    Promise::new(ft_contract.clone()).function_call_weight(
+        "storage_balance_of".to_string(),
+        storage_args,
+        NearToken::from_yoctonear(0),
+        MIN_GAS_FOR_FT_STORAGE_BALANCE,
+        GasWeight(0),
+    )
+}
+
+// Transfers FTs, registering the receiver first when given the registration to pay
+pub fn promise_to_transfer(
+    ft_contract: &AccountId,
+    receiver_id: &AccountId,
+    amount: NearToken,
+    registration: Option<NearToken>,
+    transfer_gas: Gas,
+) -> Promise {
+    let mut transfer = Promise::new(ft_contract.clone());
+
+    if let Some(registration) = registration {
+        let deposit_args = json!({ "account_id": receiver_id })
+            .to_string()
+            .into_bytes()
+            .to_vec();
+
+        transfer = transfer.function_call_weight(
+            "storage_deposit".to_string(),
+            deposit_args,
+            registration,
+            MIN_GAS_FOR_FT_STORAGE_DEPOSIT,
+            GasWeight(0),
+        );
+    }
+
+    let transfer_args = json!({"receiver_id": receiver_id, "amount": U128(amount.as_yoctonear())})
+        .to_string()
+        .into_bytes()
+        .to_vec();
+
+    transfer.function_call_weight(
+        "ft_transfer".to_string(),
+        transfer_args,
+        NearToken::from_yoctonear(1),
+        transfer_gas,
+        GasWeight(0),
+    )
+}
+
 #[near]
 impl Contract {
     // Fund an existing drop
+    #[handle_result]
     pub fn ft_on_transfer(
         &mut self,
         sender_id: AccountId,
         amount: NearToken,
         msg: String,
-    ) -> PromiseOrValue<U128> {
-        let drop_id: u32 = msg.parse().unwrap(); 
-        let drop = self.drop_by_id.get(&drop_id).expect("Missing such drop_id");
-        let counter = drop.get_counter().unwrap();
-        let amount_per_drop = drop.get_amount_per_drop().unwrap();
-        let required_amount = amount_per_drop.saturating_mul(counter.into());
-        assert_eq!(
-            amount, required_amount,
-            "Wrong FT amount, expected {required_amount}"
-        );
+    ) -> Result<PromiseOrValue<U128>, DropError> {
+        // Anyone can fund a drop, only the FT contract is checked
+        let _ = sender_id;
+        self.check_not_paused()?;
+        let drop_id: DropId = msg.parse().map_err(|_| DropError::InvalidDropId(msg))?;
+        let drop = self
+            .internal_get_drop(&drop_id)
+            .ok_or(DropError::DropNotFound(drop_id))?;
+        self.check_drop_finalized(&drop_id)?;
 
-        // Make sure the drop exists
-        if let Drop::FT(FTDrop {
-            funder,
-            ft_contract,
-            amount,
-            counter,
-            funded,
-        }) = &drop
-        {
-            assert_eq!(
-                ft_contract,
-                &env::predecessor_account_id(),
-                "Wrong FTs, expected {ft_contract}"
-            );
-            // Update and insert again
-            self.drop_by_id.insert(
-                drop_id,
-                Drop::FT(FTDrop {
-                    funder: funder.clone(),
-                    ft_contract: ft_contract.clone(),
-                    amount: amount.clone(),
-                    counter: counter.clone(),
-                    funded: true,
-                }),
-            )
-        } else {
-            panic!("Not an FT drop")
+        let ft_drop = match drop {
+            Drop::FT(ft_drop) => ft_drop,
+            Drop::Merkle(merkle_drop) => {
+                self.fund_merkle_drop(drop_id, merkle_drop, amount)?;
+                self.assert_solvent();
+                return Ok(PromiseOrValue::Value(U128(0)));
+            }
+            _ => return Err(DropError::WrongDropType),
         };
 
+        let required_amount = ft_drop.required_funding();
+        if amount != required_amount {
+            return Err(DropError::WrongFtAmount(required_amount));
+        }
+        if ft_drop.ft_contract != env::predecessor_account_id() {
+            return Err(DropError::WrongFtContract(ft_drop.ft_contract));
+        }
+
+        let (ft_contract, fee) = (ft_drop.ft_contract.clone(), ft_drop.fee);
+        let mut funded_drop = ft_drop;
+        funded_drop.funded = true;
+        self.internal_update_drop(drop_id, Drop::FT(funded_drop));
+        self.add_ft_liability(ft_contract.clone(), amount.saturating_sub(fee));
+        self.collect_ft_fee(ft_contract, fee);
+
+        self.assert_solvent();
+
         // We do not return any tokens
-        PromiseOrValue::Value(U128(0))
+        Ok(PromiseOrValue::Value(U128(0)))
+    }
+
+    // Caches the registration cost of a new FT contract and finishes creating the drop, which
+    // gets its keys later if it is created in batches. Panicking here would keep the deposit,
+    // so failures refund the funder instead
+    #[private]
+    pub fn resolve_ft_storage_bounds(
+        &mut self,
+        public_keys: Vec<PublicKey>,
+        attached_deposit: NearToken,
+        drop: FTDrop,
+        batched: bool,
+        #[callback_result] bounds: Result<StorageBalanceBounds, PromiseError>,
+    ) -> Option<DropId> {
+        let Ok(bounds) = bounds else {
+            log!(
+                "Could not get storage_balance_bounds from {}",
+                drop.ft_contract
+            );
+            Promise::new(drop.funder).transfer(attached_deposit);
+            return None;
+        };
+
+        self.ft_storage_minimums
+            .insert(drop.ft_contract.clone(), bounds.min);
+        let drop = FTDrop {
+            registration: bounds.min,
+            ..drop
+        };
+
+        // Things may have changed while the bounds were fetched
+        let funder = drop.funder.clone();
+        let created = self
+            .check_not_paused()
+            .and_then(|_| self.check_keys_unused(&public_keys))
+            .and_then(|_| match batched {
+                true => self.internal_create_batched_ft_drop(drop, attached_deposit),
+                false => self.internal_create_ft_drop(drop, &public_keys, attached_deposit),
+            });
+
+        match created {
+            Ok(drop_id) => Some(drop_id),
+            Err(error) => {
+                log!("Could not create the drop. {error}");
+                Promise::new(funder).transfer(attached_deposit);
+                None
+            }
+        }
     }
 
+    #[private]
+    pub fn resolve_ft_storage_check(
+        account_id: AccountId,
+        account_created: bool,
+        storage_freed: NearToken,
+        claim: FTClaim,
+        #[callback_result] storage_balance: Result<Option<StorageBalance>, PromiseError>,
+    ) -> Promise {
+        // If the query failed we cannot know, so we try to register the claimer anyway
+        let registered = matches!(storage_balance, Ok(Some(_)));
+        promise_to_transfer(
+            &claim.ft_contract,
+            &account_id,
+            claim.amount,
+            (!registered).then_some(claim.registration),
+            claim.transfer_gas,
+        )
+        .then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(claim.callback_gas)
+                .with_unused_gas_weight(0)
+                .resolve_ft_claim(account_created, storage_freed, registered, claim),
+        )
+    }
+
+    #[private]
     pub fn resolve_ft_claim(
+        &mut self,
         account_created: bool,
-        drop_deleted: bool,
-        funder: AccountId,
-        amount: NearToken,
-        ft_contract: AccountId,
+        storage_freed: NearToken,
+        registered: bool,
+        claim: FTClaim,
         #[callback_result] result: Result<(), PromiseError>,
     ) -> bool {
-        let mut to_refund = ACCESS_KEY_STORAGE;
+        // The tokens were either sent to the claimer or are returned to the funder below
+        self.release_ft_liability(&claim.ft_contract, claim.amount);
+        self.settle_claim_refund(
+            ACCESS_KEY_STORAGE
+                .saturating_add(storage_freed)
+                .saturating_add(claim.registration)
+                .saturating_add(CREATE_ACCOUNT_FEE),
+        );
 
-        if !account_created {
-            to_refund = to_refund.saturating_add(CREATE_ACCOUNT_FEE);
+        let mut to_refund = ACCESS_KEY_STORAGE.saturating_add(storage_freed);
+
+        // The funder paid for the registration, give it back if it was not needed
+        if registered {
+            to_refund = to_refund.saturating_add(claim.registration);
         }
 
-        if drop_deleted {
-            to_refund = to_refund.saturating_add(required_storage_drop(0));
+        if !account_created {
+            to_refund = to_refund.saturating_add(CREATE_ACCOUNT_FEE);
         }
 
         if result.is_err() {
             // Return Tokens
-            let transfer_args =
-                json!({"receiver_id": funder, "amount": U128(amount.as_yoctonear())})
-                    .to_string()
-                    .into_bytes()
-                    .to_vec();
-
-            Promise::new(ft_contract).function_call_weight(
-                "ft_transfer".to_string(),
-                transfer_args,
-                NearToken::from_yoctonear(1),
-                MIN_GAS_FOR_FT_TRANSFER,
-                GasWeight(0),
+            promise_to_transfer(
+                &claim.ft_contract,
+                &claim.funder,
+                claim.amount,
+                None,
+                claim.transfer_gas,
             );
         }
 
         // Return NEAR
-        Promise::new(funder.clone()).transfer(to_refund);
+        Promise::new(claim.funder).transfer(to_refund);
 
-        true
+        result.is_ok()
     }
 }
@@ -0,0 +1,390 @@
+use std::collections::HashMap;
+
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde_json::json;
+use near_sdk::{env, log, near, AccountId, GasWeight, NearToken, Promise, PromiseError};
+
+use crate::constants::*;
+use crate::drop_types::{Drop, Getters};
+use crate::errors::DropError;
+use crate::{storage_cost, Contract, ContractExt};
+
+// NEAR the contract holds on behalf of the drops that are not claimed yet
+#[derive(Clone, Debug, Default, BorshDeserialize, BorshSerialize)]
+#[borsh(crate = "near_sdk::borsh")]
+pub struct Liabilities {
+    // NEAR to drop, account creation fees and FT registrations
+    pub near_owed: NearToken,
+    // Allowances of the claim keys, burnt as gas when claiming
+    pub allowance_reserved: NearToken,
+    // Storage paid by the funders, refunded when it is freed
+    pub storage_locked: NearToken,
+    // Refunds of claims waiting for their callback
+    pub refunds_pending: NearToken,
+}
+
+#[near(serializers = [json])]
+pub struct LiabilitiesView {
+    pub near_owed: NearToken,
+    pub allowance_reserved: NearToken,
+    pub storage_locked: NearToken,
+    pub refunds_pending: NearToken,
+    pub fees_owed: NearToken, // NEAR fees not withdrawn yet
+    pub total: NearToken,     // NEAR needed to cover all of the above
+    pub balance: NearToken,   // NEAR the contract holds
+    pub ft_owed: HashMap<AccountId, NearToken>, // FTs funded for unclaimed drops
+}
+
+#[near]
+impl Contract {
+    pub fn get_liabilities(&self) -> LiabilitiesView {
+        LiabilitiesView {
+            near_owed: self.liabilities.near_owed,
+            allowance_reserved: self.liabilities.allowance_reserved,
+            storage_locked: self.liabilities.storage_locked,
+            refunds_pending: self.liabilities.refunds_pending,
+            fees_owed: self.treasury,
+            total: self.total_near_liabilities(),
+            balance: env::account_balance(),
+            ft_owed: self
+                .ft_owed
+                .iter()
+                .map(|(ft_contract, amount)| (ft_contract.clone(), *amount))
+                .collect(),
+        }
+    }
+
+    // Sends the NEAR that no drop, fee or storage accounts for, such as unused allowances
+    #[handle_result]
+    pub fn sweep_surplus(&mut self, receiver: AccountId) -> Result<Promise, DropError> {
+        self.check_owner()?;
+
+        let reserved = self
+            .total_near_liabilities()
+            .saturating_add(storage_cost(env::storage_usage()));
+        let surplus = env::account_balance().saturating_sub(reserved);
+        if surplus.is_zero() {
+            return Err(DropError::NoSurplus);
+        }
+
+        log!("Sweeping {surplus} to {receiver}");
+        Ok(Promise::new(receiver).transfer(surplus))
+    }
+
+    // Sends the owner the FTs held above what drops and fees account for
+    #[handle_result]
+    pub fn sweep_ft(&mut self, ft_contract: AccountId) -> Result<Promise, DropError> {
+        self.check_owner()?;
+
+        let balance_args = json!({ "account_id": env::current_account_id() })
+            .to_string()
+            .into_bytes()
+            .to_vec();
+
+        Ok(Promise::new(ft_contract.clone())
+            .function_call_weight(
+                "ft_balance_of".to_string(),
+                balance_args,
+                NearToken::from_yoctonear(0),
+                MIN_GAS_FOR_FT_BALANCE_OF,
+                GasWeight(0),
+            )
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(SWEEP_FT_CALLBACK_GAS)
+                    .resolve_sweep_ft(ft_contract),
+            ))
+    }
+
+    #[private]
+    #[handle_result]
+    pub fn resolve_sweep_ft(
+        &mut self,
+        ft_contract: AccountId,
+        #[callback_result] balance: Result<U128, PromiseError>,
+    ) -> Result<Promise, DropError> {
+        let balance = balance.map_err(|_| DropError::BalanceUnavailable(ft_contract.clone()))?;
+        let balance = NearToken::from_yoctonear(balance.0);
+        // Tokens funding a drop may be counted before the drop records them
+        let owed = self
+            .ft_owed
+            .get(&ft_contract)
+            .cloned()
+            .unwrap_or(NearToken::from_yoctonear(0))
+            .saturating_add(self.get_fee_balance(Some(ft_contract.clone())))
+            .saturating_add(self.get_ft_unfunded(&ft_contract));
+        let surplus = balance.saturating_sub(owed);
+        if surplus.is_zero() {
+            return Err(DropError::NoSurplus);
+        }
+
+        log!("Sweeping {surplus} of {ft_contract} to {}", self.owner);
+        let transfer_args =
+            json!({"receiver_id": self.owner, "amount": U128(surplus.as_yoctonear())})
+                .to_string()
+                .into_bytes()
+                .to_vec();
+
+        Ok(Promise::new(ft_contract).function_call_weight(
+            "ft_transfer".to_string(),
+            transfer_args,
+            NearToken::from_yoctonear(1),
+            MIN_GAS_FOR_FT_TRANSFER,
+            GasWeight(0),
+        ))
+    }
+}
+
+impl Contract {
+    pub(crate) fn total_near_liabilities(&self) -> NearToken {
+        self.liabilities
+            .near_owed
+            .saturating_add(self.liabilities.allowance_reserved)
+            .saturating_add(self.liabilities.storage_locked)
+            .saturating_add(self.liabilities.refunds_pending)
+            .saturating_add(self.treasury)
+    }
+
+    // Only checked in debug builds, release builds skip it to save gas
+    pub(crate) fn assert_solvent(&self) {
+        if cfg!(debug_assertions) {
+            let total = self.total_near_liabilities();
+            let balance = env::account_balance();
+            assert!(
+                balance >= total,
+                "Contract is insolvent, it holds {balance} but owes {total}"
+            );
+        }
+    }
+
+    // Reserves what a new drop holds for its keys, `storage_used` being the storage it was charged
+    pub(crate) fn add_drop_liabilities(
+        &mut self,
+        drop: &Drop,
+        num_of_keys: u32,
+        storage_used: NearToken,
+    ) {
+        let keys = num_of_keys as u128;
+        let liabilities = &mut self.liabilities;
+
+        liabilities.near_owed = liabilities.near_owed.saturating_add(
+            near_owed_per_key(drop)
+                .saturating_mul(keys)
+                .saturating_add(near_owed_upfront(drop)),
+        );
+        liabilities.allowance_reserved = liabilities
+            .allowance_reserved
+            .saturating_add(ACCESS_KEY_ALLOWANCE.saturating_mul(keys));
+        liabilities.storage_locked = liabilities.storage_locked.saturating_add(
+            ACCESS_KEY_STORAGE
+                .saturating_add(storage_cost(drop.claim_storage()))
+                .saturating_mul(keys)
+                .saturating_add(storage_used)
+                .saturating_add(storage_reserve(drop)),
+        );
+    }
+
+    // Releases what a drop held for a claimed key, `storage_freed` being the storage refunded.
+    // Whatever the claim callback may refund stays owed until the callback settles it, the
+    // largest refund is returned
+    pub(crate) fn release_claim_liabilities(
+        &mut self,
+        drop: &Drop,
+        storage_freed: NearToken,
+    ) -> NearToken {
+        let liabilities = &mut self.liabilities;
+        let near_owed = near_owed_per_key(drop);
+        let storage = ACCESS_KEY_STORAGE
+            .saturating_add(storage_freed)
+            .saturating_add(storage_reserve(drop));
+
+        // The NEAR dropped leaves with the claim, the rest is refunded to the funder
+        let refund = match drop {
+            Drop::NEAR(_) | Drop::Staking(_) => {
+                near_owed.saturating_sub(drop.get_amount_per_drop().unwrap())
+            }
+            Drop::NFTMint(nft_mint_drop) => {
+                near_owed.saturating_sub(nft_mint_drop.get_mint_deposit())
+            }
+            _ => near_owed,
+        }
+        .saturating_add(storage);
+
+        liabilities.near_owed = liabilities.near_owed.saturating_sub(near_owed);
+        liabilities.allowance_reserved = liabilities
+            .allowance_reserved
+            .saturating_sub(ACCESS_KEY_ALLOWANCE);
+        liabilities.storage_locked = liabilities.storage_locked.saturating_sub(storage);
+        liabilities.refunds_pending = liabilities.refunds_pending.saturating_add(refund);
+
+        refund
+    }
+
+    // Releases what a drop held for keys it will not have, returning it to be refunded
+    pub(crate) fn release_unused_keys(&mut self, drop: &Drop, num_of_keys: u32) -> NearToken {
+        let keys = num_of_keys as u128;
+        let liabilities = &mut self.liabilities;
+        let near_owed = near_owed_per_key(drop).saturating_mul(keys);
+        let allowance = ACCESS_KEY_ALLOWANCE.saturating_mul(keys);
+        let storage = ACCESS_KEY_STORAGE
+            .saturating_add(storage_cost(drop.claim_storage()))
+            .saturating_mul(keys);
+
+        liabilities.near_owed = liabilities.near_owed.saturating_sub(near_owed);
+        liabilities.allowance_reserved = liabilities.allowance_reserved.saturating_sub(allowance);
+        liabilities.storage_locked = liabilities.storage_locked.saturating_sub(storage);
+
+        near_owed.saturating_add(allowance).saturating_add(storage)
+    }
+
+    // Charges `deposit` for the storage used since `initial_storage`, or adds the storage freed
+    // to what is refunded. `account_id` gets the rest back
+    pub(crate) fn settle_storage_change(
+        &mut self,
+        initial_storage: u64,
+        deposit: NearToken,
+        account_id: AccountId,
+    ) -> Result<(), DropError> {
+        self.flush_drops();
+        let final_storage = env::storage_usage();
+
+        let refund = if final_storage > initial_storage {
+            let storage_used = storage_cost(final_storage - initial_storage);
+            if deposit < storage_used {
+                return Err(DropError::InsufficientDeposit(storage_used));
+            }
+            self.add_storage_locked(storage_used);
+            deposit.saturating_sub(storage_used)
+        } else {
+            let storage_freed = storage_cost(initial_storage - final_storage);
+            self.release_storage_locked(storage_freed);
+            deposit.saturating_add(storage_freed)
+        };
+
+        if !refund.is_zero() {
+            Promise::new(account_id).transfer(refund);
+        }
+        self.assert_solvent();
+
+        Ok(())
+    }
+
+    pub(crate) fn add_storage_locked(&mut self, storage_used: NearToken) {
+        self.liabilities.storage_locked =
+            self.liabilities.storage_locked.saturating_add(storage_used);
+    }
+
+    pub(crate) fn release_storage_locked(&mut self, storage_freed: NearToken) {
+        self.liabilities.storage_locked = self
+            .liabilities
+            .storage_locked
+            .saturating_sub(storage_freed);
+    }
+
+    pub(crate) fn add_near_owed(&mut self, amount: NearToken) {
+        self.liabilities.near_owed = self.liabilities.near_owed.saturating_add(amount);
+    }
+
+    pub(crate) fn release_near_owed(&mut self, amount: NearToken) {
+        self.liabilities.near_owed = self.liabilities.near_owed.saturating_sub(amount);
+    }
+
+    // Called by the claim callbacks with the largest refund they could have made
+    pub(crate) fn settle_claim_refund(&mut self, refund: NearToken) {
+        self.liabilities.refunds_pending = self.liabilities.refunds_pending.saturating_sub(refund);
+    }
+
+    pub(crate) fn add_ft_liability(&mut self, ft_contract: AccountId, amount: NearToken) {
+        let owed = self
+            .ft_owed
+            .get(&ft_contract)
+            .cloned()
+            .unwrap_or(NearToken::from_yoctonear(0));
+        self.ft_owed
+            .insert(ft_contract, owed.saturating_add(amount));
+    }
+
+    // An FT contract credits the tokens of `ft_transfer_call` before `ft_on_transfer` funds the
+    // drop, so the FTs awaited by unfunded drops are kept out of `sweep_ft`
+    pub(crate) fn reserve_ft_funding(&mut self, drop: &Drop) {
+        if let Some((ft_contract, amount)) = ft_funding(drop) {
+            let unfunded = self.get_ft_unfunded(ft_contract);
+            self.ft_unfunded
+                .insert(ft_contract.clone(), unfunded.saturating_add(amount));
+        }
+    }
+
+    pub(crate) fn release_ft_funding(&mut self, drop: &Drop) {
+        let Some((ft_contract, amount)) = ft_funding(drop) else {
+            return;
+        };
+
+        let unfunded = self.get_ft_unfunded(ft_contract).saturating_sub(amount);
+        if unfunded.is_zero() {
+            self.ft_unfunded.remove(ft_contract);
+        } else {
+            self.ft_unfunded.insert(ft_contract.clone(), unfunded);
+        }
+    }
+
+    fn get_ft_unfunded(&self, ft_contract: &AccountId) -> NearToken {
+        self.ft_unfunded
+            .get(ft_contract)
+            .copied()
+            .unwrap_or(NearToken::from_yoctonear(0))
+    }
+
+    pub(crate) fn release_ft_liability(&mut self, ft_contract: &AccountId, amount: NearToken) {
+        let Some(owed) = self.ft_owed.get(ft_contract).cloned() else {
+            return;
+        };
+
+        let owed = owed.saturating_sub(amount);
+        if owed.is_zero() {
+            self.ft_owed.remove(ft_contract);
+        } else {
+            self.ft_owed.insert(ft_contract.clone(), owed);
+        }
+    }
+}
+
+// FTs an unfunded drop waits for, with their contract
+fn ft_funding(drop: &Drop) -> Option<(&AccountId, NearToken)> {
+    match drop {
+        Drop::FT(ft_drop) if !ft_drop.is_funded() => {
+            Some((ft_drop.get_ft_contract(), ft_drop.required_funding()))
+        }
+        Drop::Merkle(merkle_drop) => merkle_drop.ft_funding(),
+        _ => None,
+    }
+}
+
+// NEAR each key holds besides its allowance and storage, paid out or refunded when claimed
+fn near_owed_per_key(drop: &Drop) -> NearToken {
+    let owed = match drop {
+        Drop::NEAR(_) | Drop::Staking(_) => drop.get_amount_per_drop().unwrap(),
+        Drop::NFTMint(nft_mint_drop) => nft_mint_drop.get_mint_deposit(),
+        Drop::FT(ft_drop) => ft_drop.get_registration(),
+        Drop::NFT(_) | Drop::Merkle(_) | Drop::POAP(_) => NearToken::from_yoctonear(0),
+    };
+
+    owed.saturating_add(CREATE_ACCOUNT_FEE)
+}
+
+// NEAR a drop holds regardless of its keys, as Merkle airdrops which have none
+fn near_owed_upfront(drop: &Drop) -> NearToken {
+    match drop {
+        Drop::Merkle(merkle_drop) => merkle_drop.near_to_drop(),
+        _ => NearToken::from_yoctonear(0),
+    }
+}
+
+// Storage paid upfront for data the drop does not hold yet
+fn storage_reserve(drop: &Drop) -> NearToken {
+    match drop {
+        Drop::NFT(nft_drop) => nft_drop.unused_token_id_storage(),
+        Drop::Merkle(merkle_drop) => merkle_drop.get_bitmap_storage(),
+        _ => NearToken::from_yoctonear(0),
+    }
+}
@@ -1,124 +1,350 @@
-use constants::{DropId, ACCESS_KEY_ALLOWANCE};
-use drop_types::Drop;
-use near_sdk::store::LookupMap;
+use access_control::Role;
+use batched_drop::DropInCreation;
+use claim_log::{ClaimLog, ClaimLogEntry};
+use constants::{
+    DropId, ACCESS_KEY_ALLOWANCE, FT_STORAGE_BOUNDS_CALLBACK_GAS, MIN_GAS_FOR_FT_STORAGE_BALANCE,
+};
+use drop_types::{Drop, DropConfig, Getters, VersionedDrop};
+use errors::DropError;
+use fees::FeeConfig;
+use ft_drop::FTDrop;
+use liabilities::Liabilities;
+use near_sdk::store::{IterableMap, LookupMap, LookupSet};
 use near_sdk::{
-    env, near, AccountId, Allowance, BorshStorageKey, NearToken, PanicOnDefault, Promise, PublicKey,
+    env, near, AccountId, Allowance, BorshStorageKey, GasWeight, NearToken, PanicOnDefault,
+    Promise, PromiseOrValue, PublicKey,
 };
+use poap_drop::{EventMetadata, Sbt};
+use staking_drop::{Delegation, PoolUnstaking};
+use vesting::{VestingFunder, VestingGrant};
 
+mod access_control;
+mod allowlist;
+mod batched_drop;
 mod claim;
+mod claim_log;
+mod claim_preview;
 mod constants;
+mod deposit_estimate;
+mod drop_metadata;
 mod drop_types;
+mod eligibility;
+mod errors;
+mod fees;
 mod ft_drop;
+mod liabilities;
+pub mod merkle;
+mod merkle_drop;
 mod near_drop;
 mod nft_drop;
+mod nft_mint_drop;
+mod ownership;
+mod poap_drop;
+mod staking_drop;
+mod upgrade;
+mod vesting;
 
 #[derive(BorshStorageKey)]
 #[near]
 enum StorageKey {
     DropIdByKey,
     DropById,
+    Roles,
+    FtFees,
+    FtStorageMinimums,
+    FtOwed,
+    FtUnfunded,
+    ClaimsPerAccount,
+    MerkleClaims,
+    VestingGrants,
+    Delegations,
+    PoolPrincipals,
+    PoolUnstakings,
+    Sbts,
+    SbtClassesByOwner,
+    SbtClasses,
+    PendingDropOwners,
+    VestingFunders,
+    PausedDrops,
+    ClaimLogs,
+    ClaimLogEntries,
+    DropsInCreation,
 }
 
 #[derive(PanicOnDefault)]
 #[near(contract_state)]
 pub struct Contract {
     pub top_level_account: AccountId,
+    pub owner: AccountId,
+    pub roles: LookupMap<AccountId, Vec<Role>>,
+    pub paused: bool,
+    pub fees: FeeConfig,
+    pub treasury: NearToken,
+    pub ft_fees: LookupMap<AccountId, NearToken>,
+    pub ft_storage_minimums: LookupMap<AccountId, NearToken>,
+    pub liabilities: Liabilities,
+    pub ft_owed: IterableMap<AccountId, NearToken>,
+    pub ft_unfunded: LookupMap<AccountId, NearToken>,
     pub next_drop_id: DropId,
-    pub drop_by_id: LookupMap<DropId, Drop>,
+    pub drop_by_id: LookupMap<DropId, VersionedDrop>,
     pub drop_id_by_key: LookupMap<PublicKey, DropId>,
+    pub claims_per_account: LookupMap<(DropId, AccountId), u32>,
+    pub merkle_claims: LookupMap<(DropId, u32), u128>,
+    pub vesting_grants: LookupMap<(DropId, AccountId), VestingGrant>,
+    pub vesting_funders: LookupMap<DropId, VestingFunder>,
+    pub delegations: LookupMap<(AccountId, AccountId), Delegation>,
+    pub pool_principals: LookupMap<AccountId, NearToken>,
+    pub pool_unstakings: LookupMap<AccountId, PoolUnstaking>,
+    pub sbts: LookupMap<(DropId, AccountId), Sbt>,
+    pub sbt_classes_by_owner: LookupMap<AccountId, Vec<DropId>>,
+    pub sbt_classes: LookupMap<DropId, EventMetadata>,
+    pub sbt_supply: u64,
+    pub pending_drop_owners: LookupMap<DropId, AccountId>,
+    pub paused_drops: LookupSet<DropId>,
+    pub claim_logs: LookupMap<DropId, ClaimLog>,
+    pub claim_log_entries: LookupMap<(DropId, u64), ClaimLogEntry>,
+    pub drops_in_creation: LookupMap<DropId, DropInCreation>,
 }
 
 #[near]
 impl Contract {
     #[init]
     #[private]
-    pub fn new(top_level_account: AccountId) -> Self {
+    pub fn new(top_level_account: AccountId, owner: Option<AccountId>) -> Self {
         Self {
             top_level_account,
+            owner: owner.unwrap_or_else(env::predecessor_account_id),
+            roles: LookupMap::new(StorageKey::Roles),
+            paused: false,
+            fees: FeeConfig::default(),
+            treasury: NearToken::from_yoctonear(0),
+            ft_fees: LookupMap::new(StorageKey::FtFees),
+            ft_storage_minimums: LookupMap::new(StorageKey::FtStorageMinimums),
+            liabilities: Liabilities::default(),
+            ft_owed: IterableMap::new(StorageKey::FtOwed),
+            ft_unfunded: LookupMap::new(StorageKey::FtUnfunded),
             next_drop_id: 0,
             drop_id_by_key: LookupMap::new(StorageKey::DropIdByKey),
             drop_by_id: LookupMap::new(StorageKey::DropById),
+            claims_per_account: LookupMap::new(StorageKey::ClaimsPerAccount),
+            merkle_claims: LookupMap::new(StorageKey::MerkleClaims),
+            vesting_grants: LookupMap::new(StorageKey::VestingGrants),
+            vesting_funders: LookupMap::new(StorageKey::VestingFunders),
+            delegations: LookupMap::new(StorageKey::Delegations),
+            pool_principals: LookupMap::new(StorageKey::PoolPrincipals),
+            pool_unstakings: LookupMap::new(StorageKey::PoolUnstakings),
+            sbts: LookupMap::new(StorageKey::Sbts),
+            sbt_classes_by_owner: LookupMap::new(StorageKey::SbtClassesByOwner),
+            sbt_classes: LookupMap::new(StorageKey::SbtClasses),
+            sbt_supply: 0,
+            pending_drop_owners: LookupMap::new(StorageKey::PendingDropOwners),
+            paused_drops: LookupSet::new(StorageKey::PausedDrops),
+            claim_logs: LookupMap::new(StorageKey::ClaimLogs),
+            claim_log_entries: LookupMap::new(StorageKey::ClaimLogEntries),
+            drops_in_creation: LookupMap::new(StorageKey::DropsInCreation),
         }
     }
 
     #[payable]
+    #[handle_result]
     pub fn create_near_drop(
         &mut self,
         public_keys: Vec<PublicKey>,
         amount_per_drop: NearToken,
-    ) -> DropId {
+        config: Option<DropConfig>,
+    ) -> Result<DropId, DropError> {
+        self.check_not_paused()?;
+
         // check that the access keys are not already used
         // TODO: add test for that case
-        for public_key in public_keys.iter() {
-            assert!(
-                self.drop_id_by_key.get(public_key).is_none(),
-                "Public key is already used for a drop"
-            );
-        }
+        self.check_keys_unused(&public_keys)?;
 
         let num_of_keys = public_keys.len().try_into().unwrap();
 
-        let drop = near_drop::create(amount_per_drop, num_of_keys);
-        let drop_id = self.save_drop(drop);
-        self.save_drop_id_by_keys(&public_keys, drop_id);
+        let config = config.unwrap_or_default();
+        let required_deposit =
+            near_drop::required_deposit(amount_per_drop, num_of_keys, &config, &self.fees);
+        let drop = near_drop::create(
+            env::predecessor_account_id(),
+            amount_per_drop,
+            num_of_keys,
+            config,
+        )?;
 
-        drop_id
+        let drop_id = self.internal_create_drop(
+            drop,
+            &public_keys,
+            env::attached_deposit(),
+            required_deposit,
+        )?;
+        self.collect_near_fee(self.fees.near_fee(amount_per_drop, num_of_keys));
+        self.assert_solvent();
+
+        Ok(drop_id)
     }
 
+    // The registration cost of an FT contract is fetched the first time it is used,
+    // in which case the drop id is returned by the callback
     #[payable]
+    #[handle_result]
     pub fn create_ft_drop(
         &mut self,
         public_keys: Vec<PublicKey>,
         ft_contract: AccountId,
         amount_per_drop: NearToken,
-    ) -> DropId {
+        config: Option<DropConfig>,
+    ) -> Result<PromiseOrValue<DropId>, DropError> {
+        self.check_not_paused()?;
+
         // check that the access keys are not already used
-        for public_key in public_keys.iter() {
-            assert!(
-                self.drop_id_by_key.get(public_key).is_none(),
-                "Public key is already used for a drop"
-            );
-        }
+        self.check_keys_unused(&public_keys)?;
 
         let num_of_keys = public_keys.len().try_into().unwrap();
-        let drop = ft_drop::create(ft_contract, amount_per_drop, num_of_keys);
-        let drop_id = self.save_drop(drop);
-        self.save_drop_id_by_keys(&public_keys, drop_id);
+        let mut drop = ft_drop::create(
+            env::predecessor_account_id(),
+            ft_contract.clone(),
+            amount_per_drop,
+            num_of_keys,
+            &self.fees,
+            config.unwrap_or_default(),
+        )?;
+        let attached_deposit = env::attached_deposit();
 
-        drop_id
+        if let Some(registration) = self.ft_storage_minimums.get(&ft_contract) {
+            drop.set_registration(*registration);
+            let drop_id = self.internal_create_ft_drop(drop, &public_keys, attached_deposit)?;
+            return Ok(PromiseOrValue::Value(drop_id));
+        }
+
+        Ok(PromiseOrValue::Promise(
+            Promise::new(ft_contract)
+                .function_call_weight(
+                    "storage_balance_bounds".to_string(),
+                    vec![],
+                    NearToken::from_yoctonear(0),
+                    MIN_GAS_FOR_FT_STORAGE_BALANCE,
+                    GasWeight(0),
+                )
+                .then(
+                    Self::ext(env::current_account_id())
+                        .with_static_gas(FT_STORAGE_BOUNDS_CALLBACK_GAS)
+                        .resolve_ft_storage_bounds(public_keys, attached_deposit, drop, false),
+                ),
+        ))
     }
 
     #[payable]
-    pub fn create_nft_drop(&mut self, public_key: PublicKey, nft_contract: AccountId) -> DropId {
-        assert!(
-            self.drop_id_by_key.get(&public_key).is_none(),
-            "Public key is already used for a drop"
-        );
+    #[handle_result]
+    pub fn create_nft_drop(
+        &mut self,
+        public_key: PublicKey,
+        nft_contract: AccountId,
+        config: Option<DropConfig>,
+    ) -> Result<DropId, DropError> {
+        self.check_not_paused()?;
 
-        let drop = nft_drop::create(nft_contract);
-        let drop_id = self.save_drop(drop);
-        self.save_drop_id_by_key(public_key, drop_id);
+        let public_keys = [public_key];
+        self.check_keys_unused(&public_keys)?;
 
-        drop_id
+        let config = config.unwrap_or_default();
+        let required_deposit = nft_drop::required_deposit(&config);
+        let drop = nft_drop::create(env::predecessor_account_id(), nft_contract, config)?;
+
+        let drop_id = self.internal_create_drop(
+            drop,
+            &public_keys,
+            env::attached_deposit(),
+            required_deposit,
+        )?;
+        self.assert_solvent();
+
+        Ok(drop_id)
     }
 
-    pub fn get_drop_by_id(&self, drop_id: DropId) -> Drop {
-        self.drop_by_id
-            .get(&drop_id)
-            .expect("No drop information for such drop_id")
-            .to_owned()
+    #[handle_result]
+    pub fn get_drop_by_id(&self, drop_id: DropId) -> Result<Drop, DropError> {
+        self.internal_get_drop(&drop_id)
+            .ok_or(DropError::DropNotFound(drop_id))
     }
 
-    pub fn get_drop_id_by_key(&self, public_key: &PublicKey) -> &DropId {
+    #[handle_result]
+    pub fn get_drop_id_by_key(&self, public_key: PublicKey) -> Result<DropId, DropError> {
         self.drop_id_by_key
-            .get(public_key)
-            .expect("No drop for public key")
-            .into()
+            .get(&public_key)
+            .copied()
+            .ok_or(DropError::NoDropForKey)
     }
 
-    fn save_drop_id_by_key(&mut self, public_key: PublicKey, drop_id: DropId) -> Promise {
-        self.drop_id_by_key.insert(public_key.clone(), drop_id);
+    // Saves the drop and its keys, charging the funder for the storage they take on top of
+    // `required_deposit`. If the deposit falls short nothing is saved and the total is returned
+    fn internal_create_drop(
+        &mut self,
+        drop: Drop,
+        public_keys: &[PublicKey],
+        attached_deposit: NearToken,
+        required_deposit: NearToken,
+    ) -> Result<DropId, DropError> {
+        let funder = drop.get_funder().clone();
+        let initial_storage = env::storage_usage();
+
+        let drop_id = self.save_drop(drop.clone());
+        for public_key in public_keys.iter() {
+            self.drop_id_by_key.insert(public_key.clone(), drop_id);
+        }
+        self.open_claim_log(drop_id, &drop);
+        self.open_vesting_funder(drop_id, &drop);
+
+        self.flush_drops();
+        let storage_used = storage_cost(env::storage_usage().saturating_sub(initial_storage));
+        let required_deposit = required_deposit.saturating_add(storage_used);
+
+        if attached_deposit < required_deposit {
+            for public_key in public_keys.iter() {
+                self.drop_id_by_key.remove(public_key);
+            }
+            self.internal_remove_drop(&drop_id);
+            self.claim_logs.remove(&drop_id);
+            self.vesting_funders.remove(&drop_id);
+            self.next_drop_id -= 1;
+            return Err(DropError::InsufficientDeposit(required_deposit));
+        }
+
+        for public_key in public_keys.iter() {
+            self.add_claim_key(public_key.clone());
+        }
+        self.add_drop_liabilities(&drop, public_keys.len() as u32, storage_used);
+
+        let extra_deposit = attached_deposit.saturating_sub(required_deposit);
+        if extra_deposit.gt(&NearToken::from_yoctonear(0)) {
+            // refund the user, we don't need that money
+            Promise::new(funder).transfer(extra_deposit);
+        }
 
+        Ok(drop_id)
+    }
+
+    fn internal_create_ft_drop(
+        &mut self,
+        drop: FTDrop,
+        public_keys: &[PublicKey],
+        attached_deposit: NearToken,
+    ) -> Result<DropId, DropError> {
+        let key_fee = self.fees.key_fee(public_keys.len() as u32);
+        let required_deposit = drop.required_deposit().saturating_add(key_fee);
+
+        let drop_id = self.internal_create_drop(
+            Drop::FT(drop),
+            public_keys,
+            attached_deposit,
+            required_deposit,
+        )?;
+        self.collect_near_fee(key_fee);
+        self.assert_solvent();
+
+        Ok(drop_id)
+    }
+
+    fn add_claim_key(&self, public_key: PublicKey) -> Promise {
         // Add key so it can be used to call `claim_for` and `create_account_and_claim`
         Promise::new(env::current_account_id()).add_access_key_allowance(
             public_key,
@@ -128,16 +354,65 @@ impl Contract {
         )
     }
 
-    fn save_drop_id_by_keys(&mut self, public_keys: &Vec<PublicKey>, drop_id: DropId) {
-        for public_key in public_keys.iter() {
-            self.save_drop_id_by_key(public_key.clone(), drop_id.clone());
-        }
-    }
-
     fn save_drop(&mut self, drop: Drop) -> DropId {
         let drop_id = self.next_drop_id;
-        self.drop_by_id.insert(drop_id, drop);
+        self.internal_update_drop(drop_id, drop);
         self.next_drop_id += 1;
         drop_id
     }
 }
+
+impl Contract {
+    // Reads a drop, upgrading it to the current layout if it was stored by an older version
+    pub(crate) fn internal_get_drop(&self, drop_id: &DropId) -> Option<Drop> {
+        self.drop_by_id
+            .get(drop_id)
+            .map(|versioned_drop| versioned_drop.clone().into())
+    }
+
+    // Also keeps the FTs awaited by unfunded drops reserved, see `reserve_ft_funding`
+    pub(crate) fn internal_update_drop(&mut self, drop_id: DropId, drop: Drop) {
+        self.reserve_ft_funding(&drop);
+        if let Some(old_drop) = self.drop_by_id.insert(drop_id, drop.into()) {
+            self.release_ft_funding(&old_drop.into());
+        }
+    }
+
+    pub(crate) fn internal_remove_drop(&mut self, drop_id: &DropId) -> Option<Drop> {
+        let drop = Drop::from(self.drop_by_id.remove(drop_id)?);
+        self.release_ft_funding(&drop);
+        Some(drop)
+    }
+
+    pub(crate) fn check_keys_unused(&self, public_keys: &[PublicKey]) -> Result<(), DropError> {
+        if public_keys
+            .iter()
+            .any(|public_key| self.drop_id_by_key.contains_key(public_key))
+        {
+            return Err(DropError::KeyAlreadyUsed);
+        }
+        Ok(())
+    }
+
+    // Writes pending changes so `env::storage_usage` reflects them
+    pub(crate) fn flush_drops(&mut self) {
+        self.drop_by_id.flush();
+        self.drop_id_by_key.flush();
+        self.claims_per_account.flush();
+        self.vesting_grants.flush();
+        self.vesting_funders.flush();
+        self.delegations.flush();
+        self.pool_principals.flush();
+        self.sbts.flush();
+        self.sbt_classes_by_owner.flush();
+        self.sbt_classes.flush();
+        self.pending_drop_owners.flush();
+        self.claim_logs.flush();
+        self.claim_log_entries.flush();
+        self.drops_in_creation.flush();
+    }
+}
+
+pub(crate) fn storage_cost(bytes: u64) -> NearToken {
+    env::storage_byte_cost().saturating_mul(bytes as u128)
+}
@@ -0,0 +1,123 @@
+use near_sdk::{borsh, env, AccountId, CryptoHash, NearToken};
+
+// Leaves and inner nodes are hashed with different prefixes so one cannot pass for the other
+const LEAF_PREFIX: u8 = 0;
+const NODE_PREFIX: u8 = 1;
+
+// Pads airdrop trees to a power of two, no leaf hashes to it
+const EMPTY_LEAF: CryptoHash = [0; 32];
+
+pub fn leaf_hash(data: &[u8]) -> CryptoHash {
+    env::sha256_array(&[&[LEAF_PREFIX], data].concat())
+}
+
+// Leaf of an allowlist tree
+pub fn allowlist_leaf(account_id: &AccountId) -> CryptoHash {
+    leaf_hash(account_id.as_bytes())
+}
+
+// Leaf of a Merkle airdrop, the index being the recipient's bit in the claimed bitmap
+pub fn airdrop_leaf(index: u32, account_id: &AccountId, amount: NearToken) -> CryptoHash {
+    let data = borsh::to_vec(&(index, account_id, amount.as_yoctonear())).unwrap();
+    leaf_hash(&data)
+}
+
+// Pairs are hashed in sorted order, which lets proofs leave out the side of each sibling
+pub fn verify(proof: &[CryptoHash], root: &CryptoHash, leaf: CryptoHash) -> bool {
+    let computed = proof
+        .iter()
+        .fold(leaf, |hash, sibling| sorted_node_hash(&hash, sibling));
+
+    &computed == root
+}
+
+// The bits of the index give the side of each sibling, so a proof only fits one position
+pub fn verify_indexed(
+    proof: &[CryptoHash],
+    root: &CryptoHash,
+    leaf: CryptoHash,
+    index: u32,
+) -> bool {
+    let computed = proof
+        .iter()
+        .enumerate()
+        .fold(leaf, |hash, (level, sibling)| {
+            if (index >> level) & 1 == 0 {
+                node_hash(&hash, sibling)
+            } else {
+                node_hash(sibling, &hash)
+            }
+        });
+
+    &computed == root
+}
+
+// Levels of an airdrop tree from the leaves up to the root, built off-chain
+pub fn build_tree(leaves: &[CryptoHash]) -> Vec<Vec<CryptoHash>> {
+    let mut level = leaves.to_vec();
+    level.resize(leaves.len().next_power_of_two(), EMPTY_LEAF);
+
+    let mut levels = vec![level];
+    while levels[levels.len() - 1].len() > 1 {
+        let next = levels[levels.len() - 1]
+            .chunks(2)
+            .map(|pair| node_hash(&pair[0], &pair[1]))
+            .collect();
+        levels.push(next);
+    }
+
+    levels
+}
+
+// Siblings of a leaf from the bottom of the tree up, as checked by `verify_indexed`
+pub fn proof(levels: &[Vec<CryptoHash>], index: usize) -> Vec<CryptoHash> {
+    levels[..levels.len() - 1]
+        .iter()
+        .enumerate()
+        .map(|(level, nodes)| nodes[(index >> level) ^ 1])
+        .collect()
+}
+
+// Levels of an allowlist tree from the leaves up to the root, built off-chain. A node left
+// without a pair moves up to the next level as is
+pub fn build_sorted_tree(leaves: &[CryptoHash]) -> Vec<Vec<CryptoHash>> {
+    let mut levels = vec![leaves.to_vec()];
+    while levels[levels.len() - 1].len() > 1 {
+        let next = levels[levels.len() - 1]
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => sorted_node_hash(left, right),
+                _ => pair[0],
+            })
+            .collect();
+        levels.push(next);
+    }
+
+    levels
+}
+
+// Siblings of a leaf from the bottom of an allowlist tree up, as checked by `verify`
+pub fn sorted_proof(levels: &[Vec<CryptoHash>], index: usize) -> Vec<CryptoHash> {
+    levels[..levels.len() - 1]
+        .iter()
+        .enumerate()
+        .filter_map(|(level, nodes)| nodes.get((index >> level) ^ 1).copied())
+        .collect()
+}
+
+// Length of the proofs of a tree with that many leaves
+pub fn tree_depth(num_leaves: u32) -> u32 {
+    num_leaves.next_power_of_two().trailing_zeros()
+}
+
+fn node_hash(left: &CryptoHash, right: &CryptoHash) -> CryptoHash {
+    env::sha256_array(&[&[NODE_PREFIX], left.as_slice(), right.as_slice()].concat())
+}
+
+fn sorted_node_hash(a: &CryptoHash, b: &CryptoHash) -> CryptoHash {
+    if a <= b {
+        node_hash(a, b)
+    } else {
+        node_hash(b, a)
+    }
+}
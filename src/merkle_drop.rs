@@ -0,0 +1,405 @@
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::Base58CryptoHash;
+use near_sdk::{env, log, near, AccountId, CryptoHash, Gas, NearToken, Promise, PromiseError};
+
+use crate::constants::*;
+use crate::drop_types::{DropConfig, Getters, Setters};
+use crate::errors::DropError;
+use crate::{ft_drop, merkle, storage_cost, Contract, ContractExt, Drop};
+
+// Recipients claimed in one word of the bitmap
+const BITMAP_WORD_BITS: u32 = 128;
+
+// Airdrop to the recipients of a Merkle tree, which claim with a proof instead of a key
+#[derive(Clone, Debug, BorshDeserialize, BorshSerialize)]
+#[near(serializers = [json])]
+#[borsh(crate = "near_sdk::borsh")]
+pub struct MerkleDrop {
+    funder: AccountId,              // Account which created the drop and funded it
+    root: Base58CryptoHash,         // Root of the tree of (index, account_id, amount) leaves
+    num_leaves: u32,                // Number of recipients in the tree
+    total: NearToken,               // Sum of the amounts in the tree
+    claimed: NearToken,             // Amount paid out so far
+    ft_contract: Option<AccountId>, // Contract of the fungible tokens to drop, NEAR if none
+    funded: bool,                   // Reflects if the drop is funded, NEAR drops are at creation
+    fee: NearToken,                 // Protocol fee in fungible tokens, collected when funded
+    bitmap_storage: NearToken,      // Storage paid upfront for the claimed bitmap
+    config: DropConfig,             // Settings chosen by the funder
+}
+
+#[near(serializers = [json])]
+pub struct MerkleProof {
+    pub index: u32,                    // Position of the leaf in the tree
+    pub hashes: Vec<Base58CryptoHash>, // Siblings of the leaf from the bottom up
+}
+
+impl Getters for MerkleDrop {
+    fn get_funder(&self) -> &AccountId {
+        &self.funder
+    }
+
+    fn get_config(&self) -> &DropConfig {
+        &self.config
+    }
+
+    fn get_counter(&self) -> Result<u32, DropError> {
+        Err(DropError::NoCounter)
+    }
+
+    fn get_amount_per_drop(&self) -> Result<NearToken, DropError> {
+        Err(DropError::NoAmountPerDrop)
+    }
+}
+
+impl Setters for MerkleDrop {
+    fn set_counter(&mut self, _value: u32) -> Result<(), DropError> {
+        Err(DropError::NoCounter)
+    }
+
+    fn set_config(&mut self, config: DropConfig) {
+        self.config = config;
+    }
+
+    fn set_funder(&mut self, funder: AccountId) {
+        self.funder = funder;
+    }
+}
+
+impl MerkleDrop {
+    // NEAR held for the recipients, FT drops hold tokens instead
+    pub fn near_to_drop(&self) -> NearToken {
+        match self.ft_contract {
+            None => self.total,
+            Some(_) => NearToken::from_yoctonear(0),
+        }
+    }
+
+    pub fn get_bitmap_storage(&self) -> NearToken {
+        self.bitmap_storage
+    }
+
+    // FTs the drop waits for until it is funded, fee included
+    pub fn ft_funding(&self) -> Option<(&AccountId, NearToken)> {
+        match &self.ft_contract {
+            Some(ft_contract) if !self.funded => {
+                Some((ft_contract, self.total.saturating_add(self.fee)))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[near]
+impl Contract {
+    // Creates an airdrop for the leaves of `root`, paying `total` in NEAR or, if an FT contract
+    // is given, in FTs sent afterwards with `ft_transfer_call`
+    #[payable]
+    #[handle_result]
+    pub fn create_merkle_drop(
+        &mut self,
+        root: Base58CryptoHash,
+        num_leaves: u32,
+        total: NearToken,
+        ft_contract: Option<AccountId>,
+        config: Option<DropConfig>,
+    ) -> Result<DropId, DropError> {
+        self.check_not_paused()?;
+
+        if num_leaves == 0 {
+            return Err(DropError::NoRecipients);
+        }
+        if total.is_zero() {
+            return Err(DropError::AmountTooLow(NearToken::from_yoctonear(1)));
+        }
+
+        let config = config.unwrap_or_default();
+        let min_transfer_gas = match ft_contract {
+            Some(_) => MIN_GAS_FOR_FT_TRANSFER,
+            None => Gas::from_tgas(0),
+        };
+        config.check_gas(min_transfer_gas, MERKLE_CLAIM_CALLBACK_GAS)?;
+        if config.vesting.is_some() {
+            return Err(DropError::UnsupportedConfig("Merkle drops cannot vest"));
+        }
+        if config.claim_log {
+            return Err(DropError::UnsupportedConfig(
+                "Merkle drops record their claims in the claimed bitmap",
+            ));
+        }
+        if config.allowlist.is_some()
+            || config.eligibility.is_some()
+            || config.max_claims_per_account.is_some()
+        {
+            return Err(DropError::UnsupportedConfig(
+                "Merkle drops can only be claimed by the recipients of the tree",
+            ));
+        }
+        if config.require_new_key {
+            return Err(DropError::UnsupportedConfig(
+                "Merkle drops pay existing accounts, they create none",
+            ));
+        }
+
+        // Bitmap words are written as recipients claim, so they are paid upfront at the
+        // storage measured for the first one
+        let drop_id = self.next_drop_id;
+        let initial_storage = env::storage_usage();
+        self.merkle_claims.insert((drop_id, 0), 0);
+        self.merkle_claims.flush();
+        let word_storage = storage_cost(env::storage_usage().saturating_sub(initial_storage));
+        let bitmap_storage =
+            word_storage.saturating_mul(num_leaves.div_ceil(BITMAP_WORD_BITS) as u128);
+
+        let (near_fee, ft_fee) = match ft_contract {
+            None => (self.fees.near_share(total), NearToken::from_yoctonear(0)),
+            Some(_) => (NearToken::from_yoctonear(0), self.fees.ft_fee(total, 1)),
+        };
+
+        let drop = MerkleDrop {
+            funder: env::predecessor_account_id(),
+            root,
+            num_leaves,
+            total,
+            claimed: NearToken::from_yoctonear(0),
+            funded: ft_contract.is_none(),
+            ft_contract,
+            fee: ft_fee,
+            bitmap_storage,
+            config,
+        };
+        let required_deposit = drop
+            .near_to_drop()
+            .saturating_add(near_fee)
+            .saturating_add(bitmap_storage);
+
+        let drop_id = self.internal_create_drop(
+            Drop::Merkle(drop),
+            &[],
+            env::attached_deposit(),
+            required_deposit,
+        )?;
+        self.collect_near_fee(near_fee);
+        self.assert_solvent();
+
+        Ok(drop_id)
+    }
+
+    // Pays `amount` to `account_id` if the proof shows they are in the tree. Anyone can
+    // submit the claim, the tokens always go to the listed account
+    #[handle_result]
+    pub fn claim_merkle(
+        &mut self,
+        drop_id: DropId,
+        account_id: AccountId,
+        amount: NearToken,
+        proof: MerkleProof,
+    ) -> Result<Promise, DropError> {
+        self.check_not_paused()?;
+        self.check_drop_not_paused(&drop_id)?;
+
+        let mut drop = match self.internal_get_drop(&drop_id) {
+            Some(Drop::Merkle(drop)) => drop,
+            Some(_) => return Err(DropError::WrongDropType),
+            None => return Err(DropError::DropNotFound(drop_id)),
+        };
+        if !drop.funded {
+            return Err(DropError::NotFunded);
+        }
+
+        // Leaves out of range and proofs of the wrong length are rejected as invalid
+        let index = proof.index;
+        let hashes: Vec<CryptoHash> = proof.hashes.into_iter().map(CryptoHash::from).collect();
+        let leaf = merkle::airdrop_leaf(index, &account_id, amount);
+        if index >= drop.num_leaves
+            || hashes.len() as u32 != merkle::tree_depth(drop.num_leaves)
+            || !merkle::verify_indexed(&hashes, &CryptoHash::from(drop.root), leaf, index)
+        {
+            return Err(DropError::InvalidProof);
+        }
+
+        if self.set_merkle_claimed(drop_id, index, true) {
+            return Err(DropError::AlreadyClaimed);
+        }
+
+        // A tree adding up to more than was funded cannot take from other drops
+        drop.claimed = drop.claimed.saturating_add(amount);
+        if drop.claimed > drop.total {
+            return Err(DropError::NotEnoughLeft);
+        }
+        self.internal_update_drop(drop_id, Drop::Merkle(drop.clone()));
+
+        let transfer = match &drop.ft_contract {
+            None => {
+                self.release_near_owed(amount);
+                Promise::new(account_id).transfer(amount)
+            }
+            Some(ft_contract) => ft_drop::promise_to_transfer(
+                ft_contract,
+                &account_id,
+                amount,
+                None,
+                drop.config.transfer_gas(MIN_GAS_FOR_FT_TRANSFER),
+            ),
+        };
+
+        Ok(transfer.then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(drop.config.callback_gas(MERKLE_CLAIM_CALLBACK_GAS))
+                .with_unused_gas_weight(0)
+                .resolve_merkle_claim(drop_id, index, amount),
+        ))
+    }
+
+    // Sends the funder what has not been claimed yet, after which nothing else can be claimed.
+    // Claims in flight that fail can still be claimed again
+    #[handle_result]
+    pub fn reclaim_merkle_drop(&mut self, drop_id: DropId) -> Result<Promise, DropError> {
+        self.check_not_paused()?;
+        let mut drop = match self.internal_get_drop_as_funder(&drop_id)? {
+            Drop::Merkle(drop) => drop,
+            _ => return Err(DropError::WrongDropType),
+        };
+        if !drop.funded {
+            return Err(DropError::NotFunded);
+        }
+
+        let amount = drop.total.saturating_sub(drop.claimed);
+        if amount.is_zero() {
+            return Err(DropError::NotEnoughLeft);
+        }
+        drop.total = drop.claimed;
+        self.internal_update_drop(drop_id, Drop::Merkle(drop.clone()));
+
+        match &drop.ft_contract {
+            None => {
+                self.release_near_owed(amount);
+                Ok(Promise::new(drop.funder).transfer(amount))
+            }
+            Some(ft_contract) => {
+                self.release_ft_liability(ft_contract, amount);
+                Ok(ft_drop::promise_to_transfer(
+                    ft_contract,
+                    &drop.funder,
+                    amount,
+                    None,
+                    MIN_GAS_FOR_FT_TRANSFER,
+                )
+                .then(
+                    Self::ext(env::current_account_id())
+                        .with_static_gas(MERKLE_RECLAIM_CALLBACK_GAS)
+                        .with_unused_gas_weight(0)
+                        .resolve_merkle_reclaim(drop_id, amount),
+                ))
+            }
+        }
+    }
+
+    pub fn is_merkle_claimed(&self, drop_id: DropId, index: u32) -> bool {
+        let word = self
+            .merkle_claims
+            .get(&(drop_id, index / BITMAP_WORD_BITS))
+            .copied()
+            .unwrap_or(0);
+
+        word & (1u128 << (index % BITMAP_WORD_BITS)) != 0
+    }
+
+    // A failed transfer leaves the recipient unclaimed so they can try again, for example
+    // after registering on the FT contract
+    #[private]
+    pub fn resolve_merkle_claim(
+        &mut self,
+        drop_id: DropId,
+        index: u32,
+        amount: NearToken,
+        #[callback_result] result: Result<(), PromiseError>,
+    ) -> bool {
+        let Some(Drop::Merkle(mut drop)) = self.internal_get_drop(&drop_id) else {
+            panic!("Not a Merkle drop")
+        };
+
+        if result.is_ok() {
+            if let Some(ft_contract) = &drop.ft_contract {
+                self.release_ft_liability(ft_contract, amount);
+            }
+            return true;
+        }
+
+        log!("Claiming leaf {index} of drop {drop_id} failed, it can be claimed again");
+        self.set_merkle_claimed(drop_id, index, false);
+        drop.claimed = drop.claimed.saturating_sub(amount);
+        if drop.ft_contract.is_none() {
+            self.add_near_owed(amount);
+        }
+        self.internal_update_drop(drop_id, Drop::Merkle(drop));
+
+        false
+    }
+
+    // FTs the funder could not receive go back to the drop, to be reclaimed again
+    #[private]
+    pub fn resolve_merkle_reclaim(
+        &mut self,
+        drop_id: DropId,
+        amount: NearToken,
+        #[callback_result] result: Result<(), PromiseError>,
+    ) -> bool {
+        if result.is_ok() {
+            return true;
+        }
+
+        let Some(Drop::Merkle(mut drop)) = self.internal_get_drop(&drop_id) else {
+            panic!("Not a Merkle drop")
+        };
+        log!("Reclaiming drop {drop_id} failed, {amount} can be reclaimed again");
+        if let Some(ft_contract) = drop.ft_contract.clone() {
+            self.add_ft_liability(ft_contract, amount);
+        }
+        drop.total = drop.total.saturating_add(amount);
+        self.internal_update_drop(drop_id, Drop::Merkle(drop));
+
+        false
+    }
+}
+
+impl Contract {
+    // Funds a Merkle airdrop of FTs, called from `ft_on_transfer`
+    pub(crate) fn fund_merkle_drop(
+        &mut self,
+        drop_id: DropId,
+        mut drop: MerkleDrop,
+        amount: NearToken,
+    ) -> Result<(), DropError> {
+        let ft_contract = drop.ft_contract.clone().ok_or(DropError::WrongDropType)?;
+        if ft_contract != env::predecessor_account_id() {
+            return Err(DropError::WrongFtContract(ft_contract));
+        }
+        if drop.funded {
+            return Err(DropError::AlreadyFunded(drop_id));
+        }
+
+        let required_amount = drop.total.saturating_add(drop.fee);
+        if amount != required_amount {
+            return Err(DropError::WrongFtAmount(required_amount));
+        }
+
+        drop.funded = true;
+        let (total, fee) = (drop.total, drop.fee);
+        self.internal_update_drop(drop_id, Drop::Merkle(drop));
+        self.add_ft_liability(ft_contract.clone(), total);
+        self.collect_ft_fee(ft_contract, fee);
+        Ok(())
+    }
+
+    // Sets or clears the bit of a recipient, returning whether it was set before
+    fn set_merkle_claimed(&mut self, drop_id: DropId, index: u32, claimed: bool) -> bool {
+        let key = (drop_id, index / BITMAP_WORD_BITS);
+        let bit = 1u128 << (index % BITMAP_WORD_BITS);
+        let word = self.merkle_claims.get(&key).copied().unwrap_or(0);
+
+        let updated_word = if claimed { word | bit } else { word & !bit };
+        self.merkle_claims.insert(key, updated_word);
+
+        word & bit != 0
+    }
+}
@@ -1,47 +1,96 @@
 use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
-use near_sdk::{env, near, AccountId, NearToken, Promise, PromiseError};
+use near_sdk::{env, near, AccountId, Gas, NearToken, Promise, PromiseError};
 
 use crate::constants::*;
-use crate::drop_types::{Dropper, Getters, Setters};
-use crate::{Contract, ContractExt, Drop};
+use crate::drop_types::{DropConfig, Dropper, Getters, Setters};
+use crate::errors::DropError;
+use crate::fees::FeeConfig;
+use crate::{storage_cost, Contract, ContractExt, Drop};
 
 #[derive(Clone, Debug, BorshDeserialize, BorshSerialize)]
 #[near(serializers = [json])]
 #[borsh(crate = "near_sdk::borsh")]
 pub struct NearDrop {
-    funder: AccountId, // An account which created the drop and funded it
-    amount: NearToken, // Reflects how much NEAR tokens will be transfer to claiming user
-    counter: u32,      // Reflects how much times the drop can be claimed
+    funder: AccountId,  // An account which created the drop and funded it
+    amount: NearToken,  // Reflects how much NEAR tokens will be transfer to claiming user
+    counter: u32,       // Reflects how much times the drop can be claimed
+    config: DropConfig, // Settings chosen by the funder
+}
+
+// Layout of `NearDrop` before drops were versioned, kept to read drops stored back then
+#[derive(Clone, Debug, BorshDeserialize, BorshSerialize)]
+#[borsh(crate = "near_sdk::borsh")]
+pub struct NearDropV1 {
+    funder: AccountId,
+    amount: NearToken,
+    counter: u32,
+}
+
+impl From<NearDropV1> for NearDrop {
+    fn from(near_drop: NearDropV1) -> Self {
+        NearDrop {
+            funder: near_drop.funder,
+            amount: near_drop.amount,
+            counter: near_drop.counter,
+            config: DropConfig::default(),
+        }
+    }
 }
 
 impl Dropper for NearDrop {
-    fn promise_for_claiming(&self, account_id: AccountId) -> Promise {
-        Promise::new(account_id).transfer(self.amount)
+    fn promise_for_claiming(&self, account_id: AccountId) -> Result<Promise, DropError> {
+        Ok(Promise::new(account_id).transfer(self.amount))
     }
 
-    fn promise_to_resolve_claim(&self, account_created: bool, drop_deleted: bool) -> Promise {
-        Contract::ext(env::current_account_id())
-            .with_static_gas(CLAIM_CALLBACK_GAS)
+    fn promise_to_resolve_claim(
+        &self,
+        _account_id: AccountId,
+        account_created: bool,
+        storage_freed: NearToken,
+    ) -> Result<Promise, DropError> {
+        Ok(Contract::ext(env::current_account_id())
+            .with_static_gas(self.config.callback_gas(CLAIM_CALLBACK_GAS))
             .with_unused_gas_weight(0)
-            .resolve_near_claim(account_created, drop_deleted, self.funder.clone(), self.amount)
+            .resolve_near_claim(
+                account_created,
+                storage_freed,
+                self.funder.clone(),
+                self.amount,
+            ))
     }
 }
 
 impl Getters for NearDrop {
-    fn get_counter(&self) -> Result<u32, &str> {
+    fn get_funder(&self) -> &AccountId {
+        &self.funder
+    }
+
+    fn get_config(&self) -> &DropConfig {
+        &self.config
+    }
+
+    fn get_counter(&self) -> Result<u32, DropError> {
         Ok(self.counter)
     }
 
-    fn get_amount_per_drop(&self) -> Result<NearToken, &str> {
+    fn get_amount_per_drop(&self) -> Result<NearToken, DropError> {
         Ok(self.amount)
     }
 }
 
 impl Setters for NearDrop {
-    fn set_counter(&mut self, value: u32) -> Result<(), &str> {
+    fn set_counter(&mut self, value: u32) -> Result<(), DropError> {
         self.counter = value;
         Ok(())
     }
+
+    fn set_config(&mut self, config: DropConfig) {
+        self.config = config;
+    }
+
+    fn set_funder(&mut self, funder: AccountId) {
+        self.funder = funder;
+    }
 }
 
 pub fn required_deposit_per_key(drop_amount: NearToken) -> NearToken {
@@ -51,74 +100,76 @@ pub fn required_deposit_per_key(drop_amount: NearToken) -> NearToken {
         .saturating_add(ACCESS_KEY_STORAGE)
 }
 
-pub fn required_storage_drop(num_access_keys: u32) -> NearToken {
-    NearToken::from_yoctonear(
-        // DropId -> Drop::Near
-        ID_STORAGE + ENUM_STORAGE + ACC_STORAGE + TOKEN_AMOUNT_STORAGE + 8 
-        // PublicKey -> DropId
-        + num_access_keys as u128 * (PK_STORAGE + ID_STORAGE)
-    )
+// Deposit needed on top of the storage, which is measured when the drop is saved
+pub fn required_deposit(
+    amount_per_drop: NearToken,
+    num_of_keys: u32,
+    config: &DropConfig,
+    fees: &FeeConfig,
+) -> NearToken {
+    required_deposit_per_key(amount_per_drop)
+        .saturating_add(storage_cost(config.claim_storage()))
+        .saturating_mul(num_of_keys as u128)
+        .saturating_add(fees.near_fee(amount_per_drop, num_of_keys))
 }
 
-pub fn create(amount_per_drop: NearToken, num_of_keys: u32) -> Drop {
-    let funder = env::predecessor_account_id();
-
-    let attached_deposit = env::attached_deposit();
-    let required_deposit = // required_storage_drop + (required_deposit_per_key * num_of_keys)
-        required_storage_drop(num_of_keys)
-        .saturating_add(
-            required_deposit_per_key(amount_per_drop)
-                .saturating_mul(num_of_keys as u128),
-        );
-
-    assert!(
-        attached_deposit >= required_deposit,
-        "Please attach at least {required_deposit}"
-    );
-
-    let extra_deposit = attached_deposit.saturating_sub(required_deposit);
-    if extra_deposit.gt(&NearToken::from_yoctonear(0)) {
-        // refund the user, we don't need that money
-        Promise::new(env::predecessor_account_id()).transfer(extra_deposit);
+pub fn create(
+    funder: AccountId,
+    amount_per_drop: NearToken,
+    num_of_keys: u32,
+    config: DropConfig,
+) -> Result<Drop, DropError> {
+    if amount_per_drop.is_zero() {
+        return Err(DropError::AmountTooLow(NearToken::from_yoctonear(1)));
     }
 
-    assert!(
-        amount_per_drop.ge(&NearToken::from_yoctonear(1)),
-        "Amount per drop should be at least 1 yN"
-    );
+    if config.transfer_gas.is_some() {
+        return Err(DropError::UnsupportedConfig(
+            "NEAR drops do not use transfer_gas",
+        ));
+    }
+    config.check_gas(Gas::from_tgas(0), CLAIM_CALLBACK_GAS)?;
+    if let Some(vesting) = &config.vesting {
+        vesting.check_valid()?;
+    }
 
-    Drop::NEAR(NearDrop {
+    Ok(Drop::NEAR(NearDrop {
         funder,
         amount: amount_per_drop,
         counter: num_of_keys,
-    })
+        config,
+    }))
 }
 
 #[near]
 impl Contract {
+    #[private]
     pub fn resolve_near_claim(
-      account_created: bool,
-        drop_deleted: bool,
+        &mut self,
+        account_created: bool,
+        storage_freed: NearToken,
         funder: AccountId,
         amount: NearToken,
         #[callback_result] result: Result<(), PromiseError>,
     ) -> bool {
-        let mut to_refund = ACCESS_KEY_STORAGE;
+        self.settle_claim_refund(
+            ACCESS_KEY_STORAGE
+                .saturating_add(storage_freed)
+                .saturating_add(CREATE_ACCOUNT_FEE),
+        );
+
+        let mut to_refund = ACCESS_KEY_STORAGE.saturating_add(storage_freed);
 
         if !account_created {
             to_refund = to_refund.saturating_add(CREATE_ACCOUNT_FEE);
         }
 
-        if drop_deleted {
-            to_refund = to_refund.saturating_add(required_storage_drop(0));
-        }
-
         if result.is_err() {
             to_refund = to_refund.saturating_add(amount);
         }
 
         // Return the money
         Promise::new(funder).transfer(to_refund);
-        true
+        result.is_ok()
     }
 }
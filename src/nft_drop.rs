@@ -7,8 +7,9 @@ use near_sdk::{
 };
 
 use crate::constants::*;
-use crate::drop_types::{Dropper, Getters};
-use crate::Drop;
+use crate::drop_types::{DropConfig, Dropper, Getters, Setters};
+use crate::errors::DropError;
+use crate::{storage_cost, Drop};
 use crate::{Contract, ContractExt};
 
 #[derive(Clone, Debug, BorshDeserialize, BorshSerialize)]
@@ -18,164 +19,233 @@ pub struct NFTDrop {
     funder: AccountId,       // Account which created the drop and funded it
     token_id: String,        // Id of token which will be transfer to claiming user
     nft_contract: AccountId, // Contract of non-fungible token which will be transfer to claiming user
+    config: DropConfig,      // Settings chosen by the funder
+}
+
+// Layout of `NFTDrop` before drops were versioned, kept to read drops stored back then
+#[derive(Clone, Debug, BorshDeserialize, BorshSerialize)]
+#[borsh(crate = "near_sdk::borsh")]
+pub struct NFTDropV1 {
+    funder: AccountId,
+    token_id: String,
+    nft_contract: AccountId,
+}
+
+impl From<NFTDropV1> for NFTDrop {
+    fn from(nft_drop: NFTDropV1) -> Self {
+        NFTDrop {
+            funder: nft_drop.funder,
+            token_id: nft_drop.token_id,
+            nft_contract: nft_drop.nft_contract,
+            config: DropConfig::default(),
+        }
+    }
 }
 
 impl Dropper for NFTDrop {
-    fn promise_for_claiming(&self, account_id: AccountId) -> Promise {
-        assert_ne!(self.token_id, "", "No tokens to drop");
+    fn promise_for_claiming(&self, account_id: AccountId) -> Result<Promise, DropError> {
+        if self.token_id.is_empty() {
+            return Err(DropError::NoTokensToDrop);
+        }
 
         let transfer_args = json!({"receiver_id": account_id, "token_id": self.token_id})
             .to_string()
             .into_bytes()
             .to_vec();
 
-        Promise::new(self.nft_contract.clone()).function_call_weight(
-            "nft_transfer".to_string(),
-            transfer_args,
-            NearToken::from_yoctonear(1),
-            MIN_GAS_FOR_NFT_TRANSFER,
-            GasWeight(0),
+        Ok(
+            Promise::new(self.nft_contract.clone()).function_call_weight(
+                "nft_transfer".to_string(),
+                transfer_args,
+                NearToken::from_yoctonear(1),
+                self.config.transfer_gas(MIN_GAS_FOR_NFT_TRANSFER),
+                GasWeight(0),
+            ),
         )
     }
 
-    fn promise_to_resolve_claim(&self, account_created: bool, drop_deleted: bool) -> Promise {
-        Contract::ext(env::current_account_id())
-            .with_static_gas(NFT_CLAIM_CALLBACK_GAS)
+    fn promise_to_resolve_claim(
+        &self,
+        _account_id: AccountId,
+        account_created: bool,
+        storage_freed: NearToken,
+    ) -> Result<Promise, DropError> {
+        Ok(Contract::ext(env::current_account_id())
+            .with_static_gas(self.config.callback_gas(NFT_CLAIM_CALLBACK_GAS))
             .with_unused_gas_weight(0)
             .resolve_nft_claim(
                 account_created,
-                drop_deleted,
+                storage_freed,
                 self.funder.clone(),
                 self.token_id.clone(),
-                self.nft_contract.clone(),
-            )
+            ))
     }
 }
 
 impl Getters for NFTDrop {
-    fn get_counter(&self) -> Result<u32, &str> {
-        Err("There is no counter field for NFT drop structure")
+    fn get_funder(&self) -> &AccountId {
+        &self.funder
     }
 
-    fn get_amount_per_drop(&self) -> Result<NearToken, &str> {
-        Err("There is no amount_per_drop field for NFT drop structure")
+    fn get_config(&self) -> &DropConfig {
+        &self.config
+    }
+
+    fn get_counter(&self) -> Result<u32, DropError> {
+        Err(DropError::NoCounter)
+    }
+
+    fn get_amount_per_drop(&self) -> Result<NearToken, DropError> {
+        Err(DropError::NoAmountPerDrop)
     }
 }
 
-pub fn required_deposit_per_key() -> NearToken {
-  CREATE_ACCOUNT_FEE
-      .saturating_add(ACCESS_KEY_ALLOWANCE)
-      .saturating_add(ACCESS_KEY_STORAGE)
+impl Setters for NFTDrop {
+    fn set_counter(&mut self, _value: u32) -> Result<(), DropError> {
+        Err(DropError::NoCounter)
+    }
+
+    fn set_config(&mut self, config: DropConfig) {
+        self.config = config;
+    }
+
+    fn set_funder(&mut self, funder: AccountId) {
+        self.funder = funder;
+    }
 }
 
-pub fn required_storage_drop() -> NearToken {
-  NearToken::from_yoctonear(
-      // DropId -> Drop::Near
-      ID_STORAGE + ENUM_STORAGE + ACC_STORAGE * 2 + NFT_TOKEN_ID_STORAGE + 8 
-      // PublicKey -> DropId
-      + (PK_STORAGE + ID_STORAGE)
-  )
+impl NFTDrop {
+    pub fn has_token(&self) -> bool {
+        !self.token_id.is_empty()
+    }
+
+    pub fn get_nft_contract(&self) -> &AccountId {
+        &self.nft_contract
+    }
+
+    pub fn get_token_id(&self) -> &str {
+        &self.token_id
+    }
+
+    // Room paid for the token id that it does not use
+    pub fn unused_token_id_storage(&self) -> NearToken {
+        storage_cost(MAX_NFT_TOKEN_ID_LEN - self.token_id.len() as u64)
+    }
 }
 
-pub fn create(nft_contract: AccountId) -> Drop {
-    let funder = env::predecessor_account_id();
-    
-    let attached_deposit = env::attached_deposit();
-    let required_deposit = // required_storage_drop + (required_deposit_per_key * num_of_keys)
-        required_storage_drop()
-        .saturating_add(
-            required_deposit_per_key()
-        );
-    
-    assert!(
-        attached_deposit >= required_deposit,
-        "Please attach at least {required_deposit}"
-    );
+pub fn required_deposit_per_key() -> NearToken {
+    CREATE_ACCOUNT_FEE
+        .saturating_add(ACCESS_KEY_ALLOWANCE)
+        .saturating_add(ACCESS_KEY_STORAGE)
+}
 
-    let extra_deposit = attached_deposit.saturating_sub(required_deposit);
-    if extra_deposit.gt(&NearToken::from_yoctonear(0)) {
-        // refund the user, we don't need that money
-        Promise::new(env::predecessor_account_id()).transfer(extra_deposit);
+// Deposit needed on top of the storage measured when the drop is saved. The token id is
+// only written once the NFT is approved, so room for the longest one is paid upfront
+pub fn required_deposit(config: &DropConfig) -> NearToken {
+    required_deposit_per_key()
+        .saturating_add(storage_cost(MAX_NFT_TOKEN_ID_LEN))
+        .saturating_add(storage_cost(config.claim_storage()))
+}
+
+pub fn create(
+    funder: AccountId,
+    nft_contract: AccountId,
+    config: DropConfig,
+) -> Result<Drop, DropError> {
+    config.check_gas(MIN_GAS_FOR_NFT_TRANSFER, NFT_CLAIM_CALLBACK_GAS)?;
+    if config.vesting.is_some() {
+        return Err(DropError::UnsupportedConfig("NFT drops cannot vest"));
     }
 
-    Drop::NFT(NFTDrop {
+    Ok(Drop::NFT(NFTDrop {
         funder,
         nft_contract,
         token_id: "".to_string(),
-    })
+        config,
+    }))
 }
 
 #[near]
 impl Contract {
     // Fund an existing drop
+    #[handle_result]
     pub fn nft_on_approve(
         &mut self,
         token_id: TokenId,
         owner_id: AccountId,
         approval_id: u32,
         msg: String,
-    ) -> PromiseOrValue<U128> {
-        let drop_id: u32 = msg.parse().unwrap();
-        let token_id_to_drop = token_id.clone();
-        let drop = self.drop_by_id.get(&drop_id).expect("Missing Drop");
+    ) -> Result<PromiseOrValue<U128>, DropError> {
+        // Only the NFT contract matters, the owner and approval are part of the standard
+        let _ = (owner_id, approval_id);
+        self.check_not_paused()?;
+        let drop_id: DropId = msg
+            .parse()
+            .map_err(|_| DropError::InvalidDropId(msg.clone()))?;
+        let drop = self
+            .internal_get_drop(&drop_id)
+            .ok_or(DropError::DropNotFound(drop_id))?;
+
+        if token_id.len() as u64 > MAX_NFT_TOKEN_ID_LEN {
+            return Err(DropError::TokenIdTooLong(MAX_NFT_TOKEN_ID_LEN));
+        }
 
         // Make sure the drop exists
-        if let Drop::NFT(NFTDrop {
+        let Drop::NFT(NFTDrop {
             funder,
             nft_contract,
             token_id: _,
-        }) = &drop
-        {
-            assert!(
-                nft_contract == &env::predecessor_account_id(),
-                "Wrong NFT contract, expected {nft_contract}",
-            );
-
-            // Update and insert again
-            self.drop_by_id.insert(
-                drop_id,
-                Drop::NFT(NFTDrop {
-                    funder: funder.clone(),
-                    nft_contract: nft_contract.clone(),
-                    token_id: token_id_to_drop,
-                }),
-            )
-        } else {
-            panic!("Not an NFT drop")
+            config,
+        }) = drop
+        else {
+            return Err(DropError::WrongDropType);
         };
+        if nft_contract != env::predecessor_account_id() {
+            return Err(DropError::WrongNftContract(nft_contract));
+        }
+
+        // Update and insert again
+        self.internal_update_drop(
+            drop_id,
+            Drop::NFT(NFTDrop {
+                funder,
+                nft_contract,
+                token_id,
+                config,
+            }),
+        );
 
         // We do not return any tokens
-        PromiseOrValue::Value(U128(0))
+        Ok(PromiseOrValue::Value(U128(0)))
     }
 
+    #[private]
     pub fn resolve_nft_claim(
+        &mut self,
         account_created: bool,
-        drop_deleted: bool,
+        storage_freed: NearToken,
         funder: AccountId,
         token_id: String,
-        nft_contract: AccountId,
         #[callback_result] result: Result<(), PromiseError>,
     ) -> bool {
-        let mut to_refund = ACCESS_KEY_STORAGE;
+        // The freed storage includes the token id, so only the unused room is added back
+        let unused_token_id_storage = storage_cost(MAX_NFT_TOKEN_ID_LEN - token_id.len() as u64);
+        let mut to_refund = ACCESS_KEY_STORAGE
+            .saturating_add(storage_freed)
+            .saturating_add(unused_token_id_storage);
+        self.settle_claim_refund(to_refund.saturating_add(CREATE_ACCOUNT_FEE));
 
         if !account_created {
             to_refund = to_refund.saturating_add(CREATE_ACCOUNT_FEE);
         }
 
-        if drop_deleted {
-            to_refund = to_refund.saturating_add(required_storage_drop());
-        }
-
-        if result.is_err() {
-            log!(
-                "There is error during claiming the drop: {:?}",
-                result.err().unwrap()
-            )
+        if let Err(error) = &result {
+            log!("There is error during claiming the drop: {:?}", error)
         }
 
         // Return NEAR
         Promise::new(funder.clone()).transfer(to_refund);
 
-        true
+        result.is_ok()
     }
 }
@@ -0,0 +1,234 @@
+use near_contract_standards::non_fungible_token::metadata::TokenMetadata;
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::serde_json::json;
+use near_sdk::{
+    env, log, near, AccountId, GasWeight, NearToken, Promise, PromiseResult, PublicKey,
+};
+
+use crate::constants::*;
+use crate::drop_types::{DropConfig, Dropper, Getters, Setters};
+use crate::errors::DropError;
+use crate::{storage_cost, Contract, ContractExt, Drop};
+
+// Replaced by the serial number of the token in the metadata template
+const SERIAL_PLACEHOLDER: &str = "{serial}";
+
+// Mints a new token for each claim, this contract being a minter on the NFT contract
+#[derive(Clone, Debug, BorshDeserialize, BorshSerialize)]
+#[near(serializers = [json])]
+#[borsh(crate = "near_sdk::borsh")]
+pub struct NFTMintDrop {
+    funder: AccountId,       // Account which created the drop and funded it
+    nft_contract: AccountId, // Contract minting the tokens
+    mint_method: String,     // Method called with (token_id, token_owner_id, token_metadata)
+    metadata: TokenMetadata, // Template of the token metadata
+    mint_deposit: NearToken, // Deposit attached to each mint, for the storage of the token
+    drop_id: DropId,         // Id of the drop, part of the token ids
+    num_of_keys: u32,        // Keys the drop was created with
+    counter: u32,            // Reflects how much times the drop can be claimed
+    config: DropConfig,      // Settings chosen by the funder
+}
+
+impl Dropper for NFTMintDrop {
+    fn promise_for_claiming(&self, account_id: AccountId) -> Result<Promise, DropError> {
+        let mint_args = json!({
+            "token_id": self.token_id(),
+            "token_owner_id": account_id,
+            "token_metadata": self.token_metadata(),
+        })
+        .to_string()
+        .into_bytes()
+        .to_vec();
+
+        Ok(
+            Promise::new(self.nft_contract.clone()).function_call_weight(
+                self.mint_method.clone(),
+                mint_args,
+                self.mint_deposit,
+                self.config.transfer_gas(MIN_GAS_FOR_NFT_MINT),
+                GasWeight(0),
+            ),
+        )
+    }
+
+    fn promise_to_resolve_claim(
+        &self,
+        _account_id: AccountId,
+        account_created: bool,
+        storage_freed: NearToken,
+    ) -> Result<Promise, DropError> {
+        Ok(Contract::ext(env::current_account_id())
+            .with_static_gas(self.config.callback_gas(NFT_MINT_CALLBACK_GAS))
+            .with_unused_gas_weight(0)
+            .resolve_nft_mint_claim(
+                account_created,
+                storage_freed,
+                self.funder.clone(),
+                self.token_id(),
+                self.mint_deposit,
+            ))
+    }
+}
+
+impl Getters for NFTMintDrop {
+    fn get_funder(&self) -> &AccountId {
+        &self.funder
+    }
+
+    fn get_config(&self) -> &DropConfig {
+        &self.config
+    }
+
+    fn get_counter(&self) -> Result<u32, DropError> {
+        Ok(self.counter)
+    }
+
+    fn get_amount_per_drop(&self) -> Result<NearToken, DropError> {
+        Err(DropError::NoAmountPerDrop)
+    }
+}
+
+impl Setters for NFTMintDrop {
+    fn set_counter(&mut self, value: u32) -> Result<(), DropError> {
+        self.counter = value;
+        Ok(())
+    }
+
+    fn set_config(&mut self, config: DropConfig) {
+        self.config = config;
+    }
+
+    fn set_funder(&mut self, funder: AccountId) {
+        self.funder = funder;
+    }
+}
+
+impl NFTMintDrop {
+    pub fn get_mint_deposit(&self) -> NearToken {
+        self.mint_deposit
+    }
+
+    pub fn get_nft_contract(&self) -> &AccountId {
+        &self.nft_contract
+    }
+
+    // Serial of the token minted by the next claim, counting from 1
+    fn serial(&self) -> u32 {
+        self.num_of_keys - self.counter + 1
+    }
+
+    pub fn token_id(&self) -> String {
+        format!("{}:{}", self.drop_id, self.serial())
+    }
+
+    fn token_metadata(&self) -> TokenMetadata {
+        let serial = self.serial().to_string();
+        let render = |field: &Option<String>| {
+            field
+                .as_ref()
+                .map(|field| field.replace(SERIAL_PLACEHOLDER, &serial))
+        };
+
+        TokenMetadata {
+            title: render(&self.metadata.title),
+            description: render(&self.metadata.description),
+            extra: render(&self.metadata.extra),
+            issued_at: Some(env::block_timestamp_ms().to_string()),
+            ..self.metadata.clone()
+        }
+    }
+}
+
+#[near]
+impl Contract {
+    // Creates a drop minting a token from `metadata` for each claim. `{serial}` in the title,
+    // description and extra of the template is replaced by the serial number of the token
+    #[payable]
+    #[handle_result]
+    pub fn create_nft_mint_drop(
+        &mut self,
+        public_keys: Vec<PublicKey>,
+        nft_contract: AccountId,
+        metadata: TokenMetadata,
+        mint_method: Option<String>,
+        mint_deposit: NearToken,
+        config: Option<DropConfig>,
+    ) -> Result<DropId, DropError> {
+        self.check_not_paused()?;
+        self.check_keys_unused(&public_keys)?;
+
+        let config = config.unwrap_or_default();
+        config.check_gas(MIN_GAS_FOR_NFT_MINT, NFT_MINT_CALLBACK_GAS)?;
+        if config.vesting.is_some() {
+            return Err(DropError::UnsupportedConfig("NFT drops cannot vest"));
+        }
+
+        let num_of_keys: u32 = public_keys.len().try_into().unwrap();
+        let drop = Drop::NFTMint(NFTMintDrop {
+            funder: env::predecessor_account_id(),
+            nft_contract,
+            mint_method: mint_method.unwrap_or_else(|| "nft_mint".to_string()),
+            metadata,
+            mint_deposit,
+            drop_id: self.next_drop_id,
+            num_of_keys,
+            counter: num_of_keys,
+            config,
+        });
+
+        let required_deposit = mint_deposit
+            .saturating_add(CREATE_ACCOUNT_FEE)
+            .saturating_add(ACCESS_KEY_ALLOWANCE)
+            .saturating_add(ACCESS_KEY_STORAGE)
+            .saturating_add(storage_cost(drop.claim_storage()))
+            .saturating_mul(num_of_keys as u128)
+            .saturating_add(self.fees.key_fee(num_of_keys));
+
+        let drop_id = self.internal_create_drop(
+            drop,
+            &public_keys,
+            env::attached_deposit(),
+            required_deposit,
+        )?;
+        self.collect_near_fee(self.fees.key_fee(num_of_keys));
+        self.assert_solvent();
+
+        Ok(drop_id)
+    }
+
+    // A failed mint gives the funder back the deposit it would have used. Mint methods return
+    // different things, so only the status of the call is checked
+    #[private]
+    pub fn resolve_nft_mint_claim(
+        &mut self,
+        account_created: bool,
+        storage_freed: NearToken,
+        funder: AccountId,
+        token_id: String,
+        mint_deposit: NearToken,
+    ) -> bool {
+        let minted = matches!(env::promise_result(0), PromiseResult::Successful(_));
+
+        self.settle_claim_refund(
+            ACCESS_KEY_STORAGE
+                .saturating_add(storage_freed)
+                .saturating_add(CREATE_ACCOUNT_FEE),
+        );
+
+        let mut to_refund = ACCESS_KEY_STORAGE.saturating_add(storage_freed);
+
+        if !account_created {
+            to_refund = to_refund.saturating_add(CREATE_ACCOUNT_FEE);
+        }
+
+        if !minted {
+            log!("Minting token {token_id} failed");
+            to_refund = to_refund.saturating_add(mint_deposit);
+        }
+
+        // Return NEAR
+        Promise::new(funder).transfer(to_refund);
+
+        minted
+    }
+}
@@ -0,0 +1,149 @@
+use near_sdk::{env, log, near, AccountId, NearToken};
+
+use crate::constants::DropId;
+use crate::drop_types::{Drop, Getters, Setters};
+use crate::errors::DropError;
+use crate::{Contract, ContractExt};
+
+#[near]
+impl Contract {
+    // Hands a drop over to `new_owner`, who then gets its refunds and manages it. With
+    // `require_accept` the drop only changes hands once `new_owner` accepts it, the deposit
+    // paying for the storage of the pending transfer
+    #[payable]
+    #[handle_result]
+    pub fn transfer_drop_ownership(
+        &mut self,
+        drop_id: DropId,
+        new_owner: AccountId,
+        require_accept: Option<bool>,
+    ) -> Result<(), DropError> {
+        self.check_not_paused()?;
+        let drop = self.internal_get_drop_as_funder(&drop_id)?;
+        let owner = drop.get_funder().clone();
+        let initial_storage = env::storage_usage();
+
+        if require_accept.unwrap_or(false) {
+            self.pending_drop_owners.insert(drop_id, new_owner.clone());
+            log!("Drop {drop_id} offered to {new_owner}");
+        } else {
+            self.pending_drop_owners.remove(&drop_id);
+            self.internal_set_drop_owner(drop_id, drop, &new_owner);
+            log!("Drop {drop_id} transferred from {owner} to {new_owner}");
+        }
+
+        self.settle_storage_change(initial_storage, env::attached_deposit(), owner)
+    }
+
+    // Called by the account a drop was offered to
+    #[handle_result]
+    pub fn accept_drop_ownership(&mut self, drop_id: DropId) -> Result<(), DropError> {
+        self.check_not_paused()?;
+        let new_owner = env::predecessor_account_id();
+        if self.pending_drop_owners.get(&drop_id) != Some(&new_owner) {
+            return Err(DropError::NotOffered(new_owner));
+        }
+
+        let drop = self
+            .internal_get_drop(&drop_id)
+            .ok_or(DropError::DropNotFound(drop_id))?;
+        let owner = drop.get_funder().clone();
+        let initial_storage = env::storage_usage();
+
+        self.pending_drop_owners.remove(&drop_id);
+        self.internal_set_drop_owner(drop_id, drop, &new_owner);
+        log!("Drop {drop_id} transferred from {owner} to {new_owner}");
+
+        // The previous owner paid for the pending transfer
+        self.settle_storage_change(initial_storage, NearToken::from_yoctonear(0), owner)
+    }
+
+    #[handle_result]
+    pub fn cancel_drop_ownership_transfer(&mut self, drop_id: DropId) -> Result<(), DropError> {
+        let drop = self.internal_get_drop_as_funder(&drop_id)?;
+        let initial_storage = env::storage_usage();
+
+        self.pending_drop_owners
+            .remove(&drop_id)
+            .ok_or(DropError::NoPendingTransfer(drop_id))?;
+
+        self.settle_storage_change(
+            initial_storage,
+            NearToken::from_yoctonear(0),
+            drop.get_funder().clone(),
+        )
+    }
+
+    pub fn get_pending_drop_owner(&self, drop_id: DropId) -> Option<AccountId> {
+        self.pending_drop_owners.get(&drop_id).cloned()
+    }
+
+    // Stops the keys of a drop from being claimed until it is resumed, the keys are kept. The
+    // deposit pays for the storage of the pause
+    #[payable]
+    #[handle_result]
+    pub fn pause_drop(&mut self, drop_id: DropId) -> Result<(), DropError> {
+        let drop = self.internal_get_drop_as_funder(&drop_id)?;
+        let initial_storage = env::storage_usage();
+
+        if !self.paused_drops.insert(drop_id) {
+            return Err(DropError::DropPaused(drop_id));
+        }
+        log!("Drop {drop_id} paused");
+
+        self.settle_storage_change(
+            initial_storage,
+            env::attached_deposit(),
+            drop.get_funder().clone(),
+        )
+    }
+
+    #[handle_result]
+    pub fn resume_drop(&mut self, drop_id: DropId) -> Result<(), DropError> {
+        let drop = self.internal_get_drop_as_funder(&drop_id)?;
+        let initial_storage = env::storage_usage();
+
+        if !self.paused_drops.remove(&drop_id) {
+            return Err(DropError::DropNotPaused(drop_id));
+        }
+        log!("Drop {drop_id} resumed");
+
+        self.settle_storage_change(
+            initial_storage,
+            NearToken::from_yoctonear(0),
+            drop.get_funder().clone(),
+        )
+    }
+
+    pub fn is_drop_paused(&self, drop_id: DropId) -> bool {
+        self.paused_drops.contains(&drop_id)
+    }
+}
+
+impl Contract {
+    pub(crate) fn check_drop_not_paused(&self, drop_id: &DropId) -> Result<(), DropError> {
+        if self.paused_drops.contains(drop_id) {
+            return Err(DropError::DropPaused(*drop_id));
+        }
+        Ok(())
+    }
+
+    // Reads a drop the caller owns
+    pub(crate) fn internal_get_drop_as_funder(&self, drop_id: &DropId) -> Result<Drop, DropError> {
+        let drop = self
+            .internal_get_drop(drop_id)
+            .ok_or(DropError::DropNotFound(*drop_id))?;
+        if &env::predecessor_account_id() != drop.get_funder() {
+            return Err(DropError::NotFunder);
+        }
+        Ok(drop)
+    }
+
+    // Everything the funder of a drop manages follows it to the new owner
+    fn internal_set_drop_owner(&mut self, drop_id: DropId, mut drop: Drop, new_owner: &AccountId) {
+        drop.set_funder(new_owner.clone());
+        self.internal_update_drop(drop_id, drop);
+        self.set_claim_log_funder(drop_id, new_owner);
+        self.set_vesting_funder(drop_id, new_owner);
+    }
+}
@@ -0,0 +1,283 @@
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U64;
+use near_sdk::{env, near, AccountId, NearToken, Promise, PublicKey};
+
+use crate::constants::*;
+use crate::drop_types::{DropConfig, Getters, Setters};
+use crate::errors::DropError;
+use crate::{storage_cost, Contract, ContractExt, Drop};
+
+// Proof of attendance, claimed as a soulbound token recorded on this contract. The drop id
+// is the class of the tokens, as in NEP-393
+#[derive(Clone, Debug, BorshDeserialize, BorshSerialize)]
+#[near(serializers = [json])]
+#[borsh(crate = "near_sdk::borsh")]
+pub struct POAPDrop {
+    funder: AccountId,      // Account which created the drop and funded it
+    starts_at: Option<U64>, // Claims open at this timestamp, in milliseconds
+    ends_at: Option<U64>,   // Claims close at this timestamp, in milliseconds
+    counter: u32,           // Reflects how much times the drop can be claimed
+    config: DropConfig,     // Settings chosen by the funder
+}
+
+// Describes the event, kept after the drop is claimed out
+#[derive(Clone, Debug, BorshDeserialize, BorshSerialize)]
+#[near(serializers = [json])]
+#[borsh(crate = "near_sdk::borsh")]
+pub struct EventMetadata {
+    pub title: String,
+    pub description: Option<String>,
+    pub media: Option<String>,
+}
+
+// Non-transferable record of an attendance
+#[derive(Clone, Debug, BorshDeserialize, BorshSerialize)]
+#[near(serializers = [json])]
+#[borsh(crate = "near_sdk::borsh")]
+pub struct Sbt {
+    pub token: u64,     // Id of the token, unique on this contract
+    pub issued_at: U64, // Timestamp of the claim, in milliseconds
+}
+
+// Token metadata of NEP-393
+#[near(serializers = [json])]
+pub struct SbtMetadata {
+    pub class: DropId,
+    pub issued_at: U64,
+    pub expires_at: Option<U64>, // Always none, attendance does not expire
+}
+
+// Token of an owner, as listed by the NEP-393 registry views
+#[near(serializers = [json])]
+pub struct OwnedToken {
+    pub token: u64,
+    pub metadata: SbtMetadata,
+}
+
+impl Getters for POAPDrop {
+    fn get_funder(&self) -> &AccountId {
+        &self.funder
+    }
+
+    fn get_config(&self) -> &DropConfig {
+        &self.config
+    }
+
+    fn get_counter(&self) -> Result<u32, DropError> {
+        Ok(self.counter)
+    }
+
+    fn get_amount_per_drop(&self) -> Result<NearToken, DropError> {
+        Err(DropError::NoAmountPerDrop)
+    }
+}
+
+impl Setters for POAPDrop {
+    fn set_counter(&mut self, value: u32) -> Result<(), DropError> {
+        self.counter = value;
+        Ok(())
+    }
+
+    fn set_config(&mut self, config: DropConfig) {
+        self.config = config;
+    }
+
+    fn set_funder(&mut self, funder: AccountId) {
+        self.funder = funder;
+    }
+}
+
+impl POAPDrop {
+    pub fn check_open(&self) -> Result<(), DropError> {
+        let now = env::block_timestamp_ms();
+        if matches!(self.starts_at, Some(starts_at) if now < starts_at.0) {
+            return Err(DropError::EventNotStarted);
+        }
+        if matches!(self.ends_at, Some(ends_at) if now >= ends_at.0) {
+            return Err(DropError::EventOver);
+        }
+        Ok(())
+    }
+}
+
+#[near]
+impl Contract {
+    // Creates a drop issuing a soulbound token to each attendee claiming a key within the
+    // event window
+    #[payable]
+    #[handle_result]
+    pub fn create_poap_drop(
+        &mut self,
+        public_keys: Vec<PublicKey>,
+        event: EventMetadata,
+        starts_at: Option<U64>,
+        ends_at: Option<U64>,
+        config: Option<DropConfig>,
+    ) -> Result<DropId, DropError> {
+        self.check_not_paused()?;
+        self.check_keys_unused(&public_keys)?;
+
+        if let (Some(starts_at), Some(ends_at)) = (starts_at, ends_at) {
+            if starts_at.0 >= ends_at.0 {
+                return Err(DropError::InvalidEventWindow);
+            }
+        }
+
+        let config = config.unwrap_or_default();
+        if config.transfer_gas.is_some() || config.callback_gas.is_some() {
+            return Err(DropError::UnsupportedConfig(
+                "POAP drops do not use gas overrides",
+            ));
+        }
+        if config.vesting.is_some() {
+            return Err(DropError::UnsupportedConfig("POAP drops cannot vest"));
+        }
+
+        let num_of_keys: u32 = public_keys.len().try_into().unwrap();
+        let drop = Drop::POAP(POAPDrop {
+            funder: env::predecessor_account_id(),
+            starts_at,
+            ends_at,
+            counter: num_of_keys,
+            config,
+        });
+
+        // Saved with the drop, so its storage is measured along with it
+        self.sbt_classes.insert(self.next_drop_id, event);
+
+        // Each key also pays for the token its claim records
+        let required_deposit = CREATE_ACCOUNT_FEE
+            .saturating_add(ACCESS_KEY_ALLOWANCE)
+            .saturating_add(ACCESS_KEY_STORAGE)
+            .saturating_add(storage_cost(drop.claim_storage()))
+            .saturating_mul(num_of_keys as u128)
+            .saturating_add(self.fees.key_fee(num_of_keys));
+
+        let drop_id = self.internal_create_drop(
+            drop,
+            &public_keys,
+            env::attached_deposit(),
+            required_deposit,
+        )?;
+        self.collect_near_fee(self.fees.key_fee(num_of_keys));
+        self.assert_solvent();
+
+        Ok(drop_id)
+    }
+
+    pub fn sbt_class_metadata(&self, class: DropId) -> Option<EventMetadata> {
+        self.sbt_classes.get(&class).cloned()
+    }
+
+    pub fn sbt_supply(&self) -> u64 {
+        self.sbt_supply
+    }
+
+    pub fn sbt_supply_by_owner(&self, account: AccountId) -> u64 {
+        self.sbt_classes_by_owner
+            .get(&account)
+            .map_or(0, |classes| classes.len() as u64)
+    }
+
+    // Tokens of an account ordered by class, starting at `from_class`, grouped by issuer as
+    // NEP-393 registries do. This contract is the only issuer, and its tokens never expire
+    pub fn sbt_tokens_by_owner(
+        &self,
+        account: AccountId,
+        ctr: Option<AccountId>,
+        from_class: Option<DropId>,
+        limit: Option<u32>,
+        with_expired: bool,
+    ) -> Vec<(AccountId, Vec<OwnedToken>)> {
+        let _ = with_expired;
+        let issuer = env::current_account_id();
+        if ctr.is_some_and(|ctr| ctr != issuer) {
+            return vec![];
+        }
+        let Some(classes) = self.sbt_classes_by_owner.get(&account) else {
+            return vec![];
+        };
+
+        let tokens: Vec<OwnedToken> = classes
+            .iter()
+            .filter(|class| **class >= from_class.unwrap_or(0))
+            .take(limit.unwrap_or(u32::MAX) as usize)
+            .map(|class| {
+                let sbt = self
+                    .sbts
+                    .get(&(*class, account.clone()))
+                    .expect("Missing token");
+                OwnedToken {
+                    token: sbt.token,
+                    metadata: SbtMetadata {
+                        class: *class,
+                        issued_at: sbt.issued_at,
+                        expires_at: None,
+                    },
+                }
+            })
+            .collect();
+
+        if tokens.is_empty() {
+            return vec![];
+        }
+        vec![(issuer, tokens)]
+    }
+
+    pub fn is_sbt_owner(&self, class: DropId, account: AccountId) -> bool {
+        self.sbts.contains_key(&(class, account))
+    }
+}
+
+impl Contract {
+    // Records the attendance of a claim, in place of paying anything out
+    pub(crate) fn issue_sbt(
+        &mut self,
+        drop_id: DropId,
+        account_id: &AccountId,
+    ) -> Result<(), DropError> {
+        let key = (drop_id, account_id.clone());
+        if self.sbts.contains_key(&key) {
+            return Err(DropError::AlreadyAttended(account_id.clone()));
+        }
+
+        self.sbt_supply += 1;
+        self.sbts.insert(
+            key,
+            Sbt {
+                token: self.sbt_supply,
+                issued_at: U64(env::block_timestamp_ms()),
+            },
+        );
+
+        // The classes of an owner are kept sorted for `from_class`
+        let mut classes = self
+            .sbt_classes_by_owner
+            .get(account_id)
+            .cloned()
+            .unwrap_or_default();
+        let index = classes.partition_point(|class| *class < drop_id);
+        classes.insert(index, drop_id);
+        self.sbt_classes_by_owner
+            .insert(account_id.clone(), classes);
+
+        Ok(())
+    }
+
+    // Nothing is paid out, the funder gets back what the key held right away
+    pub(crate) fn refund_poap_claim(
+        &mut self,
+        drop: &POAPDrop,
+        account_created: bool,
+        refund: NearToken,
+    ) -> Promise {
+        self.settle_claim_refund(refund);
+
+        let mut to_refund = refund;
+        if account_created {
+            to_refund = to_refund.saturating_sub(CREATE_ACCOUNT_FEE);
+        }
+
+        Promise::new(drop.funder.clone()).transfer(to_refund)
+    }
+}
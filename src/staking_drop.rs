@@ -0,0 +1,639 @@
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde_json::json;
+use near_sdk::{env, log, near, AccountId, GasWeight, NearToken, Promise, PromiseError, PublicKey};
+
+use crate::constants::*;
+use crate::drop_types::{DropConfig, Dropper, Getters, Setters};
+use crate::errors::DropError;
+use crate::{storage_cost, Contract, ContractExt, Drop};
+
+// Stakes each claim in a staking pool. Pools credit the account calling them, so the stake
+// is held by this contract and recorded as a delegation of the claimer
+#[derive(Clone, Debug, BorshDeserialize, BorshSerialize)]
+#[near(serializers = [json])]
+#[borsh(crate = "near_sdk::borsh")]
+pub struct StakingDrop {
+    funder: AccountId,       // Account which created the drop and funded it
+    staking_pool: AccountId, // Pool the NEAR is staked with
+    amount: NearToken,       // Reflects how much NEAR will be staked for the claiming user
+    counter: u32,            // Reflects how much times the drop can be claimed
+    config: DropConfig,      // Settings chosen by the funder
+}
+
+// NEAR staked by this contract on behalf of a claimer. Rewards are shared between the
+// delegators of a pool in proportion to their principal
+#[derive(Clone, Debug, Default, BorshDeserialize, BorshSerialize)]
+#[near(serializers = [json])]
+#[borsh(crate = "near_sdk::borsh")]
+pub struct Delegation {
+    pub principal: NearToken, // NEAR staked by the claims, rewards not included
+    pub unstaked: NearToken,  // NEAR unstaked and waiting to be withdrawn
+    pub batch: u64,           // Unstaking batch of the pool the unstaked NEAR is part of
+}
+
+// Unstaking with a pool. A pool locks everything an account unstaked again each time it
+// unstakes more, so delegators unstake in batches: a batch is only sent once the previous
+// one is withdrawn, and the contract then holds it for its delegators
+#[derive(Clone, Debug, Default, BorshDeserialize, BorshSerialize)]
+#[near(serializers = [json])]
+#[borsh(crate = "near_sdk::borsh")]
+pub struct PoolUnstaking {
+    pub queued: NearToken, // Unstaked by delegators, not sent to the pool yet
+    pub locked: NearToken, // Sent to the pool, released from `unlocks_at`
+    pub unlocks_at: u64,   // Epoch height from which the pool releases `locked`
+    pub batch: u64,        // Batch the queued NEAR is part of
+    pub released: u64,     // Batches before this one are held by the contract
+    pub pending: bool,     // A call to the pool is in flight
+    pub claiming: u32,     // Claims staking with the pool, not in its staked balance yet
+}
+
+impl Dropper for StakingDrop {
+    fn promise_for_claiming(&self, _account_id: AccountId) -> Result<Promise, DropError> {
+        Ok(
+            Promise::new(self.staking_pool.clone()).function_call_weight(
+                "deposit_and_stake".to_string(),
+                vec![],
+                self.amount,
+                self.config.transfer_gas(MIN_GAS_FOR_DEPOSIT_AND_STAKE),
+                GasWeight(0),
+            ),
+        )
+    }
+
+    fn promise_to_resolve_claim(
+        &self,
+        account_id: AccountId,
+        account_created: bool,
+        storage_freed: NearToken,
+    ) -> Result<Promise, DropError> {
+        Ok(Contract::ext(env::current_account_id())
+            .with_static_gas(self.config.callback_gas(STAKING_CLAIM_CALLBACK_GAS))
+            .with_unused_gas_weight(0)
+            .resolve_staking_claim(account_id, account_created, storage_freed, self.clone()))
+    }
+}
+
+impl Getters for StakingDrop {
+    fn get_funder(&self) -> &AccountId {
+        &self.funder
+    }
+
+    fn get_config(&self) -> &DropConfig {
+        &self.config
+    }
+
+    fn get_counter(&self) -> Result<u32, DropError> {
+        Ok(self.counter)
+    }
+
+    fn get_amount_per_drop(&self) -> Result<NearToken, DropError> {
+        Ok(self.amount)
+    }
+}
+
+impl Setters for StakingDrop {
+    fn set_counter(&mut self, value: u32) -> Result<(), DropError> {
+        self.counter = value;
+        Ok(())
+    }
+
+    fn set_config(&mut self, config: DropConfig) {
+        self.config = config;
+    }
+
+    fn set_funder(&mut self, funder: AccountId) {
+        self.funder = funder;
+    }
+}
+
+impl StakingDrop {
+    pub fn get_staking_pool(&self) -> &AccountId {
+        &self.staking_pool
+    }
+}
+
+#[near]
+impl Contract {
+    // Creates a drop whose claims are staked with `staking_pool` for the claimer
+    #[payable]
+    #[handle_result]
+    pub fn create_staking_drop(
+        &mut self,
+        public_keys: Vec<PublicKey>,
+        staking_pool: AccountId,
+        amount_per_drop: NearToken,
+        config: Option<DropConfig>,
+    ) -> Result<DropId, DropError> {
+        self.check_not_paused()?;
+        self.check_keys_unused(&public_keys)?;
+
+        // Pools do not take less than 1 NEAR
+        if amount_per_drop < NearToken::from_near(1) {
+            return Err(DropError::AmountTooLow(NearToken::from_near(1)));
+        }
+
+        let config = config.unwrap_or_default();
+        config.check_gas(MIN_GAS_FOR_DEPOSIT_AND_STAKE, STAKING_CLAIM_CALLBACK_GAS)?;
+        if config.vesting.is_some() {
+            return Err(DropError::UnsupportedConfig("Staking drops cannot vest"));
+        }
+
+        let num_of_keys: u32 = public_keys.len().try_into().unwrap();
+        let drop = Drop::Staking(StakingDrop {
+            funder: env::predecessor_account_id(),
+            staking_pool,
+            amount: amount_per_drop,
+            counter: num_of_keys,
+            config,
+        });
+
+        // Each key also pays for the delegation its claim records
+        let fee = self.fees.near_fee(amount_per_drop, num_of_keys);
+        let required_deposit = amount_per_drop
+            .saturating_add(CREATE_ACCOUNT_FEE)
+            .saturating_add(ACCESS_KEY_ALLOWANCE)
+            .saturating_add(ACCESS_KEY_STORAGE)
+            .saturating_add(storage_cost(drop.claim_storage()))
+            .saturating_mul(num_of_keys as u128)
+            .saturating_add(fee);
+
+        let drop_id = self.internal_create_drop(
+            drop,
+            &public_keys,
+            env::attached_deposit(),
+            required_deposit,
+        )?;
+        self.collect_near_fee(fee);
+        self.assert_solvent();
+
+        Ok(drop_id)
+    }
+
+    pub fn get_delegation(
+        &self,
+        staking_pool: AccountId,
+        account_id: AccountId,
+    ) -> Option<Delegation> {
+        self.delegations.get(&(staking_pool, account_id)).cloned()
+    }
+
+    pub fn get_pool_unstaking(&self, staking_pool: AccountId) -> PoolUnstaking {
+        self.pool_unstakings
+            .get(&staking_pool)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    // Unstakes the caller's principal and their share of the rewards. It is sent to the pool
+    // with the next batch, then sent to the caller with `withdraw_delegation` once released
+    #[handle_result]
+    pub fn unstake_delegation(&mut self, staking_pool: AccountId) -> Result<Promise, DropError> {
+        self.check_not_paused()?;
+        let account_id = env::predecessor_account_id();
+        let delegation = self
+            .delegations
+            .get(&(staking_pool.clone(), account_id.clone()))
+            .cloned()
+            .unwrap_or_default();
+        if delegation.principal.is_zero() {
+            return Err(DropError::NothingStaked);
+        }
+
+        // The share is computed from the staked balance, which must not move until then
+        let mut unstaking = self.get_pool_unstaking(staking_pool.clone());
+        if unstaking.pending || unstaking.claiming > 0 {
+            return Err(DropError::PoolBusy(staking_pool));
+        }
+        unstaking.pending = true;
+        self.pool_unstakings.insert(staking_pool.clone(), unstaking);
+
+        let balance_args = json!({ "account_id": env::current_account_id() })
+            .to_string()
+            .into_bytes()
+            .to_vec();
+
+        Ok(Promise::new(staking_pool.clone())
+            .function_call_weight(
+                "get_account_staked_balance".to_string(),
+                balance_args,
+                NearToken::from_yoctonear(0),
+                MIN_GAS_FOR_STAKED_BALANCE,
+                GasWeight(0),
+            )
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(DELEGATION_CALLBACK_GAS.saturating_add(POOL_CALL_GAS))
+                    .with_unused_gas_weight(0)
+                    .resolve_staked_balance(staking_pool, account_id),
+            ))
+    }
+
+    // Sends the caller what they unstaked once the pool has released their batch. Withdraws
+    // the batch locked in the pool when it is released, or sends the queued one if it is not
+    #[handle_result]
+    pub fn withdraw_delegation(&mut self, staking_pool: AccountId) -> Result<Promise, DropError> {
+        self.check_not_paused()?;
+        let account_id = env::predecessor_account_id();
+        let delegation = self
+            .delegations
+            .get(&(staking_pool.clone(), account_id.clone()))
+            .cloned()
+            .unwrap_or_default();
+        if delegation.unstaked.is_zero() {
+            return Err(DropError::NothingUnstaked);
+        }
+
+        let unstaking = self.get_pool_unstaking(staking_pool.clone());
+        if delegation.batch < unstaking.released {
+            return Ok(self.pay_delegation(&staking_pool, account_id, delegation));
+        }
+        if unstaking.pending {
+            return Err(DropError::PoolBusy(staking_pool));
+        }
+
+        // A batch the pool failed to unstake waits in the queue until it is sent again
+        if unstaking.locked.is_zero() {
+            return Ok(self.send_unstaking(&staking_pool, unstaking));
+        }
+        if env::epoch_height() < unstaking.unlocks_at {
+            return Err(DropError::UnstakeLocked(unstaking.unlocks_at));
+        }
+
+        Ok(self.withdraw_unstaking(&staking_pool, unstaking, Some(account_id)))
+    }
+
+    // A failed stake is refunded by the pool, the claimer then gets the NEAR directly
+    #[private]
+    pub fn resolve_staking_claim(
+        &mut self,
+        account_id: AccountId,
+        account_created: bool,
+        storage_freed: NearToken,
+        drop: StakingDrop,
+        #[callback_result] result: Result<(), PromiseError>,
+    ) -> bool {
+        self.settle_claim_refund(
+            ACCESS_KEY_STORAGE
+                .saturating_add(storage_freed)
+                .saturating_add(CREATE_ACCOUNT_FEE),
+        );
+
+        let mut unstaking = self.get_pool_unstaking(drop.staking_pool.clone());
+        unstaking.claiming = unstaking.claiming.saturating_sub(1);
+        self.pool_unstakings
+            .insert(drop.staking_pool.clone(), unstaking);
+
+        let mut to_refund = ACCESS_KEY_STORAGE.saturating_add(storage_freed);
+
+        if !account_created {
+            to_refund = to_refund.saturating_add(CREATE_ACCOUNT_FEE);
+        }
+
+        if result.is_err() {
+            log!(
+                "Staking with {} failed, transferring to {account_id} instead",
+                drop.staking_pool
+            );
+            let delegation =
+                self.update_delegation(&drop.staking_pool, &account_id, |delegation| {
+                    delegation.principal = delegation.principal.saturating_sub(drop.amount)
+                });
+            self.update_pool_principal(&drop.staking_pool, |principal| {
+                principal.saturating_sub(drop.amount)
+            });
+
+            // The delegation recorded by the claim may be gone, its storage goes back too
+            to_refund = to_refund.saturating_add(self.save_delegation(
+                &drop.staking_pool,
+                &account_id,
+                delegation,
+            ));
+            Promise::new(account_id).transfer(drop.amount);
+        }
+
+        // Return the money
+        Promise::new(drop.funder).transfer(to_refund);
+
+        result.is_ok()
+    }
+
+    // Moves the caller's share to the queued batch. Never fails, so the pool is always
+    // freed for the next call
+    #[private]
+    pub fn resolve_staked_balance(
+        &mut self,
+        staking_pool: AccountId,
+        account_id: AccountId,
+        #[callback_result] staked_balance: Result<U128, PromiseError>,
+    ) -> bool {
+        let mut unstaking = self.get_pool_unstaking(staking_pool.clone());
+        unstaking.pending = false;
+
+        let Ok(staked_balance) = staked_balance else {
+            log!("{}", DropError::BalanceUnavailable(staking_pool.clone()));
+            self.pool_unstakings.insert(staking_pool, unstaking);
+            return false;
+        };
+
+        let mut delegation = self
+            .delegations
+            .get(&(staking_pool.clone(), account_id.clone()))
+            .cloned()
+            .unwrap_or_default();
+        let principal = delegation.principal;
+        if principal.is_zero() {
+            log!("{}", DropError::NothingStaked);
+            self.pool_unstakings.insert(staking_pool, unstaking);
+            return false;
+        }
+
+        // The queued batch is still staked but no longer earns for the delegators
+        let staked = staked_balance
+            .0
+            .saturating_sub(unstaking.queued.as_yoctonear());
+        let pool_principal = self
+            .pool_principals
+            .get(&staking_pool)
+            .copied()
+            .unwrap_or(principal);
+        let Some(share) = mul_div(
+            principal.as_yoctonear(),
+            staked,
+            pool_principal.as_yoctonear(),
+        ) else {
+            log!("{}", DropError::ShareOverflow(staking_pool.clone()));
+            self.pool_unstakings.insert(staking_pool, unstaking);
+            return false;
+        };
+        let share = NearToken::from_yoctonear(share);
+
+        // NEAR unstaked earlier and not withdrawn yet waits for this batch too
+        delegation.principal = NearToken::from_yoctonear(0);
+        delegation.unstaked = delegation.unstaked.saturating_add(share);
+        delegation.batch = unstaking.batch;
+        self.delegations
+            .insert((staking_pool.clone(), account_id), delegation);
+        self.update_pool_principal(&staking_pool, |total| total.saturating_sub(principal));
+
+        unstaking.queued = unstaking.queued.saturating_add(share);
+        self.advance_unstaking(&staking_pool, unstaking);
+
+        true
+    }
+
+    // A failed unstake is queued again, to be sent with the next batch
+    #[private]
+    pub fn resolve_pool_unstake(
+        &mut self,
+        staking_pool: AccountId,
+        #[callback_result] result: Result<(), PromiseError>,
+    ) -> bool {
+        let mut unstaking = self.get_pool_unstaking(staking_pool.clone());
+        unstaking.pending = false;
+
+        if result.is_err() {
+            log!("Unstaking from {staking_pool} failed, it is sent again with the next batch");
+            unstaking.queued = unstaking.queued.saturating_add(unstaking.locked);
+            unstaking.locked = NearToken::from_yoctonear(0);
+        }
+        self.pool_unstakings.insert(staking_pool, unstaking);
+
+        result.is_ok()
+    }
+
+    // The contract holds a withdrawn batch for its delegators, and sends the queued one
+    #[private]
+    pub fn resolve_pool_withdraw(
+        &mut self,
+        staking_pool: AccountId,
+        account_id: Option<AccountId>,
+        #[callback_result] result: Result<(), PromiseError>,
+    ) -> bool {
+        let mut unstaking = self.get_pool_unstaking(staking_pool.clone());
+        unstaking.pending = false;
+
+        if result.is_err() {
+            log!("Withdrawing from {staking_pool} failed, it is not released yet");
+            self.pool_unstakings.insert(staking_pool, unstaking);
+            return false;
+        }
+
+        self.add_near_owed(unstaking.locked);
+        unstaking.locked = NearToken::from_yoctonear(0);
+        unstaking.released = unstaking.batch;
+
+        // The delegator who withdrew it is paid right away if their batch was released
+        if let Some(account_id) = account_id {
+            let delegation = self
+                .delegations
+                .get(&(staking_pool.clone(), account_id.clone()))
+                .cloned()
+                .unwrap_or_default();
+            if !delegation.unstaked.is_zero() && delegation.batch < unstaking.released {
+                self.pay_delegation(&staking_pool, account_id, delegation);
+            }
+        }
+        self.advance_unstaking(&staking_pool, unstaking);
+
+        true
+    }
+}
+
+impl Contract {
+    // Records the stake of a claim before the pool is called, so its storage is measured
+    // with the claim. Shares are computed from the staked balance, so claims and unstaking
+    // never overlap on a pool
+    pub(crate) fn add_delegation(
+        &mut self,
+        staking_pool: &AccountId,
+        account_id: &AccountId,
+        amount: NearToken,
+    ) -> Result<(), DropError> {
+        let mut unstaking = self.get_pool_unstaking(staking_pool.clone());
+        if unstaking.pending {
+            return Err(DropError::PoolBusy(staking_pool.clone()));
+        }
+        unstaking.claiming += 1;
+        self.pool_unstakings.insert(staking_pool.clone(), unstaking);
+
+        let delegation = self.update_delegation(staking_pool, account_id, |delegation| {
+            delegation.principal = delegation.principal.saturating_add(amount)
+        });
+        self.delegations
+            .insert((staking_pool.clone(), account_id.clone()), delegation);
+        self.update_pool_principal(staking_pool, |total| total.saturating_add(amount));
+
+        Ok(())
+    }
+
+    // Withdraws the batch locked in the pool once released, or sends the queued batch if
+    // none is locked. The pool is called once at a time
+    fn advance_unstaking(&mut self, staking_pool: &AccountId, unstaking: PoolUnstaking) {
+        if !unstaking.pending {
+            if !unstaking.locked.is_zero() && env::epoch_height() >= unstaking.unlocks_at {
+                self.withdraw_unstaking(staking_pool, unstaking, None);
+                return;
+            }
+            if unstaking.locked.is_zero() && !unstaking.queued.is_zero() {
+                self.send_unstaking(staking_pool, unstaking);
+                return;
+            }
+        }
+
+        self.pool_unstakings.insert(staking_pool.clone(), unstaking);
+    }
+
+    fn send_unstaking(
+        &mut self,
+        staking_pool: &AccountId,
+        mut unstaking: PoolUnstaking,
+    ) -> Promise {
+        let amount = unstaking.queued;
+        unstaking.locked = amount;
+        unstaking.queued = NearToken::from_yoctonear(0);
+        unstaking.unlocks_at = env::epoch_height() + NUM_EPOCHS_TO_UNLOCK;
+        unstaking.batch += 1;
+        unstaking.pending = true;
+        self.pool_unstakings.insert(staking_pool.clone(), unstaking);
+
+        Promise::new(staking_pool.clone())
+            .function_call_weight(
+                "unstake".to_string(),
+                amount_args(amount),
+                NearToken::from_yoctonear(0),
+                MIN_GAS_FOR_UNSTAKE,
+                GasWeight(0),
+            )
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(DELEGATION_CALLBACK_GAS)
+                    .with_unused_gas_weight(0)
+                    .resolve_pool_unstake(staking_pool.clone()),
+            )
+    }
+
+    fn withdraw_unstaking(
+        &mut self,
+        staking_pool: &AccountId,
+        mut unstaking: PoolUnstaking,
+        account_id: Option<AccountId>,
+    ) -> Promise {
+        let amount = unstaking.locked;
+        unstaking.pending = true;
+        self.pool_unstakings.insert(staking_pool.clone(), unstaking);
+
+        Promise::new(staking_pool.clone())
+            .function_call_weight(
+                "withdraw".to_string(),
+                amount_args(amount),
+                NearToken::from_yoctonear(0),
+                MIN_GAS_FOR_STAKING_WITHDRAW,
+                GasWeight(0),
+            )
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(POOL_WITHDRAW_CALLBACK_GAS)
+                    .with_unused_gas_weight(0)
+                    .resolve_pool_withdraw(staking_pool.clone(), account_id),
+            )
+    }
+
+    // Sends a delegator the NEAR of a released batch held by the contract. A delegation
+    // fully withdrawn is removed, the delegator gets its storage
+    fn pay_delegation(
+        &mut self,
+        staking_pool: &AccountId,
+        account_id: AccountId,
+        mut delegation: Delegation,
+    ) -> Promise {
+        let amount = std::mem::replace(&mut delegation.unstaked, NearToken::from_yoctonear(0));
+        self.release_near_owed(amount);
+        let storage_freed = self.save_delegation(staking_pool, &account_id, delegation);
+
+        Promise::new(account_id).transfer(amount.saturating_add(storage_freed))
+    }
+
+    fn update_delegation(
+        &self,
+        staking_pool: &AccountId,
+        account_id: &AccountId,
+        update: impl FnOnce(&mut Delegation),
+    ) -> Delegation {
+        let mut delegation = self
+            .delegations
+            .get(&(staking_pool.clone(), account_id.clone()))
+            .cloned()
+            .unwrap_or_default();
+        update(&mut delegation);
+
+        delegation
+    }
+
+    fn update_pool_principal(
+        &mut self,
+        staking_pool: &AccountId,
+        update: impl FnOnce(NearToken) -> NearToken,
+    ) {
+        let total = self
+            .pool_principals
+            .get(staking_pool)
+            .copied()
+            .unwrap_or(NearToken::from_yoctonear(0));
+        let total = update(total);
+
+        if total.is_zero() {
+            self.pool_principals.remove(staking_pool);
+        } else {
+            self.pool_principals.insert(staking_pool.clone(), total);
+        }
+    }
+
+    // Saves a delegation, removing it once empty. Returns the storage this freed, which is
+    // no longer locked
+    fn save_delegation(
+        &mut self,
+        staking_pool: &AccountId,
+        account_id: &AccountId,
+        delegation: Delegation,
+    ) -> NearToken {
+        let key = (staking_pool.clone(), account_id.clone());
+        if !delegation.principal.is_zero() || !delegation.unstaked.is_zero() {
+            self.delegations.insert(key, delegation);
+            return NearToken::from_yoctonear(0);
+        }
+
+        let initial_storage = env::storage_usage();
+        self.delegations.remove(&key);
+        self.delegations.flush();
+        self.pool_principals.flush();
+        let storage_freed = storage_cost(initial_storage.saturating_sub(env::storage_usage()));
+        self.release_storage_locked(storage_freed);
+
+        storage_freed
+    }
+}
+
+fn amount_args(amount: NearToken) -> Vec<u8> {
+    json!({ "amount": U128(amount.as_yoctonear()) })
+        .to_string()
+        .into_bytes()
+        .to_vec()
+}
+
+// `a * b / c` rounded down. Bits are dropped from the larger factor and the divisor when
+// the product does not fit, which only loses dust on amounts this large. None once the
+// divisor has no bits left
+fn mul_div(mut a: u128, mut b: u128, mut c: u128) -> Option<u128> {
+    loop {
+        if let Some(product) = a.checked_mul(b) {
+            return product.checked_div(c);
+        }
+        if a > b {
+            a >>= 1;
+        } else {
+            b >>= 1;
+        }
+        c >>= 1;
+    }
+}
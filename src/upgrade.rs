@@ -0,0 +1,66 @@
+use near_sdk::borsh::{self, BorshDeserialize};
+use near_sdk::store::LookupMap;
+use near_sdk::{env, near, AccountId, Gas, GasWeight, NearToken, Promise, PublicKey};
+
+use crate::access_control::Role;
+use crate::constants::DropId;
+use crate::drop_types::VersionedDrop;
+use crate::errors::DropError;
+use crate::{Contract, ContractExt};
+
+// State of the contract as first deployed, before it had an owner and could upgrade itself
+#[near(serializers = [borsh])]
+struct OldContract {
+    top_level_account: AccountId,
+    next_drop_id: DropId,
+    drop_by_id: LookupMap<DropId, VersionedDrop>,
+    drop_id_by_key: LookupMap<PublicKey, DropId>,
+}
+
+#[near]
+impl Contract {
+    // Deploys the code passed as raw input and calls `migrate` on it
+    #[handle_result]
+    pub fn update_contract(&self) -> Result<Promise, DropError> {
+        self.check_role(Role::Upgrader)?;
+
+        let code = env::input()
+            .filter(|code| !code.is_empty())
+            .ok_or(DropError::NoContractCode)?;
+
+        Ok(Promise::new(env::current_account_id())
+            .deploy_contract(code)
+            .function_call_weight(
+                "migrate".to_string(),
+                vec![],
+                NearToken::from_yoctonear(0),
+                Gas::from_tgas(0),
+                GasWeight(1),
+            ))
+    }
+
+    // Rebuilds the contract state after an upgrade. The state of the first version is
+    // extended with the default of every field added since, the contract account becoming
+    // the owner. Drops are not touched here, `VersionedDrop` upgrades each one the next
+    // time it is read
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        let state = env::storage_read(b"STATE").expect("Failed to read the contract state");
+        if let Ok(contract) = Contract::try_from_slice(&state) {
+            return contract;
+        }
+
+        let old_contract: OldContract =
+            borsh::from_slice(&state).expect("Failed to read the contract state");
+        Contract {
+            next_drop_id: old_contract.next_drop_id,
+            drop_by_id: old_contract.drop_by_id,
+            drop_id_by_key: old_contract.drop_id_by_key,
+            ..Contract::new(
+                old_contract.top_level_account,
+                Some(env::current_account_id()),
+            )
+        }
+    }
+}
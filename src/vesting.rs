@@ -0,0 +1,385 @@
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U64;
+use near_sdk::{env, log, near, AccountId, Gas, NearToken, Promise, PromiseError};
+
+use near_contract_standards::storage_management::StorageBalance;
+
+use crate::constants::*;
+use crate::drop_types::{Drop, Getters};
+use crate::errors::DropError;
+use crate::{ft_drop, storage_cost, Contract, ContractExt};
+
+const NANOS_PER_SECOND: u64 = 1_000_000_000;
+
+// Linear release of a claim, counted from the moment it is claimed
+#[derive(Clone, Debug, BorshDeserialize, BorshSerialize)]
+#[near(serializers = [json])]
+#[borsh(crate = "near_sdk::borsh")]
+pub struct Vesting {
+    pub cliff_seconds: u64,    // Nothing can be withdrawn before the cliff
+    pub duration_seconds: u64, // Everything can be withdrawn after the duration
+}
+
+impl Vesting {
+    pub fn check_valid(&self) -> Result<(), DropError> {
+        if self.duration_seconds == 0 {
+            return Err(DropError::InvalidVesting("Vesting duration cannot be 0"));
+        }
+        if self.cliff_seconds > self.duration_seconds {
+            return Err(DropError::InvalidVesting(
+                "Vesting cliff cannot be longer than the duration",
+            ));
+        }
+        Ok(())
+    }
+}
+
+// Funder of a vesting drop, which gets back what its grants do not use. Kept until the last
+// grant is settled, as the drop is removed once all its keys are claimed
+#[derive(Clone, Debug, BorshDeserialize, BorshSerialize)]
+#[borsh(crate = "near_sdk::borsh")]
+pub struct VestingFunder {
+    pub funder: AccountId,
+    pub open_grants: u32,
+}
+
+// What a recipient was granted by claiming a vesting drop
+#[derive(Clone, Debug, BorshDeserialize, BorshSerialize)]
+#[near(serializers = [json])]
+#[borsh(crate = "near_sdk::borsh")]
+pub struct VestingGrant {
+    // Contract of the fungible tokens granted, NEAR if none
+    ft_contract: Option<AccountId>,
+    total: NearToken,
+    withdrawn: NearToken,
+    // FT storage deposit held for the first withdrawal
+    registration: NearToken,
+    // Gas attached to FT transfers, as the drop may be gone by then
+    transfer_gas: Gas,
+    // Timestamp of the claim, in nanoseconds
+    start: U64,
+    vesting: Vesting,
+}
+
+impl VestingGrant {
+    // Splitting the total keeps the multiplication within u128 for any amount
+    pub fn vested(&self, now: u64) -> NearToken {
+        let elapsed = now.saturating_sub(self.start.0) / NANOS_PER_SECOND;
+        if elapsed < self.vesting.cliff_seconds {
+            return NearToken::from_yoctonear(0);
+        }
+        if elapsed >= self.vesting.duration_seconds {
+            return self.total;
+        }
+
+        let (elapsed, duration) = (elapsed as u128, self.vesting.duration_seconds as u128);
+        let total = self.total.as_yoctonear();
+        NearToken::from_yoctonear(
+            total / duration * elapsed + total % duration * elapsed / duration,
+        )
+    }
+
+    pub fn withdrawable(&self, now: u64) -> NearToken {
+        self.vested(now).saturating_sub(self.withdrawn)
+    }
+
+    fn is_settled(&self) -> bool {
+        self.withdrawn == self.total && self.registration.is_zero()
+    }
+}
+
+#[near]
+impl Contract {
+    // Sends the caller what has vested so far from their grant of the drop
+    #[handle_result]
+    pub fn withdraw_vested(&mut self, drop_id: DropId) -> Result<Promise, DropError> {
+        self.check_not_paused()?;
+        let account_id = env::predecessor_account_id();
+        let key = (drop_id, account_id.clone());
+
+        let mut grant = self
+            .vesting_grants
+            .get(&key)
+            .cloned()
+            .ok_or(DropError::NoVestingGrant(account_id.clone()))?;
+        let amount = grant.withdrawable(env::block_timestamp());
+        if amount.is_zero() {
+            return Err(DropError::NothingVested);
+        }
+
+        grant.withdrawn = grant.withdrawn.saturating_add(amount);
+        self.vesting_grants.insert(key, grant.clone());
+
+        let Some(ft_contract) = grant.ft_contract else {
+            self.release_near_owed(amount);
+            return Ok(Promise::new(account_id.clone())
+                .transfer(amount)
+                .then(resolve_withdrawal(drop_id, account_id, amount, None)));
+        };
+
+        if grant.registration.is_zero() {
+            return Ok(ft_drop::promise_to_transfer(
+                &ft_contract,
+                &account_id,
+                amount,
+                None,
+                grant.transfer_gas,
+            )
+            .then(resolve_withdrawal(drop_id, account_id, amount, None)));
+        }
+
+        // The first withdrawal registers the recipient on the FT contract if needed
+        let storage_check_gas = FT_STORAGE_CHECK_CALLBACK_GAS
+            .saturating_add(MIN_GAS_FOR_FT_STORAGE_DEPOSIT)
+            .saturating_add(grant.transfer_gas)
+            .saturating_add(VESTING_WITHDRAW_CALLBACK_GAS);
+
+        Ok(
+            ft_drop::promise_to_check_storage(&ft_contract, &account_id).then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(storage_check_gas)
+                    .with_unused_gas_weight(0)
+                    .resolve_vesting_storage_check(drop_id, account_id, amount),
+            ),
+        )
+    }
+
+    pub fn get_vesting_grant(
+        &self,
+        drop_id: DropId,
+        account_id: AccountId,
+    ) -> Option<VestingGrant> {
+        self.vesting_grants.get(&(drop_id, account_id)).cloned()
+    }
+
+    pub fn get_withdrawable(&self, drop_id: DropId, account_id: AccountId) -> NearToken {
+        self.vesting_grants
+            .get(&(drop_id, account_id))
+            .map(|grant| grant.withdrawable(env::block_timestamp()))
+            .unwrap_or(NearToken::from_yoctonear(0))
+    }
+
+    #[private]
+    pub fn resolve_vesting_storage_check(
+        &self,
+        drop_id: DropId,
+        account_id: AccountId,
+        amount: NearToken,
+        #[callback_result] storage_balance: Result<Option<StorageBalance>, PromiseError>,
+    ) -> Promise {
+        let grant = self
+            .vesting_grants
+            .get(&(drop_id, account_id.clone()))
+            .cloned()
+            .expect("No vesting grant for this account");
+
+        // If the query failed we cannot know, so we try to register the recipient anyway
+        let registered = matches!(storage_balance, Ok(Some(_)));
+        let ft_contract = grant.ft_contract.expect("Not an FT grant");
+
+        ft_drop::promise_to_transfer(
+            &ft_contract,
+            &account_id,
+            amount,
+            (!registered).then_some(grant.registration),
+            grant.transfer_gas,
+        )
+        .then(resolve_withdrawal(
+            drop_id,
+            account_id,
+            amount,
+            Some(registered),
+        ))
+    }
+
+    // A failed transfer goes back into the grant so it can be withdrawn again. `registered`
+    // is only given by withdrawals which used the FT registration
+    #[private]
+    pub fn resolve_vested_withdrawal(
+        &mut self,
+        drop_id: DropId,
+        account_id: AccountId,
+        amount: NearToken,
+        registered: Option<bool>,
+        #[callback_result] result: Result<(), PromiseError>,
+    ) -> bool {
+        let key = (drop_id, account_id);
+        let mut grant = self
+            .vesting_grants
+            .get(&key)
+            .cloned()
+            .expect("No vesting grant for this account");
+
+        // The registration was paid to the FT contract, or given back to the funder if it was
+        // not needed. A failed registration came back to the contract and is tried again
+        if let Some(registered) = registered {
+            if registered || result.is_ok() {
+                self.release_near_owed(grant.registration);
+                if registered {
+                    Promise::new(self.vesting_funder(drop_id)).transfer(grant.registration);
+                }
+                grant.registration = NearToken::from_yoctonear(0);
+            }
+        }
+
+        let succeeded = result.is_ok();
+        if succeeded {
+            if let Some(ft_contract) = &grant.ft_contract {
+                self.release_ft_liability(ft_contract, amount);
+            }
+        } else {
+            log!("Withdrawing from the grant of drop {drop_id} failed, it can be withdrawn again");
+            grant.withdrawn = grant.withdrawn.saturating_sub(amount);
+            if grant.ft_contract.is_none() {
+                self.add_near_owed(amount);
+            }
+        }
+
+        self.internal_save_grant(key, grant);
+
+        succeeded
+    }
+}
+
+impl Contract {
+    // Called when a vesting drop is claimed, in place of paying it out
+    pub(crate) fn add_vesting_grant(
+        &mut self,
+        drop_id: DropId,
+        drop: &Drop,
+        account_id: &AccountId,
+    ) -> Result<(), DropError> {
+        let config = drop.get_config();
+        let vesting = config.vesting.clone().expect("Not a vesting drop");
+
+        let (ft_contract, registration) = match drop {
+            Drop::NEAR(_) => (None, NearToken::from_yoctonear(0)),
+            Drop::FT(ft_drop) => {
+                if !ft_drop.is_funded() {
+                    return Err(DropError::NotFunded);
+                }
+                (
+                    Some(ft_drop.get_ft_contract().clone()),
+                    ft_drop.get_registration(),
+                )
+            }
+            _ => panic!("Only NEAR and FT drops can vest"),
+        };
+
+        let key = (drop_id, account_id.clone());
+        if self.vesting_grants.contains_key(&key) {
+            return Err(DropError::GrantExists(account_id.clone()));
+        }
+
+        self.vesting_grants.insert(
+            key,
+            VestingGrant {
+                ft_contract,
+                total: drop.get_amount_per_drop().unwrap(),
+                withdrawn: NearToken::from_yoctonear(0),
+                registration,
+                transfer_gas: config.transfer_gas(MIN_GAS_FOR_FT_TRANSFER),
+                start: U64(env::block_timestamp()),
+                vesting,
+            },
+        );
+        if let Some(vesting_funder) = self.vesting_funders.get_mut(&drop_id) {
+            vesting_funder.open_grants += 1;
+        }
+
+        Ok(())
+    }
+
+    // Nothing is paid out when a vesting drop is claimed. The grant keeps the NEAR dropped and
+    // the FT registration owed, the rest held by the key goes back to the funder
+    pub(crate) fn refund_vesting_claim(
+        &mut self,
+        drop: &Drop,
+        account_created: bool,
+        refund: NearToken,
+    ) -> Promise {
+        self.settle_claim_refund(refund);
+
+        let (kept, mut to_refund) = match drop {
+            Drop::NEAR(near_drop) => (near_drop.get_amount_per_drop().unwrap(), refund),
+            Drop::FT(ft_drop) => (
+                ft_drop.get_registration(),
+                refund.saturating_sub(ft_drop.get_registration()),
+            ),
+            _ => panic!("Only NEAR and FT drops can vest"),
+        };
+        self.add_near_owed(kept);
+
+        if account_created {
+            to_refund = to_refund.saturating_sub(CREATE_ACCOUNT_FEE);
+        }
+
+        Promise::new(drop.get_funder().clone()).transfer(to_refund)
+    }
+
+    // Starts tracking the funder of a new vesting drop
+    pub(crate) fn open_vesting_funder(&mut self, drop_id: DropId, drop: &Drop) {
+        if drop.get_config().vesting.is_none() {
+            return;
+        }
+
+        self.vesting_funders.insert(
+            drop_id,
+            VestingFunder {
+                funder: drop.get_funder().clone(),
+                open_grants: 0,
+            },
+        );
+    }
+
+    // The grants of a drop refund whoever owns it at the time
+    pub(crate) fn set_vesting_funder(&mut self, drop_id: DropId, funder: &AccountId) {
+        if let Some(vesting_funder) = self.vesting_funders.get_mut(&drop_id) {
+            vesting_funder.funder = funder.clone();
+        }
+    }
+
+    fn vesting_funder(&self, drop_id: DropId) -> AccountId {
+        self.vesting_funders
+            .get(&drop_id)
+            .map(|vesting_funder| vesting_funder.funder.clone())
+            .expect("No funder for this vesting drop")
+    }
+
+    // Settled grants are removed and their storage given back to the funder, along with the
+    // storage of the funder once the drop has no key or grant left
+    fn internal_save_grant(&mut self, key: (DropId, AccountId), grant: VestingGrant) {
+        if !grant.is_settled() {
+            self.vesting_grants.insert(key, grant);
+            return;
+        }
+
+        let drop_id = key.0;
+        let funder = self.vesting_funder(drop_id);
+        let initial_storage = env::storage_usage();
+        self.vesting_grants.remove(&key);
+        if let Some(vesting_funder) = self.vesting_funders.get_mut(&drop_id) {
+            vesting_funder.open_grants -= 1;
+            if vesting_funder.open_grants == 0 && !self.drop_by_id.contains_key(&drop_id) {
+                self.vesting_funders.remove(&drop_id);
+            }
+        }
+        self.flush_drops();
+        let storage_freed = storage_cost(initial_storage.saturating_sub(env::storage_usage()));
+
+        self.release_storage_locked(storage_freed);
+        Promise::new(funder).transfer(storage_freed);
+    }
+}
+
+fn resolve_withdrawal(
+    drop_id: DropId,
+    account_id: AccountId,
+    amount: NearToken,
+    registered: Option<bool>,
+) -> Promise {
+    Contract::ext(env::current_account_id())
+        .with_static_gas(VESTING_WITHDRAW_CALLBACK_GAS)
+        .with_unused_gas_weight(0)
+        .resolve_vested_withdrawal(drop_id, account_id, amount, registered)
+}
@@ -0,0 +1,43 @@
+use std::collections::BTreeMap;
+
+use near_sdk::serde_json::{json, Value};
+use near_sdk::{env, near, AccountId, PanicOnDefault};
+
+// Sputnik DAO that only serves `get_policy`, each role being a group of members
+#[derive(PanicOnDefault)]
+#[near(contract_state)]
+pub struct Dao {
+    roles: BTreeMap<String, Vec<AccountId>>,
+    policy_unavailable: bool,
+}
+
+#[near]
+impl Dao {
+    #[init]
+    pub fn new(roles: BTreeMap<String, Vec<AccountId>>) -> Self {
+        Self {
+            roles,
+            policy_unavailable: false,
+        }
+    }
+
+    // Makes `get_policy` panic from now on
+    pub fn break_policy(&mut self) {
+        self.policy_unavailable = true;
+    }
+
+    pub fn get_policy(&self) -> Value {
+        if self.policy_unavailable {
+            env::panic_str("Policy is unavailable");
+        }
+
+        // Anyone can propose, as in the default policy, so not every role is a group
+        let mut roles =
+            vec![json!({"name": "all", "kind": "Everyone", "permissions": ["*:AddProposal"]})];
+        roles.extend(self.roles.iter().map(|(name, members)| {
+            json!({"name": name, "kind": {"Group": members}, "permissions": ["*:*"]})
+        }));
+
+        json!({ "roles": roles })
+    }
+}
@@ -0,0 +1,77 @@
+use near_sdk::json_types::U128;
+use near_sdk::store::LookupMap;
+use near_sdk::{env, near, require, AccountId, NearToken, Promise};
+
+// Epochs the real pool holds unstaked NEAR for
+const NUM_EPOCHS_TO_UNLOCK: u64 = 4;
+
+#[derive(Default)]
+#[near(serializers = [borsh])]
+pub struct Account {
+    staked: u128,
+    unstaked: u128,
+    unlock_epoch: u64, // Set again by every unstake, for all the unstaked balance
+}
+
+// Staking pool without rewards, locking unstaked NEAR like the real one
+#[near(contract_state)]
+pub struct StakingPool {
+    accounts: LookupMap<AccountId, Account>,
+}
+
+impl Default for StakingPool {
+    fn default() -> Self {
+        Self {
+            accounts: LookupMap::new(b"a"),
+        }
+    }
+}
+
+#[near]
+impl StakingPool {
+    #[payable]
+    pub fn deposit_and_stake(&mut self) {
+        let account = self.account_mut(env::predecessor_account_id());
+        account.staked += env::attached_deposit().as_yoctonear();
+    }
+
+    pub fn unstake(&mut self, amount: U128) {
+        let account = self.account_mut(env::predecessor_account_id());
+        require!(amount.0 > 0, "Unstaking amount should be positive");
+        require!(account.staked >= amount.0, "Not enough staked balance to unstake");
+        account.staked -= amount.0;
+        account.unstaked += amount.0;
+        account.unlock_epoch = env::epoch_height() + NUM_EPOCHS_TO_UNLOCK;
+    }
+
+    pub fn withdraw(&mut self, amount: U128) -> Promise {
+        let account_id = env::predecessor_account_id();
+        let account = self.account_mut(account_id.clone());
+        require!(account.unstaked >= amount.0, "Not enough unstaked balance to withdraw");
+        require!(
+            account.unlock_epoch <= env::epoch_height(),
+            "The unstaked balance is not yet available due to unstaking delay"
+        );
+        account.unstaked -= amount.0;
+
+        Promise::new(account_id).transfer(NearToken::from_yoctonear(amount.0))
+    }
+
+    pub fn get_account_staked_balance(&self, account_id: AccountId) -> U128 {
+        U128(self.accounts.get(&account_id).map_or(0, |account| account.staked))
+    }
+
+    pub fn get_account_unstaked_balance(&self, account_id: AccountId) -> U128 {
+        U128(self.accounts.get(&account_id).map_or(0, |account| account.unstaked))
+    }
+
+    pub fn get_epoch_height(&self) -> u64 {
+        env::epoch_height()
+    }
+}
+
+impl StakingPool {
+    fn account_mut(&mut self, account_id: AccountId) -> &mut Account {
+        self.accounts.entry(account_id).or_default()
+    }
+}
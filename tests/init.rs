@@ -5,6 +5,22 @@ use near_workspaces::{Account, Contract, DevNetwork, Worker};
 pub async fn init(
     root: &Account,
     initial_contract_balance: NearToken,
+) -> anyhow::Result<(Account, Account, Account)> {
+    init_project(root, initial_contract_balance, ".").await
+}
+
+// Same as `init`, with the contract as first deployed
+pub async fn init_baseline(
+    root: &Account,
+    initial_contract_balance: NearToken,
+) -> anyhow::Result<(Account, Account, Account)> {
+    init_project(root, initial_contract_balance, "./tests/contracts/baseline").await
+}
+
+async fn init_project(
+    root: &Account,
+    initial_contract_balance: NearToken,
+    project: &str,
 ) -> anyhow::Result<(Account, Account, Account)> {
     let root_wasm = near_workspaces::compile_project("./tests/contracts/root").await?;
     let _ = root.deploy(&root_wasm).await?;
@@ -16,7 +32,7 @@ pub async fn init(
         .await?
         .unwrap();
 
-    let wasm = near_workspaces::compile_project(".").await?;
+    let wasm = near_workspaces::compile_project(project).await?;
 
     let _ = contract.deploy(&wasm).await?.unwrap();
 
@@ -36,7 +52,7 @@ pub async fn init(
         .await?;
     assert!(res.is_success());
 
-    return Ok((contract, creator, alice));
+    Ok((contract, creator, alice))
 }
 
 pub async fn init_ft_contract(
@@ -54,7 +70,7 @@ pub async fn init_ft_contract(
         .await?;
     assert!(res.is_success());
 
-    return Ok(ft_contract);
+    Ok(ft_contract)
 }
 
 pub async fn init_nft_contract(
@@ -82,5 +98,31 @@ pub async fn init_nft_contract(
         .await?;
     assert!(mint_res.is_success());
 
-    return Ok((nft_contract, token_id.to_string()));
+    Ok((nft_contract, token_id.to_string()))
+}
+
+pub async fn init_staking_pool(worker: &Worker<impl DevNetwork>) -> anyhow::Result<Contract> {
+    let pool_wasm = near_workspaces::compile_project("./tests/contracts/staking_pool").await?;
+    let staking_pool = worker.dev_deploy(&pool_wasm).await?;
+
+    Ok(staking_pool)
+}
+
+pub async fn init_dao_contract(
+    worker: &Worker<impl DevNetwork>,
+    council: &[&Account],
+) -> anyhow::Result<Contract> {
+    let dao_wasm = near_workspaces::compile_project("./tests/contracts/dao").await?;
+    let dao_contract = worker.dev_deploy(&dao_wasm).await?;
+
+    let members: Vec<_> = council.iter().map(|member| member.id()).collect();
+    let res = dao_contract
+        .call("new")
+        .args_json(json!({"roles": {"council": members}}))
+        .gas(Gas::from_tgas(100))
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    Ok(dao_contract)
 }
@@ -0,0 +1,206 @@
+use near_sdk::{serde_json::json, Gas, NearToken};
+use near_workspaces::types::{KeyType, SecretKey};
+use near_workspaces::Account;
+
+use crate::init::{init, init_baseline};
+use crate::utils::{get_user_balance, INITIAL_CONTRACT_BALANCE, ONE_HUNDRED_TGAS};
+
+#[tokio::test]
+async fn pause_blocks_creation() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let root = worker.root_account().unwrap();
+
+    let (contract, creator, alice) = init(&root, INITIAL_CONTRACT_BALANCE).await?;
+
+    // Only accounts with the Pauser role can pause the contract
+    let pause_result_1 = alice
+        .call(contract.id(), "pause")
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(pause_result_1.is_failure());
+
+    // The owner grants the Pauser role to Alice
+    let grant_result = contract
+        .call(contract.id(), "grant_role")
+        .args_json(json!({"account_id": alice.id(), "role": "Pauser"}))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(grant_result.is_success());
+
+    let pause_result_2 = alice
+        .call(contract.id(), "pause")
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(pause_result_2.is_success());
+
+    let secret_key = SecretKey::from_random(KeyType::ED25519);
+    let args = json!({"public_keys": [secret_key.public_key()], "amount_per_drop": NearToken::from_near(1)});
+
+    // No drops can be created while paused
+    let create_result_1 = creator
+        .call(contract.id(), "create_near_drop")
+        .args_json(args.clone())
+        .deposit(NearToken::from_millinear(1100))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(create_result_1.is_failure());
+
+    let unpause_result = alice
+        .call(contract.id(), "unpause")
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(unpause_result.is_success());
+
+    let create_result_2 = creator
+        .call(contract.id(), "create_near_drop")
+        .args_json(args)
+        .deposit(NearToken::from_millinear(1100))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(create_result_2.is_success());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn update_contract_keeps_drops() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let root = worker.root_account().unwrap();
+
+    let (contract, creator, alice) = init(&root, INITIAL_CONTRACT_BALANCE).await?;
+    let wasm = near_workspaces::compile_project(".").await?;
+
+    let secret_key = SecretKey::from_random(KeyType::ED25519);
+    let create_result = creator
+        .call(contract.id(), "create_near_drop")
+        .args_json(json!({"public_keys": [secret_key.public_key()], "amount_per_drop": NearToken::from_near(1)}))
+        .deposit(NearToken::from_millinear(1100))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    let drop_id: u32 = create_result.json()?;
+
+    // Only the owner or an Upgrader can deploy new code
+    let update_result_1 = alice
+        .call(contract.id(), "update_contract")
+        .args(wasm.clone())
+        .gas(Gas::from_tgas(300))
+        .transact()
+        .await?;
+    assert!(
+        format!("{:?}", update_result_1.into_result().unwrap_err()).contains("ERR_MISSING_ROLE")
+    );
+
+    // The code to deploy is the whole input
+    let update_result_2 = contract
+        .call(contract.id(), "update_contract")
+        .gas(Gas::from_tgas(300))
+        .transact()
+        .await?;
+    assert!(format!("{:?}", update_result_2.into_result().unwrap_err())
+        .contains("ERR_NO_CONTRACT_CODE"));
+
+    let update_result_3 = contract
+        .call(contract.id(), "update_contract")
+        .args(wasm)
+        .gas(Gas::from_tgas(300))
+        .transact()
+        .await?;
+    assert!(update_result_3.is_success());
+
+    // Drops stored before the upgrade are still readable
+    let get_drop_result = contract
+        .view(contract.id(), "get_drop_by_id")
+        .args_json(json!({"drop_id": drop_id}))
+        .await;
+    assert!(get_drop_result.is_ok());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn upgrade_from_first_version() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let root = worker.root_account().unwrap();
+
+    let (contract, creator, alice) = init_baseline(&root, INITIAL_CONTRACT_BALANCE).await?;
+
+    let secret_key_1 = SecretKey::from_random(KeyType::ED25519);
+    let secret_key_2 = SecretKey::from_random(KeyType::ED25519);
+    let create_result = creator
+        .call(contract.id(), "create_near_drop")
+        .args_json(json!({"public_keys": [secret_key_1.public_key(), secret_key_2.public_key()], "amount_per_drop": NearToken::from_near(1)}))
+        .deposit(NearToken::from_millinear(2200))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(create_result.is_success());
+    let drop_id: u32 = create_result.json()?;
+
+    // The first version cannot upgrade itself, the new code is deployed with the account key
+    let wasm = near_workspaces::compile_project(".").await?;
+    let _ = contract.deploy(&wasm).await?.unwrap();
+    let migrate_result = contract
+        .call(contract.id(), "migrate")
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(migrate_result.is_success());
+
+    let owner: String = contract
+        .view(contract.id(), "get_owner")
+        .args_json(json!({}))
+        .await?
+        .json()?;
+    assert_eq!(owner, contract.id().to_string());
+
+    // The drop is read in its old layout with the default settings
+    let drop: serde_json::Value = contract
+        .view(contract.id(), "get_drop_by_id")
+        .args_json(json!({"drop_id": drop_id}))
+        .await?
+        .json()?;
+    assert_eq!(drop["NEAR"]["counter"], json!(2));
+    assert_eq!(drop["NEAR"]["config"]["require_new_key"], json!(false));
+
+    let alice_balance = get_user_balance(&alice).await;
+    let claimer = Account::from_secret_key(contract.id().clone(), secret_key_1, &worker);
+    let claim_result = claimer
+        .call(contract.id(), "claim_for")
+        .args_json(json!({"account_id": alice.id()}))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(claim_result.is_success());
+    assert_eq!(
+        get_user_balance(&alice).await,
+        alice_balance.saturating_add(NearToken::from_near(1))
+    );
+
+    let drop: serde_json::Value = contract
+        .view(contract.id(), "get_drop_by_id")
+        .args_json(json!({"drop_id": drop_id}))
+        .await?
+        .json()?;
+    assert_eq!(drop["NEAR"]["counter"], json!(1));
+
+    // New drops continue the ids of the first version
+    let secret_key_3 = SecretKey::from_random(KeyType::ED25519);
+    let create_result_2 = creator
+        .call(contract.id(), "create_near_drop")
+        .args_json(json!({"public_keys": [secret_key_3.public_key()], "amount_per_drop": NearToken::from_near(1)}))
+        .deposit(NearToken::from_millinear(1100))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(create_result_2.is_success());
+    assert_eq!(create_result_2.json::<u32>()?, drop_id + 1);
+
+    Ok(())
+}
@@ -0,0 +1,202 @@
+use near_sdk::{serde_json::json, NearToken};
+use near_workspaces::types::{KeyType, SecretKey};
+use near_workspaces::Account;
+
+use crate::init::{init, init_dao_contract, init_ft_contract, init_nft_contract};
+use crate::utils::{get_user_balance, INITIAL_CONTRACT_BALANCE, ONE_HUNDRED_TGAS};
+
+#[tokio::test]
+async fn drop_for_nft_holders() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let root = worker.root_account().unwrap();
+
+    let (contract, creator, alice) = init(&root, INITIAL_CONTRACT_BALANCE).await?;
+    let (nft_contract, _) = init_nft_contract(&worker, &creator).await?;
+
+    // Only holders of the collection can claim, the creator holds its only token
+    let secret_key = SecretKey::from_random(KeyType::ED25519);
+    let amount_per_drop = NearToken::from_near(1);
+    let create_result = creator
+        .call(contract.id(), "create_near_drop")
+        .args_json(json!({
+            "public_keys": [secret_key.public_key()],
+            "amount_per_drop": amount_per_drop,
+            "config": {"eligibility": {"NftHolder": {"nft_contract": nft_contract.id()}}}
+        }))
+        .deposit(NearToken::from_millinear(1100))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(create_result.is_success());
+
+    let claimer: Account = Account::from_secret_key(contract.id().clone(), secret_key, &worker);
+
+    // Alice holds no NFT
+    let claim_result_1 = claimer
+        .call(contract.id(), "claim_for")
+        .args_json(json!({"account_id": alice.id()}))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(claim_result_1.is_failure());
+
+    // The key is left untouched, so a holder can still claim with it
+    let creator_balance_before = get_user_balance(&creator).await;
+    let claim_result_2 = claimer
+        .call(contract.id(), "claim_for")
+        .args_json(json!({"account_id": creator.id()}))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(claim_result_2.is_success());
+
+    let creator_balance_after = get_user_balance(&creator).await;
+    assert!(creator_balance_after >= creator_balance_before.saturating_add(amount_per_drop));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn drop_for_ft_holders() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let root = worker.root_account().unwrap();
+
+    let (contract, creator, alice) = init(&root, INITIAL_CONTRACT_BALANCE).await?;
+    let ft_contract = init_ft_contract(&worker, &creator).await?;
+
+    // Recipients need at least 100 of the FT
+    let secret_key = SecretKey::from_random(KeyType::ED25519);
+    let amount_per_drop = NearToken::from_near(1);
+    let create_result = creator
+        .call(contract.id(), "create_near_drop")
+        .args_json(json!({
+            "public_keys": [secret_key.public_key()],
+            "amount_per_drop": amount_per_drop,
+            "config": {"eligibility": {"FtHolder": {"ft_contract": ft_contract.id(), "min_balance": "100"}}}
+        }))
+        .deposit(NearToken::from_millinear(1100))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(create_result.is_success());
+
+    let storage_deposit_result = creator
+        .call(ft_contract.id(), "storage_deposit")
+        .args_json(json!({"account_id": alice.id()}))
+        .deposit(NearToken::from_yoctonear(12500000000000000000000))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(storage_deposit_result.is_success());
+
+    let ft_transfer_result_1 = creator
+        .call(ft_contract.id(), "ft_transfer")
+        .args_json(json!({"receiver_id": alice.id(), "amount": "99"}))
+        .deposit(NearToken::from_yoctonear(1))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(ft_transfer_result_1.is_success());
+
+    let claimer: Account = Account::from_secret_key(contract.id().clone(), secret_key, &worker);
+
+    // Alice holds 99, one short of the threshold
+    let claim_result_1 = claimer
+        .call(contract.id(), "claim_for")
+        .args_json(json!({"account_id": alice.id()}))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(format!("{:?}", claim_result_1.into_result().unwrap_err()).contains("ERR_NOT_ELIGIBLE"));
+
+    let ft_transfer_result_2 = creator
+        .call(ft_contract.id(), "ft_transfer")
+        .args_json(json!({"receiver_id": alice.id(), "amount": "1"}))
+        .deposit(NearToken::from_yoctonear(1))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(ft_transfer_result_2.is_success());
+
+    // Holding exactly the threshold is enough
+    let alice_balance_before = get_user_balance(&alice).await;
+    let claim_result_2 = claimer
+        .call(contract.id(), "claim_for")
+        .args_json(json!({"account_id": alice.id()}))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(claim_result_2.is_success());
+    assert_eq!(
+        get_user_balance(&alice).await,
+        alice_balance_before.saturating_add(amount_per_drop)
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn drop_for_dao_members() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let root = worker.root_account().unwrap();
+
+    let (contract, creator, alice) = init(&root, INITIAL_CONTRACT_BALANCE).await?;
+    let bob = root.create_subaccount("bob").transact().await?.unwrap();
+    let dao_contract = init_dao_contract(&worker, &[&bob]).await?;
+
+    // Only the council can claim, bob is its only member
+    let secret_key_1 = SecretKey::from_random(KeyType::ED25519);
+    let secret_key_2 = SecretKey::from_random(KeyType::ED25519);
+    let amount_per_drop = NearToken::from_near(1);
+    let create_result = creator
+        .call(contract.id(), "create_near_drop")
+        .args_json(json!({
+            "public_keys": [secret_key_1.public_key(), secret_key_2.public_key()],
+            "amount_per_drop": amount_per_drop,
+            "config": {"eligibility": {"DaoMember": {"dao_contract": dao_contract.id(), "role": "council"}}}
+        }))
+        .deposit(NearToken::from_millinear(2200))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(create_result.is_success());
+
+    let claimer_1: Account = Account::from_secret_key(contract.id().clone(), secret_key_1, &worker);
+    let claimer_2: Account = Account::from_secret_key(contract.id().clone(), secret_key_2, &worker);
+
+    // Alice is only in the role everyone has
+    let claim_result_1 = claimer_1
+        .call(contract.id(), "claim_for")
+        .args_json(json!({"account_id": alice.id()}))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(format!("{:?}", claim_result_1.into_result().unwrap_err()).contains("ERR_NOT_ELIGIBLE"));
+
+    let bob_balance_before = get_user_balance(&bob).await;
+    let claim_result_2 = claimer_1
+        .call(contract.id(), "claim_for")
+        .args_json(json!({"account_id": bob.id()}))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(claim_result_2.is_success());
+    assert_eq!(
+        get_user_balance(&bob).await,
+        bob_balance_before.saturating_add(amount_per_drop)
+    );
+
+    // A DAO whose policy cannot be read lets no one claim, members included
+    let break_result = dao_contract.call("break_policy").transact().await?;
+    assert!(break_result.is_success());
+
+    let claim_result_3 = claimer_2
+        .call(contract.id(), "claim_for")
+        .args_json(json!({"account_id": bob.id()}))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(format!("{:?}", claim_result_3.into_result().unwrap_err()).contains("ERR_NOT_ELIGIBLE"));
+
+    Ok(())
+}
@@ -0,0 +1,181 @@
+use near_sdk::{serde_json::json, NearToken};
+use near_workspaces::types::{KeyType, SecretKey};
+
+use crate::init::{init, init_ft_contract};
+use crate::utils::{INITIAL_CONTRACT_BALANCE, ONE_HUNDRED_TGAS};
+
+#[tokio::test]
+async fn near_drop_fees() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let root = worker.root_account().unwrap();
+
+    let (contract, creator, alice) = init(&root, INITIAL_CONTRACT_BALANCE).await?;
+
+    // 0.01 N per key plus 1% of the NEAR dropped
+    let set_fees_result = contract
+        .call(contract.id(), "set_fees")
+        .args_json(json!({"fees": {"per_key": NearToken::from_millinear(10), "near_bps": 100, "ft_bps": 0}}))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(set_fees_result.is_success());
+
+    let secret_key = SecretKey::from_random(KeyType::ED25519);
+    let create_result = creator
+        .call(contract.id(), "create_near_drop")
+        .args_json(json!({"public_keys": [secret_key.public_key()], "amount_per_drop": NearToken::from_near(1)}))
+        .deposit(NearToken::from_millinear(1100))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(create_result.is_success());
+
+    let fee_balance: NearToken = contract
+        .view(contract.id(), "get_fee_balance")
+        .args_json(json!({}))
+        .await?
+        .json()?;
+    assert_eq!(fee_balance, NearToken::from_millinear(20));
+
+    // Only the owner can withdraw the fees
+    let withdraw_result_1 = alice
+        .call(contract.id(), "withdraw_fees")
+        .args_json(json!({}))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(withdraw_result_1.is_failure());
+
+    let withdraw_result_2 = contract
+        .call(contract.id(), "withdraw_fees")
+        .args_json(json!({}))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(withdraw_result_2.is_success());
+
+    let fee_balance: NearToken = contract
+        .view(contract.id(), "get_fee_balance")
+        .args_json(json!({}))
+        .await?
+        .json()?;
+    assert!(fee_balance.is_zero());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn ft_drop_fees() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let root = worker.root_account().unwrap();
+
+    let (contract, creator, alice) = init(&root, INITIAL_CONTRACT_BALANCE).await?;
+    let ft_contract = init_ft_contract(&worker, &creator).await?;
+
+    // 1% of the FTs dropped, paid in the same FT
+    let set_fees_result = contract
+        .call(contract.id(), "set_fees")
+        .args_json(json!({"fees": {"per_key": "0", "near_bps": 0, "ft_bps": 100}}))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(set_fees_result.is_success());
+
+    let storage_deposit_result = creator
+        .call(ft_contract.id(), "storage_deposit")
+        .args_json(json!({"account_id": contract.id()}))
+        .deposit(NearToken::from_yoctonear(12500000000000000000000))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(storage_deposit_result.is_success());
+
+    let create_result = creator
+        .call(contract.id(), "create_ft_drop")
+        .args_json(json!({"public_keys": [SecretKey::from_random(KeyType::ED25519).public_key()], "ft_contract": ft_contract.id(), "amount_per_drop": "1000"}))
+        .deposit(NearToken::from_millinear(407))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(create_result.is_success());
+    let drop_id: u32 = create_result.json()?;
+
+    // The drop is funded with the tokens to drop and the fee
+    let ft_transfer_result = creator
+        .call(ft_contract.id(), "ft_transfer_call")
+        .args_json(
+            json!({"receiver_id": contract.id(), "amount": "1010", "msg": drop_id.to_string()}),
+        )
+        .deposit(NearToken::from_yoctonear(1))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(ft_transfer_result.is_success());
+
+    let fee_balance: NearToken = contract
+        .view(contract.id(), "get_fee_balance")
+        .args_json(json!({"token": ft_contract.id()}))
+        .await?
+        .json()?;
+    assert_eq!(fee_balance, NearToken::from_yoctonear(10));
+
+    // Alice owns the contract but is not registered with the FT contract, so the transfer
+    // fails and the fees are kept
+    let set_owner_result = contract
+        .call(contract.id(), "set_owner")
+        .args_json(json!({"new_owner": alice.id()}))
+        .transact()
+        .await?;
+    assert!(set_owner_result.is_success());
+
+    let withdraw_result_1 = alice
+        .call(contract.id(), "withdraw_fees")
+        .args_json(json!({"token": ft_contract.id()}))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    let withdrawn: bool = withdraw_result_1.json()?;
+    assert!(!withdrawn);
+
+    let fee_balance: NearToken = contract
+        .view(contract.id(), "get_fee_balance")
+        .args_json(json!({"token": ft_contract.id()}))
+        .await?
+        .json()?;
+    assert_eq!(fee_balance, NearToken::from_yoctonear(10));
+
+    let storage_deposit_result = alice
+        .call(ft_contract.id(), "storage_deposit")
+        .args_json(json!({"account_id": alice.id()}))
+        .deposit(NearToken::from_yoctonear(12500000000000000000000))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(storage_deposit_result.is_success());
+
+    let withdraw_result_2 = alice
+        .call(contract.id(), "withdraw_fees")
+        .args_json(json!({"token": ft_contract.id()}))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    let withdrawn: bool = withdraw_result_2.json()?;
+    assert!(withdrawn);
+
+    let fee_balance: NearToken = contract
+        .view(contract.id(), "get_fee_balance")
+        .args_json(json!({"token": ft_contract.id()}))
+        .await?
+        .json()?;
+    assert!(fee_balance.is_zero());
+
+    let alice_ft_balance = ft_contract
+        .call("ft_balance_of")
+        .args_json((alice.id(),))
+        .view()
+        .await?
+        .json::<NearToken>()?;
+    assert_eq!(alice_ft_balance, NearToken::from_yoctonear(10));
+
+    Ok(())
+}
@@ -1,11 +1,11 @@
 use near_sdk::{serde_json::json, AccountId, NearToken};
 use near_workspaces::{
     types::{KeyType, SecretKey},
-    Account,
+    Account, Contract,
 };
 
 use crate::init::{init, init_ft_contract};
-use crate::utils::{INITIAL_CONTRACT_BALANCE, ONE_HUNDRED_TGAS};
+use crate::utils::{get_user_balance, INITIAL_CONTRACT_BALANCE, ONE_HUNDRED_TGAS};
 
 #[tokio::test]
 async fn drop_on_existing_account() -> anyhow::Result<()> {
@@ -90,8 +90,9 @@ async fn drop_on_existing_account() -> anyhow::Result<()> {
         .args_json(json!({"account_id": alice.id()}))
         .gas(ONE_HUNDRED_TGAS)
         .transact()
-        .await?;
-    assert!(failed_claim_result.is_failure());
+        .await;
+    // The key is deleted after claiming, so the transaction may be rejected altogether
+    assert!(!failed_claim_result.is_ok_and(|result| result.is_success()));
 
     let claim_result_2 = claimer_2
         .call(contract.id(), "claim_for")
@@ -215,8 +216,9 @@ async fn drop_on_new_account() -> anyhow::Result<()> {
         .args_json(json!({"account_id": alice.id()}))
         .gas(ONE_HUNDRED_TGAS)
         .transact()
-        .await?;
-    assert!(claim_result_2.is_failure());
+        .await;
+    // The key is deleted after claiming, so the transaction may be rejected altogether
+    assert!(!claim_result_2.is_ok_and(|result| result.is_success()));
 
     let get_drop_result_2 = creator
         .call(contract.id(), "get_drop_by_id")
@@ -227,3 +229,275 @@ async fn drop_on_new_account() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn drop_with_gas_overrides() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let root = worker.root_account().unwrap();
+
+    let (contract, creator, alice) = init(&root, INITIAL_CONTRACT_BALANCE).await?;
+    let ft_contract = init_ft_contract(&worker, &creator).await?;
+
+    let amount_per_drop = NearToken::from_yoctonear(1);
+    let secret_key = SecretKey::from_random(KeyType::ED25519);
+
+    // Above the 100 TGas allowed for transfers
+    let create_drop_result_1 = creator
+        .call(contract.id(), "create_ft_drop")
+        .args_json(json!({"public_keys": [secret_key.public_key()], "ft_contract": ft_contract.id(), "amount_per_drop": amount_per_drop, "config": {"transfer_gas": "101000000000000"}}))
+        .deposit(NearToken::from_millinear(407))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(
+        format!("{:?}", create_drop_result_1.into_result().unwrap_err())
+            .contains("ERR_GAS_OUT_OF_BOUNDS")
+    );
+
+    // Below the 10 TGas the claim callback needs
+    let create_drop_result_2 = creator
+        .call(contract.id(), "create_ft_drop")
+        .args_json(json!({"public_keys": [secret_key.public_key()], "ft_contract": ft_contract.id(), "amount_per_drop": amount_per_drop, "config": {"callback_gas": "1000000000000"}}))
+        .deposit(NearToken::from_millinear(407))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(
+        format!("{:?}", create_drop_result_2.into_result().unwrap_err())
+            .contains("ERR_GAS_OUT_OF_BOUNDS")
+    );
+
+    // Within bounds the claim runs with the gas asked for
+    let create_drop_result_3 = creator
+        .call(contract.id(), "create_ft_drop")
+        .args_json(json!({"public_keys": [secret_key.public_key()], "ft_contract": ft_contract.id(), "amount_per_drop": amount_per_drop, "config": {"transfer_gas": "20000000000000", "callback_gas": "20000000000000"}}))
+        .deposit(NearToken::from_millinear(407))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(create_drop_result_3.is_success());
+
+    let drop_id: serde_json::Value = create_drop_result_3.json().unwrap();
+    let drop: serde_json::Value = contract
+        .view(contract.id(), "get_drop_by_id")
+        .args_json(json!({"drop_id": drop_id}))
+        .await?
+        .json()?;
+    assert_eq!(drop["FT"]["config"]["transfer_gas"], "20000000000000");
+
+    fund_ft_drop(&creator, &contract, &ft_contract, &drop_id, amount_per_drop).await?;
+
+    let claimer: Account = Account::from_secret_key(contract.id().clone(), secret_key, &worker);
+    let claim_result = claimer
+        .call(contract.id(), "claim_for")
+        .args_json(json!({"account_id": alice.id()}))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(claim_result.is_success());
+
+    let alice_ft_balance = ft_contract
+        .call("ft_balance_of")
+        .args_json((alice.id(),))
+        .view()
+        .await?
+        .json::<NearToken>()?;
+    assert_eq!(alice_ft_balance, amount_per_drop);
+
+    Ok(())
+}
+
+// Registers the drop contract with the FT contract if needed and sends it the tokens of a drop
+async fn fund_ft_drop(
+    creator: &Account,
+    contract: &Account,
+    ft_contract: &Contract,
+    drop_id: &serde_json::Value,
+    amount: NearToken,
+) -> anyhow::Result<()> {
+    let storage_deposit_result = creator
+        .call(ft_contract.id(), "storage_deposit")
+        .args_json(json!({"account_id": contract.id(), "registration_only": true}))
+        .deposit(NearToken::from_yoctonear(12500000000000000000000))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(storage_deposit_result.is_success());
+
+    let ft_transfer_result = creator
+        .call(ft_contract.id(), "ft_transfer_call")
+        .args_json(
+            json!({"receiver_id": contract.id(), "amount": amount, "msg": drop_id.to_string()}),
+        )
+        .deposit(NearToken::from_yoctonear(1))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(ft_transfer_result.is_success());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn drop_registers_only_unregistered_claimers() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let root = worker.root_account().unwrap();
+
+    let (contract, creator, alice) = init(&root, INITIAL_CONTRACT_BALANCE).await?;
+    let bob = root.create_subaccount("bob").transact().await?.unwrap();
+    let ft_contract = init_ft_contract(&worker, &creator).await?;
+
+    let amount_per_drop = NearToken::from_yoctonear(1);
+    let secret_key_1 = SecretKey::from_random(KeyType::ED25519);
+    let secret_key_2 = SecretKey::from_random(KeyType::ED25519);
+
+    // One drop per key, so both claims free the same storage
+    let mut drop_ids = vec![];
+    for secret_key in [&secret_key_1, &secret_key_2] {
+        let create_drop_result = creator
+            .call(contract.id(), "create_ft_drop")
+            .args_json(json!({"public_keys": [secret_key.public_key()], "ft_contract": ft_contract.id(), "amount_per_drop": amount_per_drop}))
+            .deposit(NearToken::from_millinear(407))
+            .gas(ONE_HUNDRED_TGAS)
+            .transact()
+            .await?;
+        assert!(create_drop_result.is_success());
+        drop_ids.push(create_drop_result.json::<serde_json::Value>()?);
+    }
+
+    let claimer_1: Account = Account::from_secret_key(contract.id().clone(), secret_key_1, &worker);
+    let claimer_2: Account = Account::from_secret_key(contract.id().clone(), secret_key_2, &worker);
+
+    // Nothing is checked or registered before the tokens arrive
+    let claim_result_1 = claimer_1
+        .call(contract.id(), "claim_for")
+        .args_json(json!({"account_id": alice.id()}))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(format!("{:?}", claim_result_1.into_result().unwrap_err()).contains("ERR_NOT_FUNDED"));
+
+    for drop_id in &drop_ids {
+        fund_ft_drop(&creator, &contract, &ft_contract, drop_id, amount_per_drop).await?;
+    }
+
+    let drop: serde_json::Value = contract
+        .view(contract.id(), "get_drop_by_id")
+        .args_json(json!({"drop_id": drop_ids[0]}))
+        .await?
+        .json()?;
+    let registration: NearToken = serde_json::from_value(drop["FT"]["registration"].clone())?;
+
+    // Alice is already registered, bob is not
+    let storage_deposit_result = creator
+        .call(ft_contract.id(), "storage_deposit")
+        .args_json(json!({"account_id": alice.id()}))
+        .deposit(registration)
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(storage_deposit_result.is_success());
+
+    let creator_balance_1 = get_user_balance(&creator).await;
+    let claim_result_2 = claimer_1
+        .call(contract.id(), "claim_for")
+        .args_json(json!({"account_id": alice.id()}))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(claim_result_2.is_success());
+
+    let creator_balance_2 = get_user_balance(&creator).await;
+    let claim_result_3 = claimer_2
+        .call(contract.id(), "claim_for")
+        .args_json(json!({"account_id": bob.id()}))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(claim_result_3.is_success());
+    let creator_balance_3 = get_user_balance(&creator).await;
+
+    // Both got the tokens, and the registration of alice went back to the creator
+    for account in [&alice, &bob] {
+        let ft_balance = ft_contract
+            .call("ft_balance_of")
+            .args_json((account.id(),))
+            .view()
+            .await?
+            .json::<NearToken>()?;
+        assert_eq!(ft_balance, amount_per_drop);
+    }
+    assert_eq!(
+        creator_balance_2.saturating_sub(creator_balance_1),
+        creator_balance_3
+            .saturating_sub(creator_balance_2)
+            .saturating_add(registration)
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn drop_caches_storage_bounds() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let root = worker.root_account().unwrap();
+
+    let (contract, creator, _) = init(&root, INITIAL_CONTRACT_BALANCE).await?;
+    let ft_contract = init_ft_contract(&worker, &creator).await?;
+
+    let amount_per_drop = NearToken::from_yoctonear(1);
+    let deposit = NearToken::from_millinear(407);
+
+    // Without a contract there are no storage bounds, the creator gets the deposit back
+    let not_ft_contract = root.create_subaccount("not-ft").transact().await?.unwrap();
+    let creator_balance_before = get_user_balance(&creator).await;
+    let create_drop_result_1 = creator
+        .call(contract.id(), "create_ft_drop")
+        .args_json(json!({"public_keys": [SecretKey::from_random(KeyType::ED25519).public_key()], "ft_contract": not_ft_contract.id(), "amount_per_drop": amount_per_drop}))
+        .deposit(deposit)
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(create_drop_result_1.is_success());
+    assert!(create_drop_result_1.json::<Option<u32>>()?.is_none());
+    assert!(
+        get_user_balance(&creator).await
+            > creator_balance_before.saturating_sub(NearToken::from_millinear(50))
+    );
+
+    // The first drop of a token fetches its bounds, later ones reuse them
+    let create_drop_result_2 = creator
+        .call(contract.id(), "create_ft_drop")
+        .args_json(json!({"public_keys": [SecretKey::from_random(KeyType::ED25519).public_key()], "ft_contract": ft_contract.id(), "amount_per_drop": amount_per_drop}))
+        .deposit(deposit)
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(create_drop_result_2.is_success());
+    let receipts_2 = create_drop_result_2.receipt_outcomes().len();
+    assert_eq!(create_drop_result_2.json::<Option<u32>>()?, Some(0));
+
+    let create_drop_result_3 = creator
+        .call(contract.id(), "create_ft_drop")
+        .args_json(json!({"public_keys": [SecretKey::from_random(KeyType::ED25519).public_key()], "ft_contract": ft_contract.id(), "amount_per_drop": amount_per_drop}))
+        .deposit(deposit)
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(create_drop_result_3.is_success());
+    assert!(create_drop_result_3.receipt_outcomes().len() < receipts_2);
+    assert_eq!(create_drop_result_3.json::<Option<u32>>()?, Some(1));
+
+    // Both drops charge what the FT contract asks to register a claimer
+    let bounds: serde_json::Value = ft_contract.view("storage_balance_bounds").await?.json()?;
+    for drop_id in 0..2 {
+        let drop: serde_json::Value = contract
+            .view(contract.id(), "get_drop_by_id")
+            .args_json(json!({"drop_id": drop_id}))
+            .await?
+            .json()?;
+        assert_eq!(drop["FT"]["registration"], bounds["min"]);
+    }
+
+    Ok(())
+}
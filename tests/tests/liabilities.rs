@@ -0,0 +1,205 @@
+use near_sdk::{serde_json::json, Gas, NearToken};
+use near_workspaces::types::{KeyType, SecretKey};
+use near_workspaces::Account;
+
+use crate::init::{init, init_ft_contract};
+use crate::utils::{INITIAL_CONTRACT_BALANCE, ONE_HUNDRED_TGAS};
+
+#[tokio::test]
+async fn near_drop_liabilities() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let root = worker.root_account().unwrap();
+
+    let (contract, creator, alice) = init(&root, INITIAL_CONTRACT_BALANCE).await?;
+
+    let secret_key_1 = SecretKey::from_random(KeyType::ED25519);
+    let secret_key_2 = SecretKey::from_random(KeyType::ED25519);
+
+    let create_result = creator
+        .call(contract.id(), "create_near_drop")
+        .args_json(json!({"public_keys": [secret_key_1.public_key(), secret_key_2.public_key()], "amount_per_drop": NearToken::from_near(1)}))
+        .deposit(NearToken::from_millinear(2100))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(create_result.is_success());
+
+    let liabilities: serde_json::Value = contract
+        .view(contract.id(), "get_liabilities")
+        .args_json(json!({}))
+        .await?
+        .json()?;
+    assert_eq!(
+        liabilities["near_owed"],
+        json!(NearToken::from_near(2)),
+        "both keys should be owed their NEAR"
+    );
+    assert_eq!(
+        liabilities["allowance_reserved"],
+        json!(NearToken::from_millinear(60))
+    );
+
+    let total: NearToken = serde_json::from_value(liabilities["total"].clone())?;
+    let balance: NearToken = serde_json::from_value(liabilities["balance"].clone())?;
+    assert!(balance >= total, "contract should be solvent");
+
+    let claimer: Account = Account::from_secret_key(contract.id().clone(), secret_key_1, &worker);
+    let claim_result = claimer
+        .call(contract.id(), "claim_for")
+        .args_json(json!({"account_id": alice.id()}))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(claim_result.is_success());
+
+    // Only the unclaimed key is still owed
+    let liabilities: serde_json::Value = contract
+        .view(contract.id(), "get_liabilities")
+        .args_json(json!({}))
+        .await?
+        .json()?;
+    assert_eq!(liabilities["near_owed"], json!(NearToken::from_near(1)));
+    assert_eq!(
+        liabilities["allowance_reserved"],
+        json!(NearToken::from_millinear(30))
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn sweep_surplus_keeps_liabilities() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let root = worker.root_account().unwrap();
+
+    let (contract, creator, alice) = init(&root, INITIAL_CONTRACT_BALANCE).await?;
+
+    let secret_key = SecretKey::from_random(KeyType::ED25519);
+    let create_result = creator
+        .call(contract.id(), "create_near_drop")
+        .args_json(json!({"public_keys": [secret_key.public_key()], "amount_per_drop": NearToken::from_near(1)}))
+        .deposit(NearToken::from_millinear(1100))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(create_result.is_success());
+
+    // Only the owner can sweep
+    let sweep_result_1 = alice
+        .call(contract.id(), "sweep_surplus")
+        .args_json(json!({"receiver": alice.id()}))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(format!("{:?}", sweep_result_1.into_result().unwrap_err()).contains("ERR_NOT_OWNER"));
+
+    let sweep_result_2 = contract
+        .call(contract.id(), "sweep_surplus")
+        .args_json(json!({"receiver": root.id()}))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(sweep_result_2.is_success());
+
+    let liabilities: serde_json::Value = contract
+        .view(contract.id(), "get_liabilities")
+        .args_json(json!({}))
+        .await?
+        .json()?;
+    let total: NearToken = serde_json::from_value(liabilities["total"].clone())?;
+    let balance: NearToken = serde_json::from_value(liabilities["balance"].clone())?;
+    assert!(balance >= total, "contract should still be solvent");
+
+    // The drop can still be claimed
+    let claimer: Account = Account::from_secret_key(contract.id().clone(), secret_key, &worker);
+    let claim_result = claimer
+        .call(contract.id(), "claim_for")
+        .args_json(json!({"account_id": alice.id()}))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(claim_result.is_success());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn sweep_ft_keeps_funding_of_unfunded_drops() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let root = worker.root_account().unwrap();
+
+    let (contract, creator, _) = init(&root, INITIAL_CONTRACT_BALANCE).await?;
+    let ft_contract = init_ft_contract(&worker, &creator).await?;
+
+    // The creator owns the contract, and so receives what is swept
+    let set_owner_result = contract
+        .call(contract.id(), "set_owner")
+        .args_json(json!({"new_owner": creator.id()}))
+        .transact()
+        .await?;
+    assert!(set_owner_result.is_success());
+
+    let storage_deposit_result = creator
+        .call(ft_contract.id(), "storage_deposit")
+        .args_json(json!({"account_id": contract.id()}))
+        .deposit(NearToken::from_yoctonear(12500000000000000000000))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(storage_deposit_result.is_success());
+
+    let create_result = creator
+        .call(contract.id(), "create_ft_drop")
+        .args_json(json!({"public_keys": [SecretKey::from_random(KeyType::ED25519).public_key()], "ft_contract": ft_contract.id(), "amount_per_drop": "10"}))
+        .deposit(NearToken::from_millinear(407))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(create_result.is_success());
+
+    // The tokens of the drop are credited, as by `ft_transfer_call` before `ft_on_transfer`
+    let ft_transfer_result_1 = creator
+        .call(ft_contract.id(), "ft_transfer")
+        .args_json(json!({"receiver_id": contract.id(), "amount": "10"}))
+        .deposit(NearToken::from_yoctonear(1))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(ft_transfer_result_1.is_success());
+
+    let sweep_result_1 = creator
+        .call(contract.id(), "sweep_ft")
+        .args_json(json!({"ft_contract": ft_contract.id()}))
+        .gas(Gas::from_tgas(300))
+        .transact()
+        .await?;
+    assert!(format!("{:?}", sweep_result_1.into_result().unwrap_err()).contains("ERR_NO_SURPLUS"));
+
+    // Only what comes on top is swept
+    let ft_transfer_result_2 = creator
+        .call(ft_contract.id(), "ft_transfer")
+        .args_json(json!({"receiver_id": contract.id(), "amount": "5"}))
+        .deposit(NearToken::from_yoctonear(1))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(ft_transfer_result_2.is_success());
+
+    let sweep_result_2 = creator
+        .call(contract.id(), "sweep_ft")
+        .args_json(json!({"ft_contract": ft_contract.id()}))
+        .gas(Gas::from_tgas(300))
+        .transact()
+        .await?;
+    assert!(sweep_result_2.is_success());
+
+    let contract_ft_balance = ft_contract
+        .call("ft_balance_of")
+        .args_json((contract.id(),))
+        .view()
+        .await?
+        .json::<NearToken>()?;
+    assert_eq!(contract_ft_balance, NearToken::from_yoctonear(10));
+
+    Ok(())
+}
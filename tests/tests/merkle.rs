@@ -0,0 +1,232 @@
+use near_drops::merkle;
+use near_sdk::json_types::Base58CryptoHash;
+use near_sdk::{serde_json::json, AccountId, NearToken};
+
+use crate::init::{init, init_ft_contract};
+use crate::utils::{get_user_balance, INITIAL_CONTRACT_BALANCE, ONE_HUNDRED_TGAS};
+
+#[tokio::test]
+async fn near_merkle_drop() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let root = worker.root_account().unwrap();
+
+    let (contract, creator, alice) = init(&root, INITIAL_CONTRACT_BALANCE).await?;
+
+    let recipients: Vec<(AccountId, NearToken)> = vec![
+        (alice.id().clone(), NearToken::from_near(1)),
+        (creator.id().clone(), NearToken::from_millinear(500)),
+        (root.id().clone(), NearToken::from_millinear(100)),
+    ];
+    let leaves: Vec<_> = recipients
+        .iter()
+        .enumerate()
+        .map(|(index, (account_id, amount))| {
+            merkle::airdrop_leaf(index as u32, account_id, *amount)
+        })
+        .collect();
+    let levels = merkle::build_tree(&leaves);
+    let tree_root = Base58CryptoHash::from(levels[levels.len() - 1][0]);
+    let alice_proof: Vec<Base58CryptoHash> = merkle::proof(&levels, 0)
+        .into_iter()
+        .map(Base58CryptoHash::from)
+        .collect();
+
+    let create_result = creator
+        .call(contract.id(), "create_merkle_drop")
+        .args_json(
+            json!({"root": tree_root, "num_leaves": 3, "total": NearToken::from_millinear(1600)}),
+        )
+        .deposit(NearToken::from_millinear(1700))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(create_result.is_success());
+    let drop_id: u32 = create_result.json()?;
+
+    // The amount is part of the leaf, so it cannot be changed
+    let claim_result_1 = creator
+        .call(contract.id(), "claim_merkle")
+        .args_json(json!({"drop_id": drop_id, "account_id": alice.id(), "amount": NearToken::from_near(2), "proof": {"index": 0, "hashes": alice_proof}}))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(claim_result_1.is_failure());
+
+    // Anyone can submit the claim, Alice gets the NEAR
+    let alice_balance_before = get_user_balance(&alice).await;
+    let claim_result_2 = creator
+        .call(contract.id(), "claim_merkle")
+        .args_json(json!({"drop_id": drop_id, "account_id": alice.id(), "amount": NearToken::from_near(1), "proof": {"index": 0, "hashes": alice_proof}}))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(claim_result_2.is_success());
+    assert_eq!(
+        get_user_balance(&alice).await,
+        alice_balance_before.saturating_add(NearToken::from_near(1))
+    );
+
+    let claimed: bool = contract
+        .view(contract.id(), "is_merkle_claimed")
+        .args_json(json!({"drop_id": drop_id, "index": 0}))
+        .await?
+        .json()?;
+    assert!(claimed);
+
+    let claim_result_3 = creator
+        .call(contract.id(), "claim_merkle")
+        .args_json(json!({"drop_id": drop_id, "account_id": alice.id(), "amount": NearToken::from_near(1), "proof": {"index": 0, "hashes": alice_proof}}))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(claim_result_3.is_failure());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn ft_merkle_drop() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let root = worker.root_account().unwrap();
+
+    let (contract, creator, alice) = init(&root, INITIAL_CONTRACT_BALANCE).await?;
+    let ft_contract = init_ft_contract(&worker, &creator).await?;
+
+    for account_id in [contract.id(), alice.id()] {
+        let storage_deposit_result = creator
+            .call(ft_contract.id(), "storage_deposit")
+            .args_json(json!({"account_id": account_id}))
+            .deposit(NearToken::from_yoctonear(12500000000000000000000))
+            .gas(ONE_HUNDRED_TGAS)
+            .transact()
+            .await?;
+        assert!(storage_deposit_result.is_success());
+    }
+
+    let recipients: Vec<(AccountId, NearToken)> = vec![
+        (alice.id().clone(), NearToken::from_yoctonear(100)),
+        (root.id().clone(), NearToken::from_yoctonear(50)),
+    ];
+    let leaves: Vec<_> = recipients
+        .iter()
+        .enumerate()
+        .map(|(index, (account_id, amount))| {
+            merkle::airdrop_leaf(index as u32, account_id, *amount)
+        })
+        .collect();
+    let levels = merkle::build_tree(&leaves);
+    let tree_root = Base58CryptoHash::from(levels[levels.len() - 1][0]);
+    let proofs: Vec<Vec<Base58CryptoHash>> = (0..recipients.len())
+        .map(|index| {
+            merkle::proof(&levels, index)
+                .into_iter()
+                .map(Base58CryptoHash::from)
+                .collect()
+        })
+        .collect();
+
+    // Recipients come from the tree, so claims cannot be gated any further
+    let create_result_1 = creator
+        .call(contract.id(), "create_merkle_drop")
+        .args_json(json!({"root": tree_root, "num_leaves": 2, "total": "150", "ft_contract": ft_contract.id(), "config": {"allowlist": {"Accounts": [alice.id()]}}}))
+        .deposit(NearToken::from_millinear(100))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(format!("{:?}", create_result_1.into_result().unwrap_err())
+        .contains("ERR_UNSUPPORTED_CONFIG"));
+
+    let create_result_2 = creator
+        .call(contract.id(), "create_merkle_drop")
+        .args_json(json!({"root": tree_root, "num_leaves": 2, "total": "150", "ft_contract": ft_contract.id()}))
+        .deposit(NearToken::from_millinear(100))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(create_result_2.is_success());
+    let drop_id: u32 = create_result_2.json()?;
+
+    // Nothing can be claimed before the tokens are sent
+    let claim_result_1 = creator
+        .call(contract.id(), "claim_merkle")
+        .args_json(json!({"drop_id": drop_id, "account_id": alice.id(), "amount": "100", "proof": {"index": 0, "hashes": proofs[0]}}))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(format!("{:?}", claim_result_1.into_result().unwrap_err()).contains("ERR_NOT_FUNDED"));
+
+    let ft_transfer_result = creator
+        .call(ft_contract.id(), "ft_transfer_call")
+        .args_json(
+            json!({"receiver_id": contract.id(), "amount": "150", "msg": drop_id.to_string()}),
+        )
+        .deposit(NearToken::from_yoctonear(1))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(ft_transfer_result.is_success());
+
+    let claim_result_2 = creator
+        .call(contract.id(), "claim_merkle")
+        .args_json(json!({"drop_id": drop_id, "account_id": alice.id(), "amount": "100", "proof": {"index": 0, "hashes": proofs[0]}}))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(claim_result_2.is_success());
+
+    let alice_ft_balance = ft_contract
+        .call("ft_balance_of")
+        .args_json((alice.id(),))
+        .view()
+        .await?
+        .json::<NearToken>()?;
+    assert_eq!(alice_ft_balance, NearToken::from_yoctonear(100));
+
+    // Only the funder gets back what is not claimed
+    let reclaim_result_1 = alice
+        .call(contract.id(), "reclaim_merkle_drop")
+        .args_json(json!({"drop_id": drop_id}))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(format!("{:?}", reclaim_result_1.into_result().unwrap_err()).contains("ERR_NOT_FUNDER"));
+
+    let creator_ft_balance_before = ft_contract
+        .call("ft_balance_of")
+        .args_json((creator.id(),))
+        .view()
+        .await?
+        .json::<NearToken>()?;
+    let reclaim_result_2 = creator
+        .call(contract.id(), "reclaim_merkle_drop")
+        .args_json(json!({"drop_id": drop_id}))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    let reclaimed: bool = reclaim_result_2.json()?;
+    assert!(reclaimed);
+
+    let creator_ft_balance = ft_contract
+        .call("ft_balance_of")
+        .args_json((creator.id(),))
+        .view()
+        .await?
+        .json::<NearToken>()?;
+    assert_eq!(
+        creator_ft_balance,
+        creator_ft_balance_before.saturating_add(NearToken::from_yoctonear(50))
+    );
+
+    // The rest of the tree can no longer be claimed
+    let claim_result_3 = creator
+        .call(contract.id(), "claim_merkle")
+        .args_json(json!({"drop_id": drop_id, "account_id": root.id(), "amount": "50", "proof": {"index": 1, "hashes": proofs[1]}}))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(
+        format!("{:?}", claim_result_3.into_result().unwrap_err()).contains("ERR_NOT_ENOUGH_LEFT")
+    );
+
+    Ok(())
+}
@@ -1,3 +1,11 @@
+mod admin;
+mod eligibility;
+mod fees;
 mod ft;
+mod liabilities;
+mod merkle;
 mod near;
 mod nft;
+mod poap;
+mod staking;
+mod vesting;
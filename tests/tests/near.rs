@@ -1,3 +1,5 @@
+use near_drops::merkle;
+use near_sdk::json_types::Base58CryptoHash;
 use near_sdk::AccountId;
 use near_sdk::{serde_json::json, NearToken};
 use near_workspaces::types::{KeyType, SecretKey};
@@ -112,8 +114,9 @@ async fn drop_on_existing_account() -> anyhow::Result<()> {
         .args_json(json!({"account_id": alice.id()}))
         .gas(ONE_HUNDRED_TGAS)
         .transact()
-        .await?;
-    assert!(claim_result_3.is_failure());
+        .await;
+    // The key is deleted after claiming, so the transaction may be rejected altogether
+    assert!(!claim_result_3.is_ok_and(|result| result.is_success()));
 
     let get_drop_result_2 = creator
         .call(contract.id(), "get_drop_by_id")
@@ -190,8 +193,9 @@ async fn drop_on_new_account() -> anyhow::Result<()> {
         .args_json(json!({"account_id": alice.id()}))
         .gas(ONE_HUNDRED_TGAS)
         .transact()
-        .await?;
-    assert!(claim_result_2.is_failure());
+        .await;
+    // The key is deleted after claiming, so the transaction may be rejected altogether
+    assert!(!claim_result_2.is_ok_and(|result| result.is_success()));
 
     // Ideally there should be no surplus in the contract
     assert!(contract_balance_after.ge(&contract_balance_before));
@@ -205,3 +209,813 @@ async fn drop_on_new_account() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn drop_on_new_account_requires_new_key() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let root = worker.root_account().unwrap();
+
+    let (contract, creator, _) = init(&root, INITIAL_CONTRACT_BALANCE).await?;
+
+    let amount_per_drop = NearToken::from_near(1);
+
+    // Generate the linkdrop key and the key the claimer wants to keep
+    let secret_key = SecretKey::from_random(KeyType::ED25519);
+    let new_secret_key = SecretKey::from_random(KeyType::ED25519);
+    let public_keys = vec![secret_key.public_key()];
+
+    // Creator initiates a call to create a NEAR drop which requires a new key
+    let create_near_drop_result = creator
+        .call(contract.id(), "create_near_drop")
+        .args_json(json!({"public_keys": public_keys, "amount_per_drop": amount_per_drop, "config": {"require_new_key": true}}))
+        .deposit(NearToken::from_millinear(2810))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(create_near_drop_result.is_success());
+
+    let claimer: Account =
+        Account::from_secret_key(contract.id().clone(), secret_key.clone(), &worker);
+    let new_account_id: AccountId = "claimer.test.near".parse().unwrap();
+
+    // Claiming without a new key should fail
+    let claim_result_1 = claimer
+        .call(contract.id(), "create_account_and_claim")
+        .args_json(json!({"account_id": new_account_id}))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(claim_result_1.is_failure());
+
+    // Claiming with the linkdrop key as the new key should fail
+    let claim_result_2 = claimer
+        .call(contract.id(), "create_account_and_claim")
+        .args_json(json!({"account_id": new_account_id, "new_public_key": secret_key.public_key()}))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(claim_result_2.is_failure());
+
+    let claim_result_3 = claimer
+        .call(contract.id(), "create_account_and_claim")
+        .args_json(
+            json!({"account_id": new_account_id, "new_public_key": new_secret_key.public_key()}),
+        )
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(claim_result_3.is_success());
+
+    // The new account is controlled by the key supplied by the claimer
+    let new_account = Account::from_secret_key(new_account_id.clone(), new_secret_key, &worker);
+    assert_eq!(get_user_balance(&new_account).await, amount_per_drop);
+
+    let access_key = worker
+        .view_access_key(&new_account_id, &secret_key.public_key())
+        .await;
+    assert!(access_key.is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn drop_with_allowlist_and_claim_limit() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let root = worker.root_account().unwrap();
+
+    let (contract, creator, alice) = init(&root, INITIAL_CONTRACT_BALANCE).await?;
+
+    // Only Alice can claim, and only one of the two keys
+    let secret_key_1 = SecretKey::from_random(KeyType::ED25519);
+    let secret_key_2 = SecretKey::from_random(KeyType::ED25519);
+    let create_result = creator
+        .call(contract.id(), "create_near_drop")
+        .args_json(json!({
+            "public_keys": [secret_key_1.public_key(), secret_key_2.public_key()],
+            "amount_per_drop": NearToken::from_near(1),
+            "config": {"allowlist": {"Accounts": [alice.id()]}, "max_claims_per_account": 1}
+        }))
+        .deposit(NearToken::from_millinear(2100))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(create_result.is_success());
+
+    let claimer_1: Account = Account::from_secret_key(contract.id().clone(), secret_key_1, &worker);
+    let claimer_2: Account = Account::from_secret_key(contract.id().clone(), secret_key_2, &worker);
+
+    // The creator is not listed
+    let claim_result_1 = claimer_1
+        .call(contract.id(), "claim_for")
+        .args_json(json!({"account_id": creator.id()}))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(claim_result_1.is_failure());
+
+    let claim_result_2 = claimer_1
+        .call(contract.id(), "claim_for")
+        .args_json(json!({"account_id": alice.id()}))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(claim_result_2.is_success());
+
+    // Alice already used her claim
+    let claim_result_3 = claimer_2
+        .call(contract.id(), "claim_for")
+        .args_json(json!({"account_id": alice.id()}))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(claim_result_3.is_failure());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn drop_with_merkle_allowlist() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let root = worker.root_account().unwrap();
+
+    let (contract, creator, alice) = init(&root, INITIAL_CONTRACT_BALANCE).await?;
+
+    // Three accounts are listed, so one node of the tree has no pair
+    let listed: Vec<AccountId> = vec![
+        root.id().clone(),
+        alice.id().clone(),
+        "carol.test.near".parse()?,
+    ];
+    let leaves: Vec<_> = listed.iter().map(merkle::allowlist_leaf).collect();
+    let levels = merkle::build_sorted_tree(&leaves);
+    let allowlist_root = Base58CryptoHash::from(levels[levels.len() - 1][0]);
+    let alice_proof: Vec<Base58CryptoHash> = merkle::sorted_proof(&levels, 1)
+        .into_iter()
+        .map(Base58CryptoHash::from)
+        .collect();
+
+    let secret_key = SecretKey::from_random(KeyType::ED25519);
+    let create_result = creator
+        .call(contract.id(), "create_near_drop")
+        .args_json(json!({
+            "public_keys": [secret_key.public_key()],
+            "amount_per_drop": NearToken::from_near(1),
+            "config": {"allowlist": {"MerkleRoot": allowlist_root}}
+        }))
+        .deposit(NearToken::from_millinear(1100))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(create_result.is_success());
+
+    let claimer: Account = Account::from_secret_key(contract.id().clone(), secret_key, &worker);
+
+    // The proof only fits the leaf of Alice
+    let claim_result_1 = claimer
+        .call(contract.id(), "claim_for")
+        .args_json(json!({"account_id": creator.id(), "proof": alice_proof}))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(format!("{:?}", claim_result_1.into_result().unwrap_err()).contains("ERR_NOT_LISTED"));
+
+    let claim_result_2 = claimer
+        .call(contract.id(), "claim_for")
+        .args_json(json!({"account_id": alice.id()}))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(format!("{:?}", claim_result_2.into_result().unwrap_err()).contains("ERR_NOT_LISTED"));
+
+    let alice_balance_before = get_user_balance(&alice).await;
+    let claim_result_3 = claimer
+        .call(contract.id(), "claim_for")
+        .args_json(json!({"account_id": alice.id(), "proof": alice_proof}))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(claim_result_3.is_success());
+    assert_eq!(
+        get_user_balance(&alice).await,
+        alice_balance_before.saturating_add(NearToken::from_near(1))
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn claim_limit_with_longest_account_id() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let root = worker.root_account().unwrap();
+
+    let (contract, creator, _) = init(&root, INITIAL_CONTRACT_BALANCE).await?;
+
+    let name = "a".repeat(64 - root.id().len() - 1);
+    let claimer_account = root.create_subaccount(&name).transact().await?.unwrap();
+    assert_eq!(claimer_account.id().len(), 64);
+
+    // Each key pays upfront for the count of the claims of its recipient
+    let amount_per_drop = NearToken::from_near(1);
+    let config = json!({"max_claims_per_account": 1});
+    let breakdown: serde_json::Value = contract
+        .view(contract.id(), "get_required_deposit_near_drop")
+        .args_json(json!({"num_keys": 2, "amount_per_drop": amount_per_drop, "config": config, "funder": creator.id()}))
+        .await?
+        .json()?;
+    assert_eq!(
+        breakdown["claim_storage"],
+        json!(NearToken::from_yoctonear(2 * 130 * 10u128.pow(19)))
+    );
+    let total: NearToken = serde_json::from_value(breakdown["total"].clone())?;
+
+    let secret_key_1 = SecretKey::from_random(KeyType::ED25519);
+    let secret_key_2 = SecretKey::from_random(KeyType::ED25519);
+    let create_result = creator
+        .call(contract.id(), "create_near_drop")
+        .args_json(json!({
+            "public_keys": [secret_key_1.public_key(), secret_key_2.public_key()],
+            "amount_per_drop": amount_per_drop,
+            "config": config
+        }))
+        .deposit(total)
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(create_result.is_success());
+
+    // The count takes more storage than the claim frees, it comes out of what the key paid and
+    // the rest is refunded to the funder along with the storage of the key
+    let creator_balance = get_user_balance(&creator).await;
+    let claimer_1: Account = Account::from_secret_key(contract.id().clone(), secret_key_1, &worker);
+    let claim_result_1 = claimer_1
+        .call(contract.id(), "claim_for")
+        .args_json(json!({"account_id": claimer_account.id()}))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(claim_result_1.is_success());
+
+    let refund = get_user_balance(&creator)
+        .await
+        .saturating_sub(creator_balance);
+    assert!(refund > NearToken::from_millinear(1));
+
+    let claimer_2: Account = Account::from_secret_key(contract.id().clone(), secret_key_2, &worker);
+    let claim_result_2 = claimer_2
+        .call(contract.id(), "claim_for")
+        .args_json(json!({"account_id": claimer_account.id()}))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(format!("{:?}", claim_result_2.into_result().unwrap_err())
+        .contains("ERR_CLAIM_LIMIT_REACHED"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn drop_metadata() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let root = worker.root_account().unwrap();
+
+    let (contract, creator, alice) = init(&root, INITIAL_CONTRACT_BALANCE).await?;
+
+    let secret_key = SecretKey::from_random(KeyType::ED25519);
+    let create_result = creator
+        .call(contract.id(), "create_near_drop")
+        .args_json(json!({"public_keys": [secret_key.public_key()], "amount_per_drop": NearToken::from_near(1), "config": {"metadata": {"title": "Welcome"}}}))
+        .deposit(NearToken::from_millinear(1100))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(create_result.is_success());
+    let drop_id: u32 = create_result.json()?;
+
+    let drop: serde_json::Value = contract
+        .view(contract.id(), "get_drop_by_id")
+        .args_json(json!({"drop_id": drop_id}))
+        .await?
+        .json()?;
+    assert_eq!(drop["NEAR"]["config"]["metadata"]["title"], "Welcome");
+
+    // Only the funder can update the metadata, paying for the storage it adds
+    let metadata = json!({"title": "Welcome", "media": "https://example.com/welcome.png"});
+    let update_result_1 = alice
+        .call(contract.id(), "set_drop_metadata")
+        .args_json(json!({"drop_id": drop_id, "metadata": metadata}))
+        .deposit(NearToken::from_millinear(10))
+        .transact()
+        .await?;
+    assert!(update_result_1.is_failure());
+
+    let update_result_2 = creator
+        .call(contract.id(), "set_drop_metadata")
+        .args_json(json!({"drop_id": drop_id, "metadata": metadata}))
+        .transact()
+        .await?;
+    assert!(update_result_2.is_failure());
+
+    let update_result_3 = creator
+        .call(contract.id(), "set_drop_metadata")
+        .args_json(json!({"drop_id": drop_id, "metadata": metadata}))
+        .deposit(NearToken::from_millinear(10))
+        .transact()
+        .await?;
+    assert!(update_result_3.is_success());
+
+    let drop: serde_json::Value = contract
+        .view(contract.id(), "get_drop_by_id")
+        .args_json(json!({"drop_id": drop_id}))
+        .await?
+        .json()?;
+    assert_eq!(
+        drop["NEAR"]["config"]["metadata"]["media"],
+        "https://example.com/welcome.png"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn drop_ownership_transfer() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let root = worker.root_account().unwrap();
+
+    let (contract, creator, alice) = init(&root, INITIAL_CONTRACT_BALANCE).await?;
+
+    let secret_key = SecretKey::from_random(KeyType::ED25519);
+    let create_result = creator
+        .call(contract.id(), "create_near_drop")
+        .args_json(json!({"public_keys": [secret_key.public_key()], "amount_per_drop": NearToken::from_near(1)}))
+        .deposit(NearToken::from_millinear(1100))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(create_result.is_success());
+    let drop_id: u32 = create_result.json()?;
+
+    // Offered to Alice, who has to accept it
+    let transfer_result = creator
+        .call(contract.id(), "transfer_drop_ownership")
+        .args_json(json!({"drop_id": drop_id, "new_owner": alice.id(), "require_accept": true}))
+        .deposit(NearToken::from_millinear(10))
+        .transact()
+        .await?;
+    assert!(transfer_result.is_success());
+
+    let pending_owner: Option<AccountId> = contract
+        .view(contract.id(), "get_pending_drop_owner")
+        .args_json(json!({"drop_id": drop_id}))
+        .await?
+        .json()?;
+    assert_eq!(pending_owner.as_ref(), Some(alice.id()));
+
+    let accept_result_1 = creator
+        .call(contract.id(), "accept_drop_ownership")
+        .args_json(json!({"drop_id": drop_id}))
+        .transact()
+        .await?;
+    assert!(accept_result_1.is_failure());
+
+    let accept_result_2 = alice
+        .call(contract.id(), "accept_drop_ownership")
+        .args_json(json!({"drop_id": drop_id}))
+        .transact()
+        .await?;
+    assert!(accept_result_2.is_success());
+
+    let drop: serde_json::Value = contract
+        .view(contract.id(), "get_drop_by_id")
+        .args_json(json!({"drop_id": drop_id}))
+        .await?
+        .json()?;
+    assert_eq!(drop["NEAR"]["funder"], alice.id().to_string());
+
+    // The previous owner cannot hand it over anymore
+    let transfer_result_2 = creator
+        .call(contract.id(), "transfer_drop_ownership")
+        .args_json(json!({"drop_id": drop_id, "new_owner": creator.id()}))
+        .transact()
+        .await?;
+    let error = format!("{:?}", transfer_result_2.into_result().unwrap_err());
+    assert!(error.contains("ERR_NOT_FUNDER"));
+
+    let transfer_result_3 = alice
+        .call(contract.id(), "transfer_drop_ownership")
+        .args_json(json!({"drop_id": drop_id, "new_owner": creator.id()}))
+        .transact()
+        .await?;
+    assert!(transfer_result_3.is_success());
+
+    let drop: serde_json::Value = contract
+        .view(contract.id(), "get_drop_by_id")
+        .args_json(json!({"drop_id": drop_id}))
+        .await?
+        .json()?;
+    assert_eq!(drop["NEAR"]["funder"], creator.id().to_string());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn paused_drop() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let root = worker.root_account().unwrap();
+
+    let (contract, creator, alice) = init(&root, INITIAL_CONTRACT_BALANCE).await?;
+
+    let secret_key = SecretKey::from_random(KeyType::ED25519);
+    let create_result = creator
+        .call(contract.id(), "create_near_drop")
+        .args_json(json!({"public_keys": [secret_key.public_key()], "amount_per_drop": NearToken::from_near(1)}))
+        .deposit(NearToken::from_millinear(1100))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(create_result.is_success());
+    let drop_id: u32 = create_result.json()?;
+
+    // Only the funder can pause the drop
+    let pause_result_1 = alice
+        .call(contract.id(), "pause_drop")
+        .args_json(json!({"drop_id": drop_id}))
+        .deposit(NearToken::from_millinear(10))
+        .transact()
+        .await?;
+    assert!(pause_result_1.is_failure());
+
+    let pause_result_2 = creator
+        .call(contract.id(), "pause_drop")
+        .args_json(json!({"drop_id": drop_id}))
+        .deposit(NearToken::from_millinear(10))
+        .transact()
+        .await?;
+    assert!(pause_result_2.is_success());
+
+    let claimer = Account::from_secret_key(contract.id().clone(), secret_key, &worker);
+    let claim_result_1 = claimer
+        .call(contract.id(), "claim_for")
+        .args_json(json!({"account_id": alice.id()}))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    // Errors start with a stable code
+    let error = format!("{:?}", claim_result_1.into_result().unwrap_err());
+    assert!(error.contains("ERR_DROP_PAUSED"));
+
+    let resume_result = creator
+        .call(contract.id(), "resume_drop")
+        .args_json(json!({"drop_id": drop_id}))
+        .transact()
+        .await?;
+    assert!(resume_result.is_success());
+
+    // The key was kept during the pause
+    let alice_balance_before = get_user_balance(&alice).await;
+    let claim_result_2 = claimer
+        .call(contract.id(), "claim_for")
+        .args_json(json!({"account_id": alice.id()}))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(claim_result_2.is_success());
+    assert_eq!(
+        get_user_balance(&alice).await,
+        alice_balance_before.saturating_add(NearToken::from_near(1))
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn claim_preview() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let root = worker.root_account().unwrap();
+
+    let (contract, creator, alice) = init(&root, INITIAL_CONTRACT_BALANCE).await?;
+
+    let amount_per_drop = NearToken::from_near(1);
+    let secret_key = SecretKey::from_random(KeyType::ED25519);
+    let create_result = creator
+        .call(contract.id(), "create_near_drop")
+        .args_json(
+            json!({"public_keys": [secret_key.public_key()], "amount_per_drop": amount_per_drop}),
+        )
+        .deposit(NearToken::from_millinear(1100))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(create_result.is_success());
+    let drop_id: u32 = create_result.json()?;
+
+    let preview: serde_json::Value = contract
+        .view(contract.id(), "preview_claim")
+        .args_json(json!({"public_key": secret_key.public_key(), "account_id": alice.id()}))
+        .await?
+        .json()?;
+    assert_eq!(preview["claimable"], true);
+    assert_eq!(preview["drop_id"], drop_id);
+    assert_eq!(
+        preview["asset"]["NEAR"]["amount"],
+        amount_per_drop.as_yoctonear().to_string()
+    );
+    assert_eq!(preview["creates_account"], false);
+
+    let unknown_key = SecretKey::from_random(KeyType::ED25519);
+    let check: serde_json::Value = contract
+        .view(contract.id(), "can_claim")
+        .args_json(json!({"public_key": unknown_key.public_key()}))
+        .await?
+        .json()?;
+    assert_eq!(check["claimable"], false);
+    assert_eq!(check["error"]["code"], "ERR_NO_DROP_FOR_KEY");
+
+    let pause_result = creator
+        .call(contract.id(), "pause_drop")
+        .args_json(json!({"drop_id": drop_id}))
+        .deposit(NearToken::from_millinear(10))
+        .transact()
+        .await?;
+    assert!(pause_result.is_success());
+
+    let check: serde_json::Value = contract
+        .view(contract.id(), "can_claim")
+        .args_json(json!({"public_key": secret_key.public_key()}))
+        .await?
+        .json()?;
+    assert_eq!(check["claimable"], false);
+    assert_eq!(check["error"]["code"], "ERR_DROP_PAUSED");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn required_deposit_estimate() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let root = worker.root_account().unwrap();
+
+    let (contract, creator, _) = init(&root, INITIAL_CONTRACT_BALANCE).await?;
+
+    let amount_per_drop = NearToken::from_near(1);
+    let public_keys = vec![
+        SecretKey::from_random(KeyType::ED25519).public_key(),
+        SecretKey::from_random(KeyType::ED25519).public_key(),
+    ];
+
+    let breakdown: serde_json::Value = contract
+        .view(contract.id(), "get_required_deposit_near_drop")
+        .args_json(
+            json!({"num_keys": 2, "amount_per_drop": amount_per_drop, "funder": creator.id()}),
+        )
+        .await?
+        .json()?;
+    assert_eq!(
+        breakdown["amounts"],
+        NearToken::from_near(2).as_yoctonear().to_string()
+    );
+    let total: NearToken = serde_json::from_value(breakdown["total"].clone())?;
+
+    // The estimate is exactly what creating the drop asks for
+    let short_result = creator
+        .call(contract.id(), "create_near_drop")
+        .args_json(json!({"public_keys": public_keys, "amount_per_drop": amount_per_drop}))
+        .deposit(total.saturating_sub(NearToken::from_yoctonear(1)))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(format!("{:?}", short_result.into_result().unwrap_err())
+        .contains("ERR_INSUFFICIENT_DEPOSIT"));
+
+    let create_result = creator
+        .call(contract.id(), "create_near_drop")
+        .args_json(json!({"public_keys": public_keys, "amount_per_drop": amount_per_drop}))
+        .deposit(total)
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(create_result.is_success());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn claim_log() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let root = worker.root_account().unwrap();
+
+    let (contract, creator, alice) = init(&root, INITIAL_CONTRACT_BALANCE).await?;
+
+    let amount_per_drop = NearToken::from_near(1);
+    let secret_keys = [
+        SecretKey::from_random(KeyType::ED25519),
+        SecretKey::from_random(KeyType::ED25519),
+    ];
+    let public_keys: Vec<_> = secret_keys.iter().map(SecretKey::public_key).collect();
+
+    // The estimate covers the record of the log along with its entries
+    let breakdown: serde_json::Value = contract
+        .view(contract.id(), "get_required_deposit_near_drop")
+        .args_json(json!({"num_keys": 2, "amount_per_drop": amount_per_drop, "config": {"claim_log": true}, "funder": creator.id()}))
+        .await?
+        .json()?;
+    let total: NearToken = serde_json::from_value(breakdown["total"].clone())?;
+
+    let create_result = creator
+        .call(contract.id(), "create_near_drop")
+        .args_json(json!({"public_keys": public_keys, "amount_per_drop": amount_per_drop, "config": {"claim_log": true}}))
+        .deposit(total)
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(create_result.is_success());
+    let drop_id: u32 = create_result.json()?;
+
+    // The log stays as long as the drop has keys to claim
+    let delete_result = creator
+        .call(contract.id(), "delete_claim_log")
+        .args_json(json!({"drop_id": drop_id}))
+        .transact()
+        .await?;
+    assert!(
+        format!("{:?}", delete_result.into_result().unwrap_err()).contains("ERR_DROP_NOT_CLAIMED")
+    );
+
+    for secret_key in secret_keys {
+        let claimer = Account::from_secret_key(contract.id().clone(), secret_key, &worker);
+        let claim_result = claimer
+            .call(contract.id(), "claim_for")
+            .args_json(json!({"account_id": alice.id()}))
+            .gas(ONE_HUNDRED_TGAS)
+            .transact()
+            .await?;
+        assert!(claim_result.is_success());
+    }
+
+    // The log outlives the drop, which is claimed out
+    let entries: serde_json::Value = contract
+        .view(contract.id(), "get_claim_log_entries")
+        .args_json(json!({"drop_id": drop_id}))
+        .await?
+        .json()?;
+    let entries = entries.as_array().unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0]["account_id"], alice.id().to_string());
+    assert_eq!(entries[0]["status"], "Succeeded");
+    assert_eq!(
+        entries[1]["asset"]["NEAR"]["amount"],
+        amount_per_drop.as_yoctonear().to_string()
+    );
+
+    let page: serde_json::Value = contract
+        .view(contract.id(), "get_claim_log_entries")
+        .args_json(json!({"drop_id": drop_id, "from_index": 1, "limit": 5}))
+        .await?
+        .json()?;
+    assert_eq!(page.as_array().unwrap().len(), 1);
+    assert_eq!(page[0]["index"], 1);
+
+    // Only the funder sets the retention
+    let alice_result = alice
+        .call(contract.id(), "set_claim_log_retention")
+        .args_json(json!({"drop_id": drop_id, "retention": 1}))
+        .deposit(NearToken::from_millinear(1))
+        .transact()
+        .await?;
+    assert!(format!("{:?}", alice_result.into_result().unwrap_err()).contains("ERR_NOT_FUNDER"));
+
+    let retention_result = creator
+        .call(contract.id(), "set_claim_log_retention")
+        .args_json(json!({"drop_id": drop_id, "retention": 1}))
+        .deposit(NearToken::from_millinear(1))
+        .transact()
+        .await?;
+    assert!(retention_result.is_success());
+
+    let entries: serde_json::Value = contract
+        .view(contract.id(), "get_claim_log_entries")
+        .args_json(json!({"drop_id": drop_id}))
+        .await?
+        .json()?;
+    assert_eq!(entries.as_array().unwrap().len(), 1);
+    assert_eq!(entries[0]["index"], 1);
+
+    // The funder deletes the log of the claimed out drop
+    let delete_result = creator
+        .call(contract.id(), "delete_claim_log")
+        .args_json(json!({"drop_id": drop_id}))
+        .transact()
+        .await?;
+    assert!(delete_result.is_success());
+
+    let claim_log: Option<serde_json::Value> = contract
+        .view(contract.id(), "get_claim_log")
+        .args_json(json!({"drop_id": drop_id}))
+        .await?
+        .json()?;
+    assert!(claim_log.is_none());
+
+    let entries: serde_json::Value = contract
+        .view(contract.id(), "get_claim_log_entries")
+        .args_json(json!({"drop_id": drop_id}))
+        .await?
+        .json()?;
+    assert!(entries.as_array().unwrap().is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn batched_creation() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let root = worker.root_account().unwrap();
+
+    let (contract, creator, alice) = init(&root, INITIAL_CONTRACT_BALANCE).await?;
+
+    let amount_per_drop = NearToken::from_near(1);
+    let create_result = creator
+        .call(contract.id(), "create_near_drop_batched")
+        .args_json(json!({"capacity": 3, "amount_per_drop": amount_per_drop}))
+        .deposit(NearToken::from_millinear(3200))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(create_result.is_success());
+    let drop_id: u32 = create_result.json()?;
+
+    let secret_keys = [
+        SecretKey::from_random(KeyType::ED25519),
+        SecretKey::from_random(KeyType::ED25519),
+    ];
+    let public_keys: Vec<_> = secret_keys.iter().map(SecretKey::public_key).collect();
+
+    // Sending the same batch again does not add its keys twice
+    for _ in 0..2 {
+        let batch_result = creator
+            .call(contract.id(), "add_keys_batch")
+            .args_json(json!({"drop_id": drop_id, "public_keys": public_keys}))
+            .gas(ONE_HUNDRED_TGAS)
+            .transact()
+            .await?;
+        assert!(batch_result.is_success());
+        let num_keys: u32 = batch_result.json()?;
+        assert_eq!(num_keys, 2);
+    }
+
+    let too_many_keys: Vec<_> = (0..2)
+        .map(|_| SecretKey::from_random(KeyType::ED25519).public_key())
+        .collect();
+    let too_many_result = creator
+        .call(contract.id(), "add_keys_batch")
+        .args_json(json!({"drop_id": drop_id, "public_keys": too_many_keys}))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(format!("{:?}", too_many_result.into_result().unwrap_err())
+        .contains("ERR_CAPACITY_EXCEEDED"));
+
+    let claimer = Account::from_secret_key(contract.id().clone(), secret_keys[0].clone(), &worker);
+    let early_claim_result = claimer
+        .call(contract.id(), "claim_for")
+        .args_json(json!({"account_id": alice.id()}))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(
+        format!("{:?}", early_claim_result.into_result().unwrap_err())
+            .contains("ERR_DROP_NOT_FINALIZED")
+    );
+
+    let creator_balance_before = get_user_balance(&creator).await;
+    let finalize_result = creator
+        .call(contract.id(), "finalize_drop")
+        .args_json(json!({"drop_id": drop_id}))
+        .transact()
+        .await?;
+    assert!(finalize_result.is_success());
+
+    // The unused key is refunded, which is more than the gas of the call
+    let creator_balance_after = get_user_balance(&creator).await;
+    assert!(creator_balance_after > creator_balance_before.saturating_add(NearToken::from_near(1)));
+
+    let drop: serde_json::Value = contract
+        .view(contract.id(), "get_drop_by_id")
+        .args_json(json!({"drop_id": drop_id}))
+        .await?
+        .json()?;
+    assert_eq!(drop["NEAR"]["counter"], 2);
+
+    let alice_balance_before = get_user_balance(&alice).await;
+    let claim_result = claimer
+        .call(contract.id(), "claim_for")
+        .args_json(json!({"account_id": alice.id()}))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(claim_result.is_success());
+    assert_eq!(
+        get_user_balance(&alice).await,
+        alice_balance_before.saturating_add(amount_per_drop)
+    );
+
+    Ok(())
+}
@@ -174,8 +174,9 @@ async fn drop_on_new_account() -> anyhow::Result<()> {
         .args_json(json!({"account_id": alice.id()}))
         .gas(ONE_HUNDRED_TGAS)
         .transact()
-        .await?;
-    assert!(claim_result_2.is_failure());
+        .await;
+    // The key is deleted after claiming, so the transaction may be rejected altogether
+    assert!(!claim_result_2.is_ok_and(|result| result.is_success()));
 
     let get_drop_result_2 = creator
         .call(contract.id(), "get_drop_by_id")
@@ -186,3 +187,66 @@ async fn drop_on_new_account() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn nft_mint_drop() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let root = worker.root_account().unwrap();
+
+    let (contract, creator, alice) = init(&root, INITIAL_CONTRACT_BALANCE).await?;
+
+    // The drop contract owns the NFT contract, which makes it the minter
+    let nft_wasm = near_workspaces::compile_project("./tests/contracts/nft").await?;
+    let nft_contract = worker.dev_deploy(&nft_wasm).await?;
+    let new_result = nft_contract
+        .call("new_default_meta")
+        .args_json(json!({"owner_id": contract.id()}))
+        .transact()
+        .await?;
+    assert!(new_result.is_success());
+
+    let secret_key_1 = SecretKey::from_random(KeyType::ED25519);
+    let secret_key_2 = SecretKey::from_random(KeyType::ED25519);
+
+    let create_result = creator
+        .call(contract.id(), "create_nft_mint_drop")
+        .args_json(json!({
+            "public_keys": [secret_key_1.public_key(), secret_key_2.public_key()],
+            "nft_contract": nft_contract.id(),
+            "metadata": {"title": "Ticket #{serial}", "media": "https://example.com/ticket.png"},
+            "mint_deposit": NearToken::from_millinear(10),
+        }))
+        .deposit(NearToken::from_millinear(100))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(create_result.is_success());
+    let drop_id: u32 = create_result.json()?;
+
+    for secret_key in [secret_key_1, secret_key_2] {
+        let claimer = Account::from_secret_key(contract.id().clone(), secret_key, &worker);
+        let claim_result = claimer
+            .call(contract.id(), "claim_for")
+            .args_json(json!({"account_id": alice.id()}))
+            .gas(ONE_HUNDRED_TGAS)
+            .transact()
+            .await?;
+        assert!(claim_result.is_success());
+    }
+
+    let alice_nfts = nft_contract
+        .call("nft_tokens_for_owner")
+        .args_json(json!({"account_id": alice.id()}))
+        .view()
+        .await?
+        .json::<Vec<Token>>()?;
+
+    assert_eq!(alice_nfts.len(), 2);
+    assert_eq!(alice_nfts[0].token_id, format!("{drop_id}:1"));
+    assert_eq!(
+        alice_nfts[1].metadata.as_ref().unwrap().title.as_deref(),
+        Some("Ticket #2")
+    );
+
+    Ok(())
+}
@@ -0,0 +1,112 @@
+use near_sdk::{serde_json::json, NearToken};
+use near_workspaces::types::{KeyType, SecretKey};
+use near_workspaces::Account;
+
+use crate::init::init;
+use crate::utils::{INITIAL_CONTRACT_BALANCE, ONE_HUNDRED_TGAS};
+
+#[tokio::test]
+async fn poap_drop() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let root = worker.root_account().unwrap();
+
+    let (contract, creator, alice) = init(&root, INITIAL_CONTRACT_BALANCE).await?;
+
+    let secret_key_1 = SecretKey::from_random(KeyType::ED25519);
+    let secret_key_2 = SecretKey::from_random(KeyType::ED25519);
+
+    let create_result = creator
+        .call(contract.id(), "create_poap_drop")
+        .args_json(json!({"public_keys": [secret_key_1.public_key(), secret_key_2.public_key()], "event": {"title": "Meetup"}}))
+        .deposit(NearToken::from_millinear(100))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(create_result.is_success());
+    let drop_id: u32 = create_result.json()?;
+
+    let claimer_1 = Account::from_secret_key(contract.id().clone(), secret_key_1, &worker);
+    let claim_result_1 = claimer_1
+        .call(contract.id(), "claim_for")
+        .args_json(json!({"account_id": alice.id()}))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(claim_result_1.is_success());
+
+    let owned: bool = contract
+        .view(contract.id(), "is_sbt_owner")
+        .args_json(json!({"class": drop_id, "account": alice.id()}))
+        .await?
+        .json()?;
+    assert!(owned);
+
+    // Tokens are grouped by issuer, as NEP-393 registries list them
+    let tokens: Vec<(String, Vec<serde_json::Value>)> = contract
+        .view(contract.id(), "sbt_tokens_by_owner")
+        .args_json(json!({"account": alice.id(), "with_expired": false}))
+        .await?
+        .json()?;
+    assert_eq!(tokens.len(), 1);
+    assert_eq!(tokens[0].0, contract.id().as_str());
+    assert_eq!(tokens[0].1.len(), 1);
+    assert_eq!(tokens[0].1[0]["metadata"]["class"], drop_id);
+
+    // No tokens from other issuers or from later classes
+    for args in [
+        json!({"account": alice.id(), "ctr": creator.id(), "with_expired": false}),
+        json!({"account": alice.id(), "from_class": drop_id + 1, "with_expired": false}),
+    ] {
+        let tokens: Vec<(String, Vec<serde_json::Value>)> = contract
+            .view(contract.id(), "sbt_tokens_by_owner")
+            .args_json(args)
+            .await?
+            .json()?;
+        assert!(tokens.is_empty());
+    }
+
+    // Alice cannot attend the same event twice
+    let claimer_2 = Account::from_secret_key(contract.id().clone(), secret_key_2, &worker);
+    let claim_result_2 = claimer_2
+        .call(contract.id(), "claim_for")
+        .args_json(json!({"account_id": alice.id()}))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(claim_result_2.is_failure());
+
+    let supply: u64 = contract.view(contract.id(), "sbt_supply").await?.json()?;
+    assert_eq!(supply, 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn poap_drop_after_event() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let root = worker.root_account().unwrap();
+
+    let (contract, creator, alice) = init(&root, INITIAL_CONTRACT_BALANCE).await?;
+
+    let secret_key = SecretKey::from_random(KeyType::ED25519);
+
+    let create_result = creator
+        .call(contract.id(), "create_poap_drop")
+        .args_json(json!({"public_keys": [secret_key.public_key()], "event": {"title": "Meetup"}, "ends_at": "1"}))
+        .deposit(NearToken::from_millinear(100))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(create_result.is_success());
+
+    let claimer = Account::from_secret_key(contract.id().clone(), secret_key, &worker);
+    let claim_result = claimer
+        .call(contract.id(), "claim_for")
+        .args_json(json!({"account_id": alice.id()}))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(claim_result.is_failure());
+
+    Ok(())
+}
@@ -0,0 +1,315 @@
+use near_sdk::{serde_json::json, Gas, NearToken};
+use near_workspaces::network::Sandbox;
+use near_workspaces::types::{KeyType, SecretKey};
+use near_workspaces::{Account, Contract, DevNetwork, Worker};
+
+use crate::init::{init, init_staking_pool};
+use crate::utils::{get_user_balance, INITIAL_CONTRACT_BALANCE, ONE_HUNDRED_TGAS};
+
+#[tokio::test]
+async fn staking_drop_falls_back_to_transfer() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let root = worker.root_account().unwrap();
+
+    let (contract, creator, alice) = init(&root, INITIAL_CONTRACT_BALANCE).await?;
+
+    let amount_per_drop = NearToken::from_near(1);
+    let secret_key = SecretKey::from_random(KeyType::ED25519);
+
+    // The pool has no contract, so staking fails
+    let staking_pool = root
+        .create_subaccount("pool")
+        .initial_balance(NearToken::from_near(1))
+        .transact()
+        .await?
+        .into_result()?;
+
+    let create_result = creator
+        .call(contract.id(), "create_staking_drop")
+        .args_json(json!({"public_keys": [secret_key.public_key()], "staking_pool": staking_pool.id(), "amount_per_drop": amount_per_drop}))
+        .deposit(NearToken::from_millinear(1100))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(create_result.is_success());
+
+    let alice_balance_before = get_user_balance(&alice).await;
+    let claimer = Account::from_secret_key(contract.id().clone(), secret_key, &worker);
+    let claim_result = claimer
+        .call(contract.id(), "claim_for")
+        .args_json(json!({"account_id": alice.id()}))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(claim_result.is_success());
+
+    // Alice gets the NEAR directly and holds no stake
+    assert_eq!(
+        get_user_balance(&alice).await,
+        alice_balance_before.saturating_add(amount_per_drop)
+    );
+    let delegation: Option<serde_json::Value> = contract
+        .view(contract.id(), "get_delegation")
+        .args_json(json!({"staking_pool": staking_pool.id(), "account_id": alice.id()}))
+        .await?
+        .json()?;
+    assert!(delegation.is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn staking_drop_unstakes_and_withdraws() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let root = worker.root_account().unwrap();
+
+    let (contract, creator, alice) = init(&root, INITIAL_CONTRACT_BALANCE).await?;
+    let staking_pool = init_staking_pool(&worker).await?;
+
+    let amount_per_drop = NearToken::from_near(1);
+    let secret_key = SecretKey::from_random(KeyType::ED25519);
+    create_staking_drop(&creator, &contract, &staking_pool, vec![secret_key.clone()]).await?;
+    claim_for(&worker, &contract, secret_key, &alice).await?;
+
+    // The claim is staked by the contract and recorded for alice
+    let staked_balance: String = staking_pool
+        .view("get_account_staked_balance")
+        .args_json(json!({"account_id": contract.id()}))
+        .await?
+        .json()?;
+    assert_eq!(staked_balance, amount_per_drop.as_yoctonear().to_string());
+    let delegation = get_delegation(&contract, &staking_pool, &alice).await?;
+    assert_eq!(
+        delegation["principal"],
+        amount_per_drop.as_yoctonear().to_string()
+    );
+
+    let unstake_result = alice
+        .call(contract.id(), "unstake_delegation")
+        .args_json(json!({"staking_pool": staking_pool.id()}))
+        .gas(THREE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(unstake_result.is_success());
+
+    let delegation = get_delegation(&contract, &staking_pool, &alice).await?;
+    assert_eq!(delegation["principal"], "0");
+    assert_eq!(
+        delegation["unstaked"],
+        amount_per_drop.as_yoctonear().to_string()
+    );
+
+    // The pool has not released it yet
+    let withdraw_result_1 = alice
+        .call(contract.id(), "withdraw_delegation")
+        .args_json(json!({"staking_pool": staking_pool.id()}))
+        .gas(THREE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(
+        format!("{:?}", withdraw_result_1.into_result().unwrap_err())
+            .contains("ERR_UNSTAKE_LOCKED")
+    );
+
+    let unlocks_at = get_unlocks_at(&contract, &staking_pool).await?;
+    fast_forward_to_epoch(&worker, &staking_pool, unlocks_at).await?;
+
+    let alice_balance_before = get_user_balance(&alice).await;
+    let withdraw_result_2 = alice
+        .call(contract.id(), "withdraw_delegation")
+        .args_json(json!({"staking_pool": staking_pool.id()}))
+        .gas(THREE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(withdraw_result_2.is_success());
+
+    // Alice gets the NEAR, less the gas she paid, and the delegation is gone
+    assert!(
+        get_user_balance(&alice).await
+            > alice_balance_before.saturating_add(NearToken::from_millinear(950))
+    );
+    let delegation: Option<serde_json::Value> = contract
+        .view(contract.id(), "get_delegation")
+        .args_json(json!({"staking_pool": staking_pool.id(), "account_id": alice.id()}))
+        .await?
+        .json()?;
+    assert!(delegation.is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn staking_drop_unstake_does_not_relock_other_delegators() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let root = worker.root_account().unwrap();
+
+    let (contract, creator, alice) = init(&root, INITIAL_CONTRACT_BALANCE).await?;
+    let bob = root.create_subaccount("bob").transact().await?.unwrap();
+    let staking_pool = init_staking_pool(&worker).await?;
+
+    let secret_key_1 = SecretKey::from_random(KeyType::ED25519);
+    let secret_key_2 = SecretKey::from_random(KeyType::ED25519);
+    create_staking_drop(
+        &creator,
+        &contract,
+        &staking_pool,
+        vec![secret_key_1.clone(), secret_key_2.clone()],
+    )
+    .await?;
+    claim_for(&worker, &contract, secret_key_1, &alice).await?;
+    claim_for(&worker, &contract, secret_key_2, &bob).await?;
+
+    let unstake_result_1 = alice
+        .call(contract.id(), "unstake_delegation")
+        .args_json(json!({"staking_pool": staking_pool.id()}))
+        .gas(THREE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(unstake_result_1.is_success());
+    let alice_unlocks_at = get_unlocks_at(&contract, &staking_pool).await?;
+
+    // Bob unstakes while alice waits, his NEAR is queued instead of locking hers again
+    fast_forward_to_epoch(&worker, &staking_pool, alice_unlocks_at - 2).await?;
+    let unstake_result_2 = bob
+        .call(contract.id(), "unstake_delegation")
+        .args_json(json!({"staking_pool": staking_pool.id()}))
+        .gas(THREE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(unstake_result_2.is_success());
+
+    let pool_unstaking: serde_json::Value = contract
+        .view(contract.id(), "get_pool_unstaking")
+        .args_json(json!({"staking_pool": staking_pool.id()}))
+        .await?
+        .json()?;
+    assert_eq!(
+        pool_unstaking["queued"],
+        NearToken::from_near(1).as_yoctonear().to_string()
+    );
+    assert_eq!(pool_unstaking["unlocks_at"], alice_unlocks_at);
+
+    // Alice withdraws when her NEAR unlocks, which sends the batch of bob
+    fast_forward_to_epoch(&worker, &staking_pool, alice_unlocks_at).await?;
+    let alice_balance_before = get_user_balance(&alice).await;
+    let withdraw_result_1 = alice
+        .call(contract.id(), "withdraw_delegation")
+        .args_json(json!({"staking_pool": staking_pool.id()}))
+        .gas(THREE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(withdraw_result_1.is_success());
+    assert!(
+        get_user_balance(&alice).await
+            > alice_balance_before.saturating_add(NearToken::from_millinear(950))
+    );
+
+    let withdraw_result_2 = bob
+        .call(contract.id(), "withdraw_delegation")
+        .args_json(json!({"staking_pool": staking_pool.id()}))
+        .gas(THREE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(
+        format!("{:?}", withdraw_result_2.into_result().unwrap_err())
+            .contains("ERR_UNSTAKE_LOCKED")
+    );
+
+    // Bob withdraws once his own batch unlocks
+    let bob_unlocks_at = get_unlocks_at(&contract, &staking_pool).await?;
+    assert!(bob_unlocks_at > alice_unlocks_at);
+    fast_forward_to_epoch(&worker, &staking_pool, bob_unlocks_at).await?;
+
+    let bob_balance_before = get_user_balance(&bob).await;
+    let withdraw_result_3 = bob
+        .call(contract.id(), "withdraw_delegation")
+        .args_json(json!({"staking_pool": staking_pool.id()}))
+        .gas(THREE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(withdraw_result_3.is_success());
+    assert!(
+        get_user_balance(&bob).await
+            > bob_balance_before.saturating_add(NearToken::from_millinear(950))
+    );
+
+    Ok(())
+}
+
+async fn create_staking_drop(
+    creator: &Account,
+    contract: &Account,
+    staking_pool: &Contract,
+    secret_keys: Vec<SecretKey>,
+) -> anyhow::Result<()> {
+    let public_keys: Vec<_> = secret_keys.iter().map(|key| key.public_key()).collect();
+    let deposit = NearToken::from_millinear(1100).saturating_mul(public_keys.len() as u128);
+
+    let create_result = creator
+        .call(contract.id(), "create_staking_drop")
+        .args_json(json!({"public_keys": public_keys, "staking_pool": staking_pool.id(), "amount_per_drop": NearToken::from_near(1)}))
+        .deposit(deposit)
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(create_result.is_success());
+
+    Ok(())
+}
+
+async fn claim_for(
+    worker: &Worker<impl DevNetwork>,
+    contract: &Account,
+    secret_key: SecretKey,
+    account: &Account,
+) -> anyhow::Result<()> {
+    let claimer = Account::from_secret_key(contract.id().clone(), secret_key, worker);
+    let claim_result = claimer
+        .call(contract.id(), "claim_for")
+        .args_json(json!({"account_id": account.id()}))
+        .gas(THREE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(claim_result.is_success());
+
+    Ok(())
+}
+
+async fn get_delegation(
+    contract: &Account,
+    staking_pool: &Contract,
+    account: &Account,
+) -> anyhow::Result<serde_json::Value> {
+    Ok(contract
+        .view(contract.id(), "get_delegation")
+        .args_json(json!({"staking_pool": staking_pool.id(), "account_id": account.id()}))
+        .await?
+        .json()?)
+}
+
+async fn get_unlocks_at(contract: &Account, staking_pool: &Contract) -> anyhow::Result<u64> {
+    let pool_unstaking: serde_json::Value = contract
+        .view(contract.id(), "get_pool_unstaking")
+        .args_json(json!({"staking_pool": staking_pool.id()}))
+        .await?
+        .json()?;
+
+    Ok(pool_unstaking["unlocks_at"].as_u64().unwrap())
+}
+
+// Produces blocks until the chain reaches `epoch_height`
+async fn fast_forward_to_epoch(
+    worker: &Worker<Sandbox>,
+    staking_pool: &Contract,
+    epoch_height: u64,
+) -> anyhow::Result<()> {
+    loop {
+        let current: u64 = staking_pool.view("get_epoch_height").await?.json()?;
+        if current >= epoch_height {
+            return Ok(());
+        }
+        worker.fast_forward(100).await?;
+    }
+}
+
+const THREE_HUNDRED_TGAS: Gas = Gas::from_tgas(300);
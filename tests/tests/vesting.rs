@@ -0,0 +1,276 @@
+use near_sdk::{serde_json::json, Gas, NearToken};
+use near_workspaces::types::{KeyType, SecretKey};
+use near_workspaces::Account;
+
+use crate::init::{init, init_ft_contract};
+use crate::utils::{get_user_balance, INITIAL_CONTRACT_BALANCE, ONE_HUNDRED_TGAS};
+
+#[tokio::test]
+async fn near_vesting_drop() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let root = worker.root_account().unwrap();
+
+    let (contract, creator, alice) = init(&root, INITIAL_CONTRACT_BALANCE).await?;
+
+    let amount_per_drop = NearToken::from_near(1);
+    let secret_key_1 = SecretKey::from_random(KeyType::ED25519);
+    let secret_key_2 = SecretKey::from_random(KeyType::ED25519);
+
+    // A cliff far in the future, then a schedule which is over after a second
+    let schedules = [
+        (
+            secret_key_1.clone(),
+            json!({"cliff_seconds": 1_000_000, "duration_seconds": 1_000_000}),
+        ),
+        (
+            secret_key_2.clone(),
+            json!({"cliff_seconds": 0, "duration_seconds": 1}),
+        ),
+    ];
+
+    let mut drop_ids = vec![];
+    for (secret_key, vesting) in schedules {
+        let create_result = creator
+            .call(contract.id(), "create_near_drop")
+            .args_json(json!({"public_keys": [secret_key.public_key()], "amount_per_drop": amount_per_drop, "config": {"vesting": vesting}}))
+            .deposit(NearToken::from_millinear(1100))
+            .gas(ONE_HUNDRED_TGAS)
+            .transact()
+            .await?;
+        assert!(create_result.is_success());
+        drop_ids.push(create_result.json::<u32>()?);
+
+        // Claiming registers Alice for the schedule instead of paying her
+        let alice_balance_before = get_user_balance(&alice).await;
+        let claimer = Account::from_secret_key(contract.id().clone(), secret_key, &worker);
+        let claim_result = claimer
+            .call(contract.id(), "claim_for")
+            .args_json(json!({"account_id": alice.id()}))
+            .gas(ONE_HUNDRED_TGAS)
+            .transact()
+            .await?;
+        assert!(claim_result.is_success());
+        assert_eq!(get_user_balance(&alice).await, alice_balance_before);
+    }
+
+    let grant: Option<serde_json::Value> = contract
+        .view(contract.id(), "get_vesting_grant")
+        .args_json(json!({"drop_id": drop_ids[0], "account_id": alice.id()}))
+        .await?
+        .json()?;
+    assert!(grant.is_some());
+
+    let withdraw_result_1 = alice
+        .call(contract.id(), "withdraw_vested")
+        .args_json(json!({"drop_id": drop_ids[0]}))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(withdraw_result_1.is_failure());
+
+    worker.fast_forward(10).await?;
+
+    let withdrawable: NearToken = contract
+        .view(contract.id(), "get_withdrawable")
+        .args_json(json!({"drop_id": drop_ids[1], "account_id": alice.id()}))
+        .await?
+        .json()?;
+    assert_eq!(withdrawable, amount_per_drop);
+
+    let alice_balance_before = get_user_balance(&alice).await;
+    let withdraw_result_2 = alice
+        .call(contract.id(), "withdraw_vested")
+        .args_json(json!({"drop_id": drop_ids[1]}))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(withdraw_result_2.is_success());
+    assert!(
+        get_user_balance(&alice).await
+            > alice_balance_before.saturating_add(NearToken::from_millinear(990))
+    );
+
+    // Fully withdrawn grants are removed
+    let grant: Option<serde_json::Value> = contract
+        .view(contract.id(), "get_vesting_grant")
+        .args_json(json!({"drop_id": drop_ids[1], "account_id": alice.id()}))
+        .await?
+        .json()?;
+    assert!(grant.is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn vesting_refunds_follow_drop_owner() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let root = worker.root_account().unwrap();
+
+    let (contract, creator, alice) = init(&root, INITIAL_CONTRACT_BALANCE).await?;
+    let bob = root.create_subaccount("bob").transact().await?.unwrap();
+
+    let secret_key_1 = SecretKey::from_random(KeyType::ED25519);
+    let secret_key_2 = SecretKey::from_random(KeyType::ED25519);
+    let create_result = creator
+        .call(contract.id(), "create_near_drop")
+        .args_json(json!({
+            "public_keys": [secret_key_1.public_key(), secret_key_2.public_key()],
+            "amount_per_drop": NearToken::from_near(1),
+            "config": {"vesting": {"cliff_seconds": 0, "duration_seconds": 1}}
+        }))
+        .deposit(NearToken::from_millinear(2100))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(create_result.is_success());
+    let drop_id: u32 = create_result.json()?;
+
+    let claimer = Account::from_secret_key(contract.id().clone(), secret_key_1, &worker);
+    let claim_result = claimer
+        .call(contract.id(), "claim_for")
+        .args_json(json!({"account_id": alice.id()}))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(claim_result.is_success());
+
+    // The grant was made before the transfer, its storage still goes to the new owner
+    let transfer_result = creator
+        .call(contract.id(), "transfer_drop_ownership")
+        .args_json(json!({"drop_id": drop_id, "new_owner": bob.id()}))
+        .transact()
+        .await?;
+    assert!(transfer_result.is_success());
+
+    worker.fast_forward(10).await?;
+
+    let creator_balance = get_user_balance(&creator).await;
+    let bob_balance = get_user_balance(&bob).await;
+    let withdraw_result = alice
+        .call(contract.id(), "withdraw_vested")
+        .args_json(json!({"drop_id": drop_id}))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(withdraw_result.is_success());
+
+    assert_eq!(get_user_balance(&creator).await, creator_balance);
+    assert!(get_user_balance(&bob).await > bob_balance);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn ft_vesting_drop() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let root = worker.root_account().unwrap();
+
+    let (contract, creator, alice) = init(&root, INITIAL_CONTRACT_BALANCE).await?;
+    let ft_contract = init_ft_contract(&worker, &creator).await?;
+
+    let storage_deposit_result = creator
+        .call(ft_contract.id(), "storage_deposit")
+        .args_json(json!({"account_id": contract.id()}))
+        .deposit(NearToken::from_yoctonear(12500000000000000000000))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(storage_deposit_result.is_success());
+
+    // Released over a long time, so every withdrawal has something new to send
+    let secret_key = SecretKey::from_random(KeyType::ED25519);
+    let create_result = creator
+        .call(contract.id(), "create_ft_drop")
+        .args_json(json!({
+            "public_keys": [secret_key.public_key()],
+            "ft_contract": ft_contract.id(),
+            "amount_per_drop": "1000000000",
+            "config": {"vesting": {"cliff_seconds": 0, "duration_seconds": 1_000_000}}
+        }))
+        .deposit(NearToken::from_near(1))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(create_result.is_success());
+    let drop_id: u32 = create_result.json()?;
+
+    let ft_transfer_result = creator
+        .call(ft_contract.id(), "ft_transfer_call")
+        .args_json(json!({"receiver_id": contract.id(), "amount": "1000000000", "msg": drop_id.to_string()}))
+        .deposit(NearToken::from_yoctonear(1))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(ft_transfer_result.is_success());
+
+    let claimer = Account::from_secret_key(contract.id().clone(), secret_key, &worker);
+    let claim_result = claimer
+        .call(contract.id(), "claim_for")
+        .args_json(json!({"account_id": alice.id()}))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(claim_result.is_success());
+
+    worker.fast_forward(10).await?;
+
+    // Alice is not registered with the FT contract, the first withdrawal registers her with
+    // the deposit the grant holds
+    let withdraw_result_1 = alice
+        .call(contract.id(), "withdraw_vested")
+        .args_json(json!({"drop_id": drop_id}))
+        .gas(Gas::from_tgas(300))
+        .transact()
+        .await?;
+    let withdrawn: bool = withdraw_result_1.json()?;
+    assert!(withdrawn);
+
+    let alice_ft_balance = ft_contract
+        .call("ft_balance_of")
+        .args_json((alice.id(),))
+        .view()
+        .await?
+        .json::<NearToken>()?;
+    assert!(!alice_ft_balance.is_zero());
+
+    let grant: serde_json::Value = contract
+        .view(contract.id(), "get_vesting_grant")
+        .args_json(json!({"drop_id": drop_id, "account_id": alice.id()}))
+        .await?
+        .json()?;
+    assert_eq!(grant["registration"], "0");
+    assert_eq!(
+        grant["withdrawn"],
+        alice_ft_balance.as_yoctonear().to_string()
+    );
+
+    // Once Alice unregisters, transfers to her fail and go back into the grant
+    let unregister_result = alice
+        .call(ft_contract.id(), "storage_unregister")
+        .args_json(json!({"force": true}))
+        .deposit(NearToken::from_yoctonear(1))
+        .gas(ONE_HUNDRED_TGAS)
+        .transact()
+        .await?;
+    assert!(unregister_result.is_success());
+
+    worker.fast_forward(10).await?;
+
+    let withdraw_result_2 = alice
+        .call(contract.id(), "withdraw_vested")
+        .args_json(json!({"drop_id": drop_id}))
+        .gas(Gas::from_tgas(300))
+        .transact()
+        .await?;
+    let withdrawn: bool = withdraw_result_2.json()?;
+    assert!(!withdrawn);
+
+    let grant_after: serde_json::Value = contract
+        .view(contract.id(), "get_vesting_grant")
+        .args_json(json!({"drop_id": drop_id, "account_id": alice.id()}))
+        .await?
+        .json()?;
+    assert_eq!(grant_after["withdrawn"], grant["withdrawn"]);
+
+    Ok(())
+}